@@ -0,0 +1,85 @@
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+/// Challenge as served by `GET /v1/site/{siteId}/challenge`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Challenge {
+    pub id: Uuid,
+    #[serde(deserialize_with = "deserialize_prefixes")]
+    pub prefixes: Vec<Vec<u8>>,
+    pub difficulty: u8,
+    #[serde(rename = "prefixesToSolve")]
+    pub prefixes_to_solve: usize,
+    #[serde(rename = "solutionLength")]
+    pub solution_length: usize,
+    /// Unix timestamp in seconds
+    #[serde(rename = "expiresAt")]
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution(Vec<u8>);
+
+impl Solution {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Serialize for Solution {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&BASE64_STANDARD.encode(&self.0))
+    }
+}
+
+fn deserialize_prefixes<'de, D>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|prefix| {
+            BASE64_STANDARD
+                .decode(prefix)
+                .map_err(|_| de::Error::custom("could not base64 decode prefix"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Challenge, Solution};
+
+    #[test]
+    fn test_deserialize() {
+        let testee = r#"{
+            "id": "26439bc6-80a6-4533-8e9b-df12f768c464",
+            "prefixes": ["W6BG0YkK+Xs=", "ipIeIQvAVqg="],
+            "difficulty": 4,
+            "prefixesToSolve": 1,
+            "solutionLength": 8,
+            "expiresAt": 1792372035
+        }"#;
+
+        let challenge: Challenge = serde_json::from_str(testee).expect("Failed parsing json");
+
+        assert_eq!(challenge.prefixes.len(), 2);
+        assert_eq!(challenge.prefixes[0].len(), 8);
+        assert_eq!(challenge.prefixes_to_solve, 1);
+    }
+
+    #[test]
+    fn test_serialize_solution() {
+        let solution = Solution::new(vec![0xd8, 0x5a, 0xe0]);
+
+        assert_eq!(serde_json::to_string(&solution).unwrap(), r#""2Frg""#);
+    }
+}
@@ -0,0 +1,321 @@
+use std::time::SystemTime;
+
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Request, Response,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{Challenge, Error, Solution};
+
+const API_KEY_HEADER: &str = "api-key";
+const TIMESTAMP_HEADER: &str = "x-oxidecaptcha-timestamp";
+const NONCE_HEADER: &str = "x-oxidecaptcha-nonce";
+const SIGNATURE_HEADER: &str = "x-oxidecaptcha-signature";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Validation {
+    pub valid: bool,
+    /// Only issued for sites with a `passToken` config
+    #[serde(rename = "passToken")]
+    pub pass_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    id: String,
+    context: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SubmitBody<'a> {
+    solutions: &'a [Option<Solution>],
+}
+
+/// Client for the `/v1` api of an oxidecaptcha server
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    signing_secret: Option<Vec<u8>>,
+}
+
+impl Client {
+    /// `base_url` is the root the server is reachable at, without `/v1`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+
+        Self {
+            http: reqwest::Client::new(),
+            base_url,
+            api_key: None,
+            signing_secret: None,
+        }
+    }
+
+    /// Needed for [`Client::submit`], which calls a backend route
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Signs backend requests for sites with a `requestSigning` config
+    pub fn with_request_signing(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.signing_secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub async fn get_challenge(&self, site_id: &Uuid) -> Result<Challenge, Error> {
+        let response = self
+            .http
+            .get(format!("{}/v1/site/{site_id}/challenge", self.base_url))
+            .send()
+            .await?;
+
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    pub async fn submit(
+        &self,
+        site_id: &Uuid,
+        challenge_id: &Uuid,
+        solutions: &[Option<Solution>],
+    ) -> Result<Validation, Error> {
+        let api_key = self.api_key.as_ref().ok_or(Error::MissingApiKey)?;
+
+        let body = serde_json::to_vec(&SubmitBody { solutions }).expect("Solutions serialize");
+
+        let mut request = self
+            .http
+            .post(format!(
+                "{}/v1/site/{site_id}/challenge/{challenge_id}",
+                self.base_url
+            ))
+            .header(API_KEY_HEADER, api_key)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .build()?;
+
+        self.sign(&mut request);
+
+        let response = self.http.execute(request).await?;
+
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    /// Adds the signature headers if request signing is configured
+    fn sign(&self, request: &mut Request) {
+        let Some(secret) = &self.signing_secret else {
+            return;
+        };
+
+        let path = match request.url().query() {
+            Some(query) => format!("{}?{query}", request.url().path()),
+            None => request.url().path().to_string(),
+        };
+
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .unwrap_or_default();
+
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Duration since failed")
+            .as_secs();
+        let nonce = Uuid::new_v4().simple().to_string();
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("Hmac accepts keys of any size");
+        mac.update(
+            format!(
+                "{}\n{path}\n{timestamp}\n{nonce}\n{}",
+                request.method(),
+                hex(&Sha256::digest(body))
+            )
+            .as_bytes(),
+        );
+        let signature = hex(&mac.finalize().into_bytes());
+
+        let headers = request.headers_mut();
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+        headers.insert(
+            NONCE_HEADER,
+            HeaderValue::from_str(&nonce).expect("Nonce is a valid header"),
+        );
+        headers.insert(
+            SIGNATURE_HEADER,
+            HeaderValue::from_str(&signature).expect("Signature is a valid header"),
+        );
+    }
+
+    async fn check(response: Response) -> Result<Response, Error> {
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let error = response.json::<ApiError>().await.unwrap_or_else(|_| ApiError {
+            id: "Unknown".to_string(),
+            context: status.to_string(),
+        });
+
+        Err(Error::Api {
+            status: status.as_u16(),
+            id: error.id,
+            context: error.context,
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use oxidecaptcha::{
+        auth::RequestSigningConfig,
+        config::{Config, HousekeepingConfig, InMemoryConfig, StorageTypeConfig},
+        site::Site,
+        RouterBuilder,
+    };
+    use uuid::{uuid, Uuid};
+
+    use super::Client;
+    use crate::{Error, Solver};
+
+    const SITE_ID: Uuid = uuid!("60601796-7dc2-4d4f-afae-5728592bba6f");
+
+    const SIGNING_SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    async fn spawn_server() -> String {
+        spawn_server_with(Site::new(
+            SITE_ID,
+            "key".to_string(),
+            4,
+            8,
+            2,
+            8,
+            8,
+            Duration::from_secs(60),
+        ))
+        .await
+    }
+
+    async fn spawn_server_with(site: Site) -> String {
+        let housekeeping = HousekeepingConfig::new(Duration::from_secs(10), 10);
+        let storage = StorageTypeConfig::Memory(InMemoryConfig::new(housekeeping, vec![site]));
+
+        let router = RouterBuilder::new(Config::new(storage))
+            .build()
+            .expect("Unable to build router");
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Unable to bind server");
+        let address = listener.local_addr().expect("No local address");
+
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        format!("http://{address}")
+    }
+
+    #[tokio::test]
+    async fn test_solve_and_submit() {
+        let client = Client::new(spawn_server().await).with_api_key("key");
+
+        let challenge = client.get_challenge(&SITE_ID).await.expect("No challenge");
+
+        let solutions = Solver::new().solve(&challenge).expect("Unable to solve");
+
+        let validation = client
+            .submit(&SITE_ID, &challenge.id, &solutions)
+            .await
+            .expect("Submit failed");
+
+        assert!(validation.valid);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_site() {
+        let client = Client::new(spawn_server().await);
+
+        let error = client
+            .get_challenge(&Uuid::new_v4())
+            .await
+            .expect_err("Got challenge for unknown site");
+
+        assert!(matches!(error, Error::Api { status: 404, ref id, .. } if id == "SiteNotFound"));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_api_key() {
+        let client = Client::new(spawn_server().await).with_api_key("wrong");
+
+        let challenge = client.get_challenge(&SITE_ID).await.expect("No challenge");
+        let solutions = vec![None; challenge.prefixes.len()];
+
+        let error = client
+            .submit(&SITE_ID, &challenge.id, &solutions)
+            .await
+            .expect_err("Submit with wrong api key succeeded");
+
+        assert!(matches!(error, Error::Api { status: 401, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_request_signing() {
+        let site = Site::new(
+            SITE_ID,
+            "key".to_string(),
+            4,
+            8,
+            2,
+            8,
+            8,
+            Duration::from_secs(60),
+        )
+        .with_request_signing(RequestSigningConfig::new(
+            SIGNING_SECRET,
+            Duration::from_secs(60),
+        ));
+        let base_url = spawn_server_with(site).await;
+
+        let unsigned = Client::new(&base_url).with_api_key("key");
+        let challenge = unsigned
+            .get_challenge(&SITE_ID)
+            .await
+            .expect("No challenge");
+        let solutions = Solver::new().solve(&challenge).expect("Unable to solve");
+
+        let error = unsigned
+            .submit(&SITE_ID, &challenge.id, &solutions)
+            .await
+            .expect_err("Unsigned submit succeeded");
+        assert!(
+            matches!(error, Error::Api { status: 401, ref id, .. } if id == "InvalidSignature")
+        );
+
+        let validation = Client::new(&base_url)
+            .with_api_key("key")
+            .with_request_signing(SIGNING_SECRET)
+            .submit(&SITE_ID, &challenge.id, &solutions)
+            .await
+            .expect("Signed submit failed");
+
+        assert!(validation.valid);
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    /// The server answered with an error response
+    Api {
+        status: u16,
+        id: String,
+        context: String,
+    },
+    MissingApiKey,
+    Cancelled,
+    /// Too few prefixes have a solution of the challenge's solution length
+    Unsolvable,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "Http request failed: {e}"),
+            Error::Api {
+                status,
+                id,
+                context,
+            } => write!(f, "Server responded with {status} {id}: {context}"),
+            Error::MissingApiKey => f.write_str("Api-key required to submit solutions"),
+            Error::Cancelled => f.write_str("Solving was cancelled"),
+            Error::Unsolvable => f.write_str("No solution found"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Error::Http(value)
+    }
+}
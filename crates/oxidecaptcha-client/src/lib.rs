@@ -0,0 +1,13 @@
+//! Client for the oxidecaptcha http api and a multi-threaded solver for its
+//! challenges. The hashing is shared with the server through
+//! `oxidecaptcha-core`.
+
+mod challenge;
+mod client;
+mod error;
+mod solver;
+
+pub use challenge::{Challenge, Solution};
+pub use client::{Client, Validation};
+pub use error::Error;
+pub use solver::{CancellationToken, Progress, Solver};
@@ -0,0 +1,290 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use oxidecaptcha_core::PrefixHasher;
+
+use crate::{Challenge, Error, Solution};
+
+/// Attempts between two checks for cancellation or a solution found by
+/// another thread
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Cancels a running [`Solver::solve`] from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub solved: usize,
+    pub required: usize,
+    /// Hashes computed so far over all threads
+    pub hashes: u64,
+}
+
+type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Solves challenges by brute force, splitting the search for each prefix
+/// across threads. Blocks the calling thread, so use `spawn_blocking` or
+/// similar from async code.
+#[derive(Clone)]
+pub struct Solver {
+    threads: usize,
+    cancellation: CancellationToken,
+    progress: Option<ProgressCallback>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver {
+    /// Uses one thread per available cpu
+    pub fn new() -> Self {
+        let threads = thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1);
+
+        Self {
+            threads,
+            cancellation: CancellationToken::new(),
+            progress: None,
+        }
+    }
+
+    pub fn with_threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads.get();
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Called after each solved prefix
+    pub fn on_progress(mut self, callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Returns one entry per prefix of the challenge. Prefixes are solved in
+    /// order until `prefixes_to_solve` are, prefixes without a solution of
+    /// `solution_length` are skipped in favour of the spare ones.
+    pub fn solve(&self, challenge: &Challenge) -> Result<Vec<Option<Solution>>, Error> {
+        let mut solutions = vec![None; challenge.prefixes.len()];
+        let hashes = AtomicU64::new(0);
+        let mut solved = 0;
+
+        for (index, prefix) in challenge.prefixes.iter().enumerate() {
+            if solved == challenge.prefixes_to_solve {
+                break;
+            }
+
+            if challenge.prefixes.len() - index < challenge.prefixes_to_solve - solved {
+                return Err(Error::Unsolvable);
+            }
+
+            let solution = match self.solve_prefix(
+                prefix,
+                challenge.difficulty,
+                challenge.solution_length,
+                &hashes,
+            ) {
+                Ok(solution) => solution,
+                Err(Error::Unsolvable) => continue,
+                Err(e) => return Err(e),
+            };
+
+            solutions[index] = Some(solution);
+            solved += 1;
+
+            if let Some(progress) = &self.progress {
+                progress(Progress {
+                    solved,
+                    required: challenge.prefixes_to_solve,
+                    hashes: hashes.load(Ordering::Relaxed),
+                });
+            }
+        }
+
+        if solved < challenge.prefixes_to_solve {
+            return Err(Error::Unsolvable);
+        }
+
+        Ok(solutions)
+    }
+
+    fn solve_prefix(
+        &self,
+        prefix: &[u8],
+        difficulty: u8,
+        solution_length: usize,
+        hashes: &AtomicU64,
+    ) -> Result<Solution, Error> {
+        let hasher = PrefixHasher::new(prefix);
+        let found = Mutex::new(None);
+        let done = AtomicBool::new(false);
+
+        // Solutions shorter than a counter limit the search space
+        let counter_bytes = solution_length.min(8);
+        let max_counter = match counter_bytes {
+            8 => u64::MAX,
+            n => (1u64 << (8 * n)) - 1,
+        };
+
+        thread::scope(|scope| {
+            for start in 0..self.threads as u64 {
+                let hasher = &hasher;
+                let found = &found;
+                let done = &done;
+                let step = self.threads as u64;
+
+                scope.spawn(move || {
+                    let mut solution = vec![0u8; solution_length];
+                    let mut counter = start;
+                    let mut attempts = 0u64;
+
+                    while counter <= max_counter {
+                        solution[..counter_bytes]
+                            .copy_from_slice(&counter.to_le_bytes()[..counter_bytes]);
+
+                        attempts += 1;
+
+                        if hasher.is_valid(&solution, difficulty) {
+                            done.store(true, Ordering::Relaxed);
+                            found.lock().expect("Solver mutex poisoned").get_or_insert(solution);
+                            break;
+                        }
+
+                        if attempts.is_multiple_of(CHECK_INTERVAL)
+                            && (done.load(Ordering::Relaxed) || self.cancellation.is_cancelled())
+                        {
+                            break;
+                        }
+
+                        counter = match counter.checked_add(step) {
+                            Some(v) => v,
+                            None => break,
+                        };
+                    }
+
+                    hashes.fetch_add(attempts, Ordering::Relaxed);
+                });
+            }
+        });
+
+        match found.into_inner().expect("Solver mutex poisoned") {
+            Some(solution) => Ok(Solution::new(solution)),
+            None if self.cancellation.is_cancelled() => Err(Error::Cancelled),
+            None => Err(Error::Unsolvable),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::NonZeroUsize,
+        sync::{Arc, Mutex},
+    };
+
+    use uuid::Uuid;
+
+    use super::{CancellationToken, Solver};
+    use crate::{Challenge, Error};
+
+    fn challenge(difficulty: u8, solution_length: usize) -> Challenge {
+        Challenge {
+            id: Uuid::new_v4(),
+            prefixes: vec![vec![1; 8], vec![2; 8], vec![3; 8], vec![4; 8]],
+            difficulty,
+            prefixes_to_solve: 2,
+            solution_length,
+            expires_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let challenge = challenge(10, 8);
+        let progress = Arc::new(Mutex::new(Vec::new()));
+
+        let reported = progress.clone();
+        let solutions = Solver::new()
+            .with_threads(NonZeroUsize::new(4).unwrap())
+            .on_progress(move |p| reported.lock().unwrap().push(p.solved))
+            .solve(&challenge)
+            .expect("Unable to solve");
+
+        assert_eq!(solutions.len(), 4);
+        assert!(solutions[2].is_none() && solutions[3].is_none());
+
+        for (prefix, solution) in challenge.prefixes.iter().zip(&solutions).take(2) {
+            let solution = solution.as_ref().expect("Prefix not solved");
+
+            assert_eq!(solution.get_bytes().len(), 8);
+            assert!(oxidecaptcha_core::is_valid(prefix, solution.get_bytes(), 10));
+        }
+
+        assert_eq!(*progress.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let result = Solver::new()
+            .with_cancellation(cancellation)
+            .solve(&challenge(64, 8));
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        // One byte solutions leave 256 attempts, not enough for some prefixes
+        let solvable =
+            |prefix: &Vec<u8>| (0..=255u8).any(|b| oxidecaptcha_core::is_valid(prefix, &[b], 7));
+        let prefixes = (0..=255u8).map(|i| vec![i; 8]);
+
+        let unsolvable = prefixes.clone().find(|p| !solvable(p)).expect("No unsolvable prefix");
+        let spares: Vec<Vec<u8>> = prefixes.filter(solvable).take(2).collect();
+
+        let mut challenge = challenge(7, 1);
+        challenge.prefixes = vec![unsolvable.clone(), spares[0].clone(), spares[1].clone()];
+
+        let solutions = Solver::new().solve(&challenge).expect("Spare prefix not used");
+
+        assert!(solutions[0].is_none());
+        assert!(solutions[1].is_some() && solutions[2].is_some());
+
+        challenge.prefixes = vec![unsolvable, spares[0].clone()];
+
+        let result = Solver::new().solve(&challenge);
+
+        assert!(matches!(result, Err(Error::Unsolvable)));
+    }
+}
@@ -0,0 +1,92 @@
+//! Proof-of-work primitives shared by the oxidecaptcha server and its clients.
+//! A solution is valid if `sha256(prefix || solution)` starts with at least
+//! `difficulty` zero bits.
+
+use sha2::{Digest, Sha256};
+
+pub const HASH_LENGTH: usize = 32;
+
+pub fn hash(prefix: &[u8], solution: &[u8]) -> [u8; HASH_LENGTH] {
+    PrefixHasher::new(prefix).hash(solution)
+}
+
+pub fn has_leading_zeros(hash: &[u8; HASH_LENGTH], difficulty: u8) -> bool {
+    let bytes_to_scan = (difficulty / 8) as usize;
+    let bits_to_scan = (difficulty % 8) as u32;
+
+    if hash[..bytes_to_scan].iter().any(|b| *b != 0) {
+        return false;
+    }
+
+    match hash.get(bytes_to_scan) {
+        Some(last_byte) => last_byte.leading_zeros() >= bits_to_scan,
+        None => true,
+    }
+}
+
+pub fn is_valid(prefix: &[u8], solution: &[u8], difficulty: u8) -> bool {
+    has_leading_zeros(&hash(prefix, solution), difficulty)
+}
+
+/// Hashes many solutions for the same prefix, reusing the hasher state after
+/// the prefix was absorbed
+#[derive(Debug, Clone)]
+pub struct PrefixHasher {
+    state: Sha256,
+}
+
+impl PrefixHasher {
+    pub fn new(prefix: &[u8]) -> Self {
+        let mut state = Sha256::new();
+        state.update(prefix);
+
+        Self { state }
+    }
+
+    pub fn hash(&self, solution: &[u8]) -> [u8; HASH_LENGTH] {
+        let mut state = self.state.clone();
+        state.update(solution);
+
+        state.finalize().into()
+    }
+
+    pub fn is_valid(&self, solution: &[u8], difficulty: u8) -> bool {
+        has_leading_zeros(&self.hash(solution), difficulty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex_literal::hex;
+
+    use super::{has_leading_zeros, is_valid, PrefixHasher};
+
+    const PREFIX: [u8; 20] = hex!("12bedfcafb0491a1998f94f4648c494fc384ceec");
+    const SOLUTION: [u8; 12] = hex!("d85ae00d155c6ca8edb4838a");
+
+    #[test]
+    fn test_valid() {
+        assert!(is_valid(&PREFIX, &SOLUTION, 13));
+        assert!(!is_valid(&PREFIX, &SOLUTION, 20));
+    }
+
+    #[test]
+    fn test_prefix_hasher_matches() {
+        let hasher = PrefixHasher::new(&PREFIX);
+
+        assert_eq!(hasher.hash(&SOLUTION), super::hash(&PREFIX, &SOLUTION));
+        assert!(hasher.is_valid(&SOLUTION, 13));
+    }
+
+    #[test]
+    fn test_leading_zeros() {
+        let mut hash = [0xFF; 32];
+        hash[0] = 0;
+        hash[1] = 0b0001_0000;
+
+        assert!(has_leading_zeros(&hash, 8));
+        assert!(has_leading_zeros(&hash, 11));
+        assert!(!has_leading_zeros(&hash, 12));
+        assert!(has_leading_zeros(&[0; 32], 255));
+    }
+}
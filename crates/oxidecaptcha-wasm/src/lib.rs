@@ -0,0 +1,107 @@
+//! Solver exported to JavaScript. Each web worker of the widget owns one
+//! [`PrefixSolver`] and calls [`PrefixSolver::step`] in small batches, so it
+//! can report progress and be terminated between them. Once every solution
+//! the counter can express was tried, [`PrefixSolver::exhausted`] is set.
+//!
+//! Build with `wasm-pack build crates/oxidecaptcha-wasm --target web --out-dir ../../widget/pkg`.
+
+use oxidecaptcha_core::PrefixHasher;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct PrefixSolver {
+    hasher: PrefixHasher,
+    difficulty: u8,
+    solution: Vec<u8>,
+    /// Next counter to try, `None` once all were tried
+    next: Option<u64>,
+    last: u64,
+    tried: u64,
+}
+
+#[wasm_bindgen]
+impl PrefixSolver {
+    #[wasm_bindgen(constructor)]
+    pub fn new(prefix: &[u8], difficulty: u8, solution_length: usize) -> Self {
+        Self {
+            hasher: PrefixHasher::new(prefix),
+            difficulty,
+            solution: vec![0; solution_length],
+            next: Some(0),
+            last: last_counter(solution_length),
+            tried: 0,
+        }
+    }
+
+    /// Tries up to `attempts` solutions, returns the first valid one
+    pub fn step(&mut self, attempts: u32) -> Option<Vec<u8>> {
+        let counter_bytes = self.solution.len().min(8);
+
+        for _ in 0..attempts {
+            let counter = self.next?;
+
+            self.solution[..counter_bytes]
+                .copy_from_slice(&counter.to_le_bytes()[..counter_bytes]);
+            self.next = (counter < self.last).then(|| counter + 1);
+            self.tried += 1;
+
+            if self.hasher.is_valid(&self.solution, self.difficulty) {
+                return Some(self.solution.clone());
+            }
+        }
+
+        None
+    }
+
+    /// Number of solutions tried so far
+    #[wasm_bindgen(getter)]
+    pub fn attempts(&self) -> f64 {
+        self.tried as f64
+    }
+
+    /// Whether all solutions were tried without finding a valid one
+    #[wasm_bindgen(getter)]
+    pub fn exhausted(&self) -> bool {
+        self.next.is_none()
+    }
+}
+
+/// Largest counter that fits into a solution of `solution_length` bytes
+fn last_counter(solution_length: usize) -> u64 {
+    match solution_length {
+        8.. => u64::MAX,
+        bytes => (1u64 << (8 * bytes)) - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefixSolver;
+
+    #[test]
+    fn test_step_finds_solution() {
+        let prefix = [7u8; 8];
+        let mut solver = PrefixSolver::new(&prefix, 8, 8);
+
+        let solution = loop {
+            if let Some(solution) = solver.step(256) {
+                break solution;
+            }
+        };
+
+        assert_eq!(solution.len(), 8);
+        assert!(oxidecaptcha_core::is_valid(&prefix, &solution, 8));
+        assert!(solver.attempts() > 0.0);
+        assert!(!solver.exhausted());
+    }
+
+    #[test]
+    fn test_step_exhausts_short_solutions() {
+        let mut solver = PrefixSolver::new(&[7u8; 8], 255, 1);
+
+        assert_eq!(solver.step(1000), None);
+        assert!(solver.exhausted());
+        assert_eq!(solver.attempts(), 256.0);
+        assert_eq!(solver.step(1000), None);
+    }
+}
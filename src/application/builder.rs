@@ -0,0 +1,229 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use axum::Router;
+
+use crate::{
+    audit::{AuditLog, AuditSink},
+    config::{Config, RouteGroup},
+    guard::Captcha,
+    state::State,
+    storage::{Storage, StorageProvider},
+};
+
+use super::Application;
+
+/// Builds an [`axum::Router`] serving the oxidecaptcha routes, to be mounted
+/// into another axum application
+pub struct RouterBuilder {
+    config: Config,
+    storage: Option<StorageProvider>,
+    routes: BTreeSet<RouteGroup>,
+    audit: AuditLog,
+}
+
+impl RouterBuilder {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            storage: None,
+            routes: RouteGroup::all(),
+            audit: AuditLog::new(),
+        }
+    }
+
+    /// Use `storage` instead of the storage described by the config
+    pub fn storage(mut self, storage: impl Storage + 'static) -> Self {
+        self.storage = Some(StorageProvider::custom(storage));
+        self
+    }
+
+    /// Only serve the given route groups, defaults to all of them
+    pub fn routes(mut self, routes: impl IntoIterator<Item = RouteGroup>) -> Self {
+        self.routes = routes.into_iter().collect();
+        self
+    }
+
+    /// Also send audit entries to `sink`
+    pub fn audit_sink(mut self, sink: impl AuditSink) -> Self {
+        self.audit = self.audit.with_sink(sink);
+        self
+    }
+
+    /// Must be called from within a tokio runtime, as the configured storage
+    /// may spawn background tasks
+    pub fn build(self) -> Result<Router> {
+        self.build_with_captcha().map(|(router, _)| router)
+    }
+
+    /// Like [`RouterBuilder::build`], but also returns a [`Captcha`] handle
+    /// for guarding other routes with the same storage
+    pub fn build_with_captcha(self) -> Result<(Router, Captcha)> {
+        let storage = match self.storage {
+            Some(storage) => storage,
+            None => StorageProvider::new(&self.config)?,
+        };
+
+        let audit = self.audit.with_config(self.config.get_audit())?;
+
+        let state = State::new(self.config, storage, audit);
+        let spec = Application::build_openapi(&state);
+
+        let router = Application::build_router(&state, &self.routes, &spec)?;
+
+        Ok((router, Captcha::new(state)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use crate::{
+        audit::{tests::RecordingSink, AuditAction, AuditOutcome},
+        challenge::Challenge,
+        config::{Config, RouteGroup},
+        site::Site,
+        storage::{Storage, StorageError},
+        test_support::{config, site, SITE_ID},
+    };
+
+    use super::RouterBuilder;
+
+    #[derive(Default)]
+    struct RecordingStorage {
+        stored: Mutex<Vec<Uuid>>,
+    }
+
+    impl Storage for Arc<RecordingStorage> {
+        async fn get_site(&self, id: &Uuid) -> Option<Site> {
+            (*id == SITE_ID).then(site)
+        }
+
+        async fn get_challange(&self, _id: &Uuid, site: &Site) -> Option<Challenge> {
+            Some(site.generate_challenge())
+        }
+
+        async fn store_challenge(
+            &self,
+            _site: &Site,
+            challenge: &Challenge,
+        ) -> Result<(), StorageError> {
+            self.stored.lock().unwrap().push(*challenge.get_id());
+            Ok(())
+        }
+
+        async fn delete_challenge(
+            &self,
+            _site: &Site,
+            _challenge: &Challenge,
+        ) -> Result<(), StorageError> {
+            Err(StorageError::ChallengeNotFound)
+        }
+
+        async fn healthy(&self) -> bool {
+            true
+        }
+    }
+
+    fn test_config() -> Config {
+        config(Vec::new())
+    }
+
+    #[tokio::test]
+    async fn test_custom_storage() {
+        let storage = Arc::new(RecordingStorage::default());
+
+        let router = RouterBuilder::new(Config::without_storage())
+            .storage(storage.clone())
+            .routes([RouteGroup::Public])
+            .build()
+            .expect("Unable to build router");
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(storage.stored.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_route_groups() {
+        let router = RouterBuilder::new(test_config())
+            .routes([RouteGroup::Public])
+            .build()
+            .expect("Unable to build router");
+
+        let request = Request::get("/v1/health").body(Body::empty()).unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_audit_sink() {
+        let sink = RecordingSink::default();
+
+        let router = RouterBuilder::new(test_config())
+            .storage(Arc::new(RecordingStorage::default()))
+            .routes([RouteGroup::Backend])
+            .audit_sink(sink.clone())
+            .build()
+            .expect("Unable to build router");
+
+        let request = Request::delete(format!("/v1/site/{SITE_ID}/challenge/{}", Uuid::new_v4()))
+            .header("api-key", "key")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+
+        let entries = sink.0.lock().unwrap();
+        let actions: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.action, entry.outcome))
+            .collect();
+
+        assert_eq!(
+            actions,
+            [
+                (AuditAction::ConfigLoaded, AuditOutcome::Success),
+                (AuditAction::ApiKeyUsed, AuditOutcome::Success),
+                (AuditAction::ChallengeDeleted, AuditOutcome::Failure),
+            ]
+        );
+        assert!(entries[1..].iter().all(|entry| entry.actor == "apiKey:default"));
+    }
+
+    #[test]
+    fn test_missing_storage() {
+        RouterBuilder::new(Config::without_storage())
+            .build()
+            .expect_err("Built a router without storage");
+    }
+
+    #[tokio::test]
+    async fn test_custom_storage_skips_config_sites() {
+        let sink = RecordingSink::default();
+
+        let _router = RouterBuilder::new(config(vec![site()]))
+            .storage(Arc::new(RecordingStorage::default()))
+            .audit_sink(sink.clone())
+            .build()
+            .expect("Unable to build router");
+
+        let actions: Vec<_> = sink.0.lock().unwrap().iter().map(|entry| entry.action).collect();
+
+        assert_eq!(actions, [AuditAction::ConfigLoaded]);
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    net::SocketAddr,
+    os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use axum::{extract::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server,
+};
+use tokio::net::{TcpListener, UnixListener};
+use tower::Service;
+use tracing::{info, warn};
+
+use crate::config::{ListenAddress, ListenerConfig, RouteGroup, UnixSocketConfig};
+
+#[derive(Debug)]
+enum BoundSocket {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+#[derive(Debug)]
+pub struct Listener {
+    socket: BoundSocket,
+    address: ListenAddress,
+    routes: BTreeSet<RouteGroup>,
+}
+
+impl Listener {
+    pub async fn bind(config: &ListenerConfig) -> Result<Self> {
+        let socket = match &config.address {
+            ListenAddress::Tcp(addr) => {
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("Unable to bind listener {addr}"))?;
+
+                BoundSocket::Tcp(listener)
+            }
+            ListenAddress::Unix(unix) => BoundSocket::Unix(Self::bind_unix(unix)?),
+        };
+
+        Ok(Self {
+            socket,
+            address: config.address.clone(),
+            routes: config.routes.clone(),
+        })
+    }
+
+    /// Binds in a private directory next to the socket and moves the socket
+    /// into place once it has its permissions, so it is never reachable with
+    /// the default ones
+    fn bind_unix(unix: &UnixSocketConfig) -> Result<UnixListener> {
+        let path = &unix.path;
+
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                // Only sockets nobody listens on anymore are replaced
+                match std::os::unix::net::UnixStream::connect(path) {
+                    Ok(_) => bail!("{} is in use by another process", path.display()),
+                    Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                        std::fs::remove_file(path).with_context(|| {
+                            format!("Unable to remove stale socket {}", path.display())
+                        })?;
+                    }
+                    Err(e) => {
+                        return Err(e)
+                            .with_context(|| format!("Unable to probe {}", path.display()))
+                    }
+                }
+            }
+            Ok(_) => bail!("{} exists and is not a socket", path.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Unable to inspect {}", path.display()))
+            }
+        }
+
+        let file_name = path
+            .file_name()
+            .with_context(|| format!("{} has no file name", path.display()))?;
+        let private_dir = path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(format!(".{}.{}", file_name.to_string_lossy(), std::process::id()));
+
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&private_dir)
+            .with_context(|| format!("Unable to create {}", private_dir.display()))?;
+
+        let result = Self::bind_in(&private_dir.join(file_name), unix);
+
+        let _ = std::fs::remove_dir_all(&private_dir);
+
+        result
+    }
+
+    fn bind_in(private_path: &Path, unix: &UnixSocketConfig) -> Result<UnixListener> {
+        let listener = UnixListener::bind(private_path)
+            .with_context(|| format!("Unable to bind listener {}", unix.path.display()))?;
+
+        if let Some(mode) = unix.permissions {
+            let permissions = std::fs::Permissions::from_mode(mode);
+            std::fs::set_permissions(private_path, permissions).with_context(|| {
+                format!("Unable to set permissions on {}", unix.path.display())
+            })?;
+        }
+
+        std::fs::rename(private_path, &unix.path)
+            .with_context(|| format!("Unable to move socket to {}", unix.path.display()))?;
+
+        Ok(listener)
+    }
+
+    pub fn get_routes(&self) -> &BTreeSet<RouteGroup> {
+        &self.routes
+    }
+
+    pub async fn serve(self, router: Router) -> Result<()> {
+        info!("Listening on {} for {:?}", self.address, self.routes);
+
+        match self.socket {
+            BoundSocket::Tcp(listener) => axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .context("Tcp listener failed"),
+            BoundSocket::Unix(listener) => Self::serve_unix(listener, router).await,
+        }
+    }
+
+    async fn serve_unix(listener: UnixListener, router: Router) -> Result<()> {
+        loop {
+            let socket = match listener.accept().await {
+                Ok((socket, _)) => socket,
+                Err(e) => {
+                    Self::handle_accept_error(e).await;
+                    continue;
+                }
+            };
+
+            let socket = TokioIo::new(socket);
+            let router = router.clone();
+
+            tokio::spawn(async move {
+                let service = hyper::service::service_fn(move |request: Request<Incoming>| {
+                    router.clone().call(request)
+                });
+
+                let result = server::conn::auto::Builder::new(TokioExecutor::new())
+                    .serve_connection_with_upgrades(socket, service)
+                    .await;
+
+                if let Err(e) = result {
+                    warn!("Unix connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Same as `axum::serve`: errors of single connections are skipped, others
+    /// like running out of file descriptors are retried after a pause
+    async fn handle_accept_error(e: io::Error) {
+        if matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::ConnectionReset
+        ) {
+            return;
+        }
+
+        warn!("Unable to accept unix connection: {}", e);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, os::unix::fs::PermissionsExt};
+
+    use crate::config::{ListenAddress, ListenerConfig, UnixSocketConfig};
+
+    use super::Listener;
+
+    fn unix_config(path: std::path::PathBuf) -> ListenerConfig {
+        ListenerConfig {
+            address: ListenAddress::Unix(UnixSocketConfig {
+                path,
+                permissions: Some(0o600),
+            }),
+            routes: BTreeSet::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let directory =
+            std::env::temp_dir().join(format!("oxidecaptcha-listener-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("captcha.sock");
+
+        let config = unix_config(path.clone());
+
+        let listener = Listener::bind(&config).await.expect("Unable to bind");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A socket still listened on is not taken over
+        let Err(error) = Listener::bind(&config).await else {
+            panic!("Took over a live socket");
+        };
+        assert!(error.to_string().contains("in use"), "{error}");
+        assert!(path.exists());
+        drop(listener);
+
+        // Stale sockets are replaced
+        Listener::bind(&config).await.expect("Unable to replace stale socket");
+
+        // Anything else is left alone
+        let file = directory.join("data");
+        std::fs::write(&file, "keep").unwrap();
+        Listener::bind(&unix_config(file.clone()))
+            .await
+            .expect_err("Replaced a regular file");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep");
+
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,93 +1,372 @@
+use std::collections::BTreeSet;
+
 use crate::{
-    config::Config,
-    routes::{delete_challange, get_challange, validate_challenges},
+    audit::AuditLog,
+    config::{Config, RouteGroup},
+    openapi::ApiDoc,
+    routes::*,
     state::State,
-    storage::StorageProvider,
+    storage::{Storage, StorageProvider},
 };
-use anyhow::{Context, Result};
+use anyhow::Result;
 use axum::{
-    routing::{delete, get, post},
+    routing::{get, options},
     Router,
 };
-use tokio::net::TcpListener;
+use futures::future::try_join_all;
+use listener::Listener;
+use utoipa::{openapi::OpenApi as OpenApiSpec, OpenApi};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+mod builder;
+mod listener;
+
+pub use builder::RouterBuilder;
 
 pub struct Application {
-    listener: TcpListener,
+    listeners: Vec<Listener>,
     state: State,
 }
 
 impl Application {
-    pub async fn new() -> Result<Self> {
-        let config = Self::parse_config()?;
-        let storage = StorageProvider::new(&config);
+    pub async fn new(config: Config) -> Result<Self> {
+        let storage = StorageProvider::new(&config)?;
+
+        Self::with_storage_provider(config, storage).await
+    }
 
-        let listener = Self::create_listener(&config).await?;
+    pub async fn with_storage(config: Config, storage: impl Storage + 'static) -> Result<Self> {
+        Self::with_storage_provider(config, StorageProvider::custom(storage)).await
+    }
+
+    async fn with_storage_provider(config: Config, storage: StorageProvider) -> Result<Self> {
+        let listeners = Self::create_listeners(&config).await?;
+
+        let audit = AuditLog::from_config(config.get_audit())?;
 
-        let state = State::new(config, storage);
+        let state = State::new(config, storage, audit);
 
-        Ok(Self { listener, state })
+        Ok(Self { listeners, state })
     }
 
     pub async fn run(self) -> Result<()> {
-        let get_site_middleware = axum::middleware::from_fn_with_state(
-            self.state.clone(),
-            crate::middleware::get_site_middleware,
-        );
+        let spec = Self::build_openapi(&self.state);
 
-        let get_challenge_middleware = axum::middleware::from_fn_with_state(
-            self.state.clone(),
-            crate::middleware::get_challenge_middleware,
-        );
+        let mut servers = Vec::with_capacity(self.listeners.len());
+
+        for listener in self.listeners {
+            let router = Self::build_router(&self.state, listener.get_routes(), &spec)?;
+
+            servers.push(listener.serve(router));
+        }
+
+        try_join_all(servers).await?;
+
+        Ok(())
+    }
+
+    fn group_router(state: &State, group: RouteGroup) -> OpenApiRouter {
+        match group {
+            RouteGroup::Public => {
+                let get_site_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::get_site_middleware,
+                );
+
+                let cors_middleware = axum::middleware::from_fn(crate::middleware::cors_middleware);
+
+                OpenApiRouter::new()
+                    .routes(routes!(get_challange))
+                    .routes(routes!(get_challenges_batch))
+                    .route("/site/:siteId/challenge", options(preflight))
+                    .route("/site/:siteId/challenges:batch", options(preflight))
+                    .route_layer(cors_middleware)
+                    .route_layer(get_site_middleware)
+                    .with_state(state.clone())
+            }
+            RouteGroup::Backend => {
+                let get_challenge_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::get_challenge_middleware,
+                );
 
+                let auth_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::auth_middleware,
+                );
+
+                let get_site_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::get_site_middleware,
+                );
+
+                let batch_router = OpenApiRouter::new()
+                    .routes(routes!(validate_challenges_batch))
+                    .route_layer(auth_middleware.clone())
+                    .route_layer(get_site_middleware);
+
+                OpenApiRouter::new()
+                    .routes(routes!(delete_challange, validate_challenges))
+                    .route_layer(auth_middleware)
+                    .route_layer(get_challenge_middleware)
+                    .merge(batch_router)
+                    .with_state(state.clone())
+            }
+            RouteGroup::Admin => OpenApiRouter::new()
+                .routes(routes!(health))
+                .routes(routes!(metrics))
+                .with_state(state.clone()),
+            RouteGroup::Proxy | RouteGroup::Grpc => OpenApiRouter::new(),
+        }
+    }
+
+    /// The unversioned routes served before `/v1`, later endpoints only
+    /// exist under `/v1`
+    fn legacy_router(state: &State, group: RouteGroup) -> Router {
+        match group {
+            RouteGroup::Public => {
+                let get_site_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::get_site_middleware,
+                );
+
+                let cors_middleware = axum::middleware::from_fn(crate::middleware::cors_middleware);
+
+                // Origins are enforced, but preflights are only answered under `/v1`
+                Router::new()
+                    .route(
+                        "/site/:siteId/challenge",
+                        get(get_challange).route_layer(cors_middleware),
+                    )
+                    .route_layer(get_site_middleware)
+                    .with_state(state.clone())
+            }
+            RouteGroup::Backend => {
+                let get_challenge_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::get_challenge_middleware,
+                );
+
+                let auth_middleware = axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    crate::middleware::auth_middleware,
+                );
+
+                Router::new()
+                    .route(
+                        "/site/:siteId/challenge/:challengeId",
+                        axum::routing::delete(delete_challange).post(validate_challenges),
+                    )
+                    .route_layer(auth_middleware)
+                    .route_layer(get_challenge_middleware)
+                    .with_state(state.clone())
+            }
+            RouteGroup::Admin | RouteGroup::Proxy | RouteGroup::Grpc => Router::new(),
+        }
+    }
+
+    fn build_openapi(state: &State) -> OpenApiSpec {
+        let mut spec = RouteGroup::all()
+            .into_iter()
+            .fold(OpenApiRouter::with_openapi(ApiDoc::openapi()), |router, group| {
+                router.nest("/v1", Self::group_router(state, group))
+            })
+            .into_openapi();
+
+        crate::openapi::add_wire_formats(&mut spec);
+
+        spec
+    }
+
+    fn build_router(
+        state: &State,
+        groups: &BTreeSet<RouteGroup>,
+        spec: &OpenApiSpec,
+    ) -> Result<Router> {
         let timeout_middleware = axum::middleware::from_fn(crate::middleware::timeout_middleware);
 
         let logging_middleware = axum::middleware::from_fn(crate::middleware::logging_middleware);
 
-        let auth_middleware = axum::middleware::from_fn(crate::middleware::auth_middleware);
-
-        let site_router = axum::Router::new()
-            .route("/site/:siteId/challenge", get(get_challange))
-            .route_layer(get_site_middleware.clone())
-            .with_state(self.state.clone());
-
-        let site_challenge_router = axum::Router::new()
-            .route(
-                "/site/:siteId/challenge/:challengeId",
-                delete(delete_challange),
-            )
-            .route(
-                "/site/:siteId/challenge/:challengeId",
-                post(validate_challenges)
-        )
-            .route_layer(auth_middleware)
-            .route_layer(get_challenge_middleware)
-            .with_state(self.state);
-
-        let combined_router = Router::new()
-            .merge(site_router)
-            .merge(site_challenge_router)
-            .layer(timeout_middleware)
-            .layer(logging_middleware);
-
-        axum::serve(self.listener, combined_router).await?;
+        let request_id_middleware =
+            axum::middleware::from_fn(crate::middleware::request_id_middleware);
 
-        Ok(())
+        let error_format_middleware = axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::error_format_middleware,
+        );
+
+        let legacy_adapter_middleware = axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::middleware::legacy_adapter_middleware,
+        );
+
+        let mut router = Router::new();
+
+        for group in groups
+            .iter()
+            .filter(|group| !matches!(group, RouteGroup::Proxy | RouteGroup::Grpc))
+        {
+            let v1_router = Router::from(Self::group_router(state, *group));
+            let legacy_router =
+                Self::legacy_router(state, *group).layer(legacy_adapter_middleware.clone());
+
+            router = router.nest("/v1", v1_router).merge(legacy_router);
+        }
+
+        if groups.contains(&RouteGroup::Public) {
+            router = router.merge(crate::openapi::router(spec)?);
+
+            if let Some(widget) = state.get_config().get_widget() {
+                router = router.merge(crate::widget::router(widget));
+            }
+        }
+
+        router = router.layer(timeout_middleware);
+
+        if groups.contains(&RouteGroup::Proxy) {
+            router = router.merge(crate::proxy::router(state)?);
+        }
+
+        if groups.contains(&RouteGroup::Grpc) {
+            router = router.merge(crate::grpc::router(state));
+        }
+
+        Ok(router
+            .layer(error_format_middleware)
+            .layer(logging_middleware)
+            .layer(request_id_middleware))
     }
 
-    fn parse_config() -> Result<Config> {
-        let config =
-            std::fs::read_to_string("config.json").context("Unable to open config.json")?;
-        let config: Config =
-            serde_json::from_str(&config).context("Unable to parse config.json")?;
+    async fn create_listeners(config: &Config) -> Result<Vec<Listener>> {
+        let listeners = config.get_listeners();
+
+        if listeners.is_empty() {
+            anyhow::bail!("No listeners configured");
+        }
 
-        Ok(config)
+        let mut bound = Vec::with_capacity(listeners.len());
+
+        for listener in listeners {
+            bound.push(Listener::bind(listener).await?);
+        }
+
+        Ok(bound)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use crate::{
+        audit::AuditLog,
+        config::Config,
+        state::State,
+        storage::StorageProvider,
+        test_support::{router, site, SITE_ID},
+    };
+
+    use super::Application;
+
+    fn test_state() -> State {
+        let config = r#"
+            {
+                "listeners": [{ "tcp": "127.0.0.1:0" }],
+                "storage": {
+                    "type": "Memory",
+                    "housekeeping": { "interval": { "seconds": 10 }, "batchSize": 10 },
+                    "sites": []
+                }
+            }
+        "#;
+
+        let config: Config = serde_json::from_str(config).expect("Failed parsing json");
+        let storage = StorageProvider::new(&config).expect("Storage configured");
+
+        State::new(config, storage, AuditLog::new())
+    }
+
+    #[tokio::test]
+    async fn test_openapi_matches_routes() {
+        let spec = Application::build_openapi(&test_state());
+        let spec = serde_json::to_value(&spec).expect("Unable to serialize spec");
+
+        let paths = spec["paths"].as_object().expect("No paths in spec");
+
+        let mut documented: Vec<&str> = paths.keys().map(String::as_str).collect();
+        documented.sort();
+
+        assert_eq!(
+            documented,
+            vec![
+                "/v1/health",
+                "/v1/metrics",
+                "/v1/site/{siteId}/challenge",
+                "/v1/site/{siteId}/challenge/{challengeId}",
+                "/v1/site/{siteId}/challenges:batch",
+                "/v1/site/{siteId}/solutions:batch"
+            ]
+        );
+
+        let challenge = &paths["/v1/site/{siteId}/challenge/{challengeId}"];
+        assert!(challenge["post"]["responses"]["401"].is_object());
+        assert!(challenge["post"]["responses"]["403"].is_object());
+        assert!(challenge["delete"]["responses"]["401"].is_object());
+
+        let content = &challenge["post"]["requestBody"]["content"];
+        assert!(content["application/cbor"].is_object());
+        assert!(content["application/msgpack"].is_object());
+        assert!(challenge["post"]["responses"]["200"]["content"]["application/cbor"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_openapi_documents_error_ids() {
+        let spec = Application::build_openapi(&test_state());
+        let spec = serde_json::to_value(&spec).expect("Unable to serialize spec");
+
+        let error_ids = spec["components"]["schemas"]["ErrorId"]["enum"]
+            .as_array()
+            .expect("ErrorId is not an enum");
+
+        for id in ["WrongNumberOfSolutions", "InternalServerError", "Timeout"] {
+            assert!(error_ids.iter().any(|e| e == id), "{id} is not documented");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_legacy_routes_are_baseline_only() {
+        let router = router(site());
+
+        let request = Request::post(format!("/site/{SITE_ID}/challenges:batch"))
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{ "count": 1 }"#))
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+
+        for (method, path) in [
+            ("POST", format!("/site/{SITE_ID}/solutions:batch")),
+            ("OPTIONS", format!("/site/{SITE_ID}/challenge")),
+            ("GET", "/metrics".to_string()),
+        ] {
+            let request = Request::builder()
+                .method(method)
+                .uri(&path)
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{ "count": 1 }"#))
+                .unwrap();
+            let response = router.clone().oneshot(request).await.unwrap();
+
+            assert!(matches!(response.status().as_u16(), 404 | 405), "{method} {path}");
+        }
 
-    async fn create_listener(config: &Config) -> Result<TcpListener> {
-        let listener = config.get_listen_socket();
+        let request = Request::get(format!("/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
 
-        TcpListener::bind(listener)
-            .await
-            .context("Unable to bind listener")
+        assert_eq!(response.status(), 200);
     }
 }
@@ -0,0 +1,274 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use anyhow::{Context, Result};
+use tracing::error;
+
+use crate::{config::AuditConfig, metrics::Counter};
+
+use super::{AuditEntry, AuditSink};
+
+/// Routine entries waiting for the writer before new ones are dropped
+const QUEUE_SIZE: usize = 10_000;
+
+#[derive(Debug)]
+struct Line {
+    text: String,
+    /// Counted towards `QUEUE_SIZE`
+    routine: bool,
+}
+
+/// Appends entries as json lines. Once the file would grow past `maxSize`
+/// it is renamed to `<path>.1`, shifting older files up to `<path>.<maxFiles>`.
+/// Writes happen on a dedicated thread. Routine entries are dropped and
+/// counted while it is `QUEUE_SIZE` of them behind, security entries like
+/// lockouts are always queued. Their number is bounded by the brute-force
+/// guard, so they can't be used to flood the queue.
+#[derive(Debug)]
+pub struct FileAuditSink {
+    sender: Sender<Line>,
+    queued: Arc<AtomicUsize>,
+    dropped: Arc<Counter>,
+}
+
+impl FileAuditSink {
+    pub fn open(config: &AuditConfig) -> Result<Self> {
+        let writer =
+            RotatingWriter::open(config.path.clone(), config.max_size, config.max_files.get())?;
+
+        let (sink, receiver) = Self::channel();
+        let queued = sink.queued.clone();
+
+        thread::Builder::new()
+            .name("audit-writer".to_string())
+            .spawn(move || {
+                let mut writer = writer;
+
+                while let Ok(line) = receiver.recv() {
+                    // Flushed once the queue is drained instead of per entry
+                    let result = std::iter::once(line)
+                        .chain(receiver.try_iter())
+                        .try_for_each(|line| {
+                            if line.routine {
+                                queued.fetch_sub(1, Ordering::Relaxed);
+                            }
+
+                            writer.write_line(&line.text)
+                        })
+                        .and_then(|()| writer.flush());
+
+                    if let Err(e) = result {
+                        error!("Unable to write audit entry: {:?}", e);
+                    }
+                }
+            })
+            .context("Unable to spawn audit writer")?;
+
+        Ok(sink)
+    }
+
+    fn channel() -> (Self, Receiver<Line>) {
+        let (sender, receiver) = mpsc::channel();
+
+        let sink = Self {
+            sender,
+            queued: Arc::default(),
+            dropped: Arc::default(),
+        };
+
+        (sink, receiver)
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, entry: &AuditEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Unable to serialize audit entry: {}", e);
+                return;
+            }
+        };
+
+        let routine = !entry.action.is_security();
+
+        if routine
+            && self
+                .queued
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+                    (queued < QUEUE_SIZE).then_some(queued + 1)
+                })
+                .is_err()
+        {
+            self.dropped.inc();
+            return;
+        }
+
+        if self.sender.send(Line { text: line, routine }).is_err() {
+            self.dropped.inc();
+            error!("Audit writer stopped, dropping entry");
+        }
+    }
+
+    fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+}
+
+struct RotatingWriter {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingWriter {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> Result<Self> {
+        let file = Self::open_file(&path)?;
+        let size = file.get_ref().metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn open_file(path: &Path) -> Result<BufWriter<File>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Unable to open audit log {}", path.display()))?;
+
+        Ok(BufWriter::new(file))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let length = line.len() as u64 + 1;
+
+        if self.size > 0 && self.size + length > self.max_size {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += length;
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.flush()?;
+
+        for n in (1..self.max_files).rev() {
+            let from = self.rotated(n);
+
+            if from.exists() {
+                fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+
+        fs::rename(&self.path, self.rotated(1))?;
+
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        audit::{AuditAction, AuditEntry, AuditOutcome, AuditSink},
+        config::AuditConfig,
+    };
+
+    use super::{FileAuditSink, RotatingWriter, QUEUE_SIZE};
+
+    #[test]
+    fn test_rotation() {
+        let directory = std::env::temp_dir().join(format!("audit-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("audit.jsonl");
+
+        let mut writer = RotatingWriter::open(path.clone(), 10, 2).unwrap();
+
+        for line in ["first", "second", "third", "fourth"] {
+            writer.write_line(line).unwrap();
+        }
+
+        writer.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(directory.join("audit.jsonl.1")).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(directory.join("audit.jsonl.2")).unwrap(), "second\n");
+        assert!(!directory.join("audit.jsonl.3").exists());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_full_queue_keeps_security_entries() {
+        // No writer, so nothing leaves the queue
+        let (sink, receiver) = FileAuditSink::channel();
+
+        let used = AuditEntry::new("apiKey:ci", AuditAction::ApiKeyUsed, AuditOutcome::Success);
+
+        for _ in 0..=QUEUE_SIZE {
+            sink.record(&used);
+        }
+
+        let lockout = AuditEntry::new("client:127.0.0.1", AuditAction::Lockout, AuditOutcome::Success);
+        let threshold =
+            AuditEntry::new("client:127.0.0.1", AuditAction::FailureThreshold, AuditOutcome::Failure);
+
+        sink.record(&lockout);
+        sink.record(&threshold);
+
+        assert_eq!(sink.dropped(), 1);
+
+        let lines: Vec<_> = receiver.try_iter().collect();
+
+        assert_eq!(lines.len(), QUEUE_SIZE + 2);
+        assert!(lines[QUEUE_SIZE].text.contains("auth.lockout"));
+        assert!(lines[QUEUE_SIZE + 1].text.contains("auth.failureThreshold"));
+    }
+
+    #[test]
+    fn test_max_files_required() {
+        let config = serde_json::from_value::<AuditConfig>(serde_json::json!({
+            "path": "/tmp/audit.jsonl",
+            "maxFiles": 0
+        }));
+
+        assert!(config.is_err());
+    }
+}
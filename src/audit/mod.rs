@@ -0,0 +1,270 @@
+use std::{
+    fmt::{self, Write},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::AuditConfig;
+
+mod file;
+
+pub use file::FileAuditSink;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AuditAction {
+    #[serde(rename = "config.loaded")]
+    ConfigLoaded,
+    #[serde(rename = "site.loaded")]
+    SiteLoaded,
+    #[serde(rename = "apiKey.used")]
+    ApiKeyUsed,
+    #[serde(rename = "apiKey.failed")]
+    ApiKeyFailed,
+    #[serde(rename = "auth.lockout")]
+    Lockout,
+    #[serde(rename = "auth.failureThreshold")]
+    FailureThreshold,
+    #[serde(rename = "challenge.deleted")]
+    ChallengeDeleted,
+}
+
+impl AuditAction {
+    /// Entries sinks must not drop when falling behind. Only recorded at
+    /// startup or once per lockout, unlike the per-request ones.
+    pub fn is_security(&self) -> bool {
+        matches!(
+            self,
+            AuditAction::ConfigLoaded
+                | AuditAction::SiteLoaded
+                | AuditAction::Lockout
+                | AuditAction::FailureThreshold
+        )
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            AuditAction::ConfigLoaded => "config.loaded",
+            AuditAction::SiteLoaded => "site.loaded",
+            AuditAction::ApiKeyUsed => "apiKey.used",
+            AuditAction::ApiKeyFailed => "apiKey.failed",
+            AuditAction::Lockout => "auth.lockout",
+            AuditAction::FailureThreshold => "auth.failureThreshold",
+            AuditAction::ChallengeDeleted => "challenge.deleted",
+        };
+
+        f.write_str(action)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+    /// `apiKey:<id>`, `client:<ip>` or `config`
+    pub actor: String,
+    #[serde(rename = "siteId", skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub outcome: AuditOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(actor: impl Into<String>, action: AuditAction, outcome: AuditOutcome) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Duration since failed")
+            .as_millis() as u64;
+
+        Self {
+            timestamp,
+            actor: actor.into(),
+            site_id: None,
+            action,
+            outcome,
+            detail: None,
+        }
+    }
+
+    pub fn with_site(mut self, site_id: &Uuid) -> Self {
+        self.site_id = Some(*site_id);
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Destination of audit entries. Implementations must not block for long,
+/// they are called while handling requests.
+pub trait AuditSink: Send + Sync + 'static {
+    fn record(&self, entry: &AuditEntry);
+
+    /// Entries the sink had to drop, exposed on `/metrics`
+    fn dropped(&self) -> u64 {
+        0
+    }
+}
+
+/// Logs entries with the `audit` tracing target
+#[derive(Debug, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, entry: &AuditEntry) {
+        info!(
+            target: "audit",
+            actor = %entry.actor,
+            site_id = ?entry.site_id,
+            action = %entry.action,
+            outcome = ?entry.outcome,
+            detail = ?entry.detail,
+            "Audit"
+        );
+    }
+}
+
+/// Fans entries out to all configured sinks
+#[derive(Clone)]
+pub struct AuditLog {
+    sinks: Vec<Arc<dyn AuditSink>>,
+}
+
+impl AuditLog {
+    /// Logs to tracing only
+    pub fn new() -> Self {
+        Self {
+            sinks: vec![Arc::new(TracingAuditSink)],
+        }
+    }
+
+    /// Logs to tracing and the file of `config`, if any
+    pub fn from_config(config: Option<&AuditConfig>) -> Result<Self> {
+        Self::new().with_config(config)
+    }
+
+    /// Adds the file sink of `config`, if any
+    pub fn with_config(self, config: Option<&AuditConfig>) -> Result<Self> {
+        match config {
+            Some(config) => Ok(self.with_sink(FileAuditSink::open(config)?)),
+            None => Ok(self),
+        }
+    }
+
+    pub fn with_sink(mut self, sink: impl AuditSink) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        for sink in &self.sinks {
+            sink.record(&entry);
+        }
+    }
+
+    /// Appends the dropped entries of all sinks in the prometheus text format
+    pub fn render(&self, output: &mut String) {
+        let name = "oxidecaptcha_audit_dropped_total";
+        let dropped: u64 = self.sinks.iter().map(|sink| sink.dropped()).sum();
+
+        let _ = writeln!(output, "# HELP {name} Audit entries dropped by a sink falling behind");
+        let _ = writeln!(output, "# TYPE {name} counter");
+        let _ = writeln!(output, "{name} {dropped}");
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("sinks", &self.sinks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use uuid::Uuid;
+
+    use super::{AuditAction, AuditEntry, AuditLog, AuditOutcome, AuditSink};
+
+    #[derive(Clone, Default)]
+    pub(crate) struct RecordingSink(pub(crate) Arc<Mutex<Vec<AuditEntry>>>);
+
+    impl AuditSink for RecordingSink {
+        fn record(&self, entry: &AuditEntry) {
+            self.0.lock().unwrap().push(entry.clone());
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let site_id = Uuid::new_v4();
+        let entry = AuditEntry::new("apiKey:ci", AuditAction::ChallengeDeleted, AuditOutcome::Success)
+            .with_site(&site_id);
+
+        let json = serde_json::to_value(&entry).unwrap();
+
+        assert_eq!(json["actor"], "apiKey:ci");
+        assert_eq!(json["siteId"], site_id.to_string());
+        assert_eq!(json["action"], "challenge.deleted");
+        assert_eq!(json["outcome"], "success");
+        assert!(json["timestamp"].is_u64());
+        assert!(json.get("detail").is_none());
+    }
+
+    #[test]
+    fn test_fan_out() {
+        let sink = RecordingSink::default();
+        let log = AuditLog::new().with_sink(sink.clone());
+
+        log.record(AuditEntry::new("config", AuditAction::ConfigLoaded, AuditOutcome::Success));
+
+        assert_eq!(sink.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_render_dropped() {
+        struct FullSink;
+
+        impl AuditSink for FullSink {
+            fn record(&self, _entry: &AuditEntry) {}
+
+            fn dropped(&self) -> u64 {
+                3
+            }
+        }
+
+        let log = AuditLog::new().with_sink(FullSink).with_sink(FullSink);
+
+        let mut output = String::new();
+        log.render(&mut output);
+
+        assert!(output.contains("oxidecaptcha_audit_dropped_total 6\n"));
+    }
+}
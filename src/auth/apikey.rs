@@ -0,0 +1,183 @@
+use std::fmt;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use serde::{de, Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiKeyError {
+    /// Not a PHC string of an argon2 hash
+    InvalidHash,
+}
+
+impl fmt::Display for ApiKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiKeyError::InvalidHash => f.write_str("apiKeyHash must be an argon2 PHC string"),
+        }
+    }
+}
+
+impl std::error::Error for ApiKeyError {}
+
+/// Api-key of a site. Plaintext keys from the config are only kept as their
+/// sha256 digest.
+#[derive(Clone)]
+pub enum ApiKey {
+    Sha256([u8; 32]),
+    /// Argon2 hash in PHC string format, e.g. `$argon2id$v=19$...`
+    Argon2(String),
+}
+
+impl ApiKey {
+    pub fn from_plaintext(key: &str) -> Self {
+        ApiKey::Sha256(Sha256::digest(key.as_bytes()).into())
+    }
+
+    pub fn from_phc(hash: impl Into<String>) -> Result<Self, ApiKeyError> {
+        let hash = hash.into();
+
+        let parsed = PasswordHash::new(&hash).map_err(|_| ApiKeyError::InvalidHash)?;
+
+        if !parsed.algorithm.as_str().starts_with("argon2") {
+            return Err(ApiKeyError::InvalidHash);
+        }
+
+        Ok(ApiKey::Argon2(hash))
+    }
+
+    /// Argon2 verification is slow by design, run it off the async executor
+    pub fn is_expensive(&self) -> bool {
+        matches!(self, ApiKey::Argon2(_))
+    }
+
+    /// Identifies the stored key, e.g. to invalidate cached verifications
+    /// when it changes
+    pub(crate) fn fingerprint(&self) -> &[u8] {
+        match self {
+            ApiKey::Sha256(digest) => digest,
+            ApiKey::Argon2(hash) => hash.as_bytes(),
+        }
+    }
+
+    /// Compares in constant time
+    pub fn verify(&self, key: &[u8]) -> bool {
+        match self {
+            ApiKey::Sha256(digest) => {
+                let presented: [u8; 32] = Sha256::digest(key).into();
+
+                presented.ct_eq(digest).into()
+            }
+            ApiKey::Argon2(hash) => match PasswordHash::new(hash) {
+                Ok(hash) => Argon2::default().verify_password(key, &hash).is_ok(),
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiKey::Sha256(_) => f.write_str("ApiKey::Sha256(..)"),
+            ApiKey::Argon2(_) => f.write_str("ApiKey::Argon2(..)"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hash = String::deserialize(deserializer)?;
+
+        ApiKey::from_phc(hash).map_err(de::Error::custom)
+    }
+}
+
+/// Hashes `key` with argon2id and the default parameters, for `apiKeyHash`
+pub fn hash_api_key(key: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(key.as_bytes(), &salt)
+        .expect("Argon2 hashing with default parameters failed")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use argon2::{
+        password_hash::{PasswordHasher, SaltString},
+        Algorithm, Argon2, Params, Version,
+    };
+
+    use super::{ApiKey, ApiKeyError};
+
+    /// Cheap parameters to keep the tests fast
+    fn cheap_hash(key: &str) -> String {
+        let argon2 = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(8, 1, 1, None).unwrap(),
+        );
+
+        argon2
+            .hash_password(
+                key.as_bytes(),
+                &SaltString::from_b64("c2FsdHNhbHRzYWx0").unwrap(),
+            )
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_plaintext() {
+        let key = ApiKey::from_plaintext("cool");
+
+        assert!(key.verify(b"cool"));
+        assert!(!key.verify(b"cool2"));
+        assert!(!key.is_expensive());
+    }
+
+    #[test]
+    fn test_argon2() {
+        let key = ApiKey::from_phc(cheap_hash("cool")).expect("Rejected argon2 hash");
+
+        assert!(key.verify(b"cool"));
+        assert!(!key.verify(b"wrong"));
+        assert!(key.is_expensive());
+    }
+
+    #[test]
+    fn test_reject_invalid_hash() {
+        assert_eq!(
+            ApiKey::from_phc("cool").unwrap_err(),
+            ApiKeyError::InvalidHash
+        );
+        assert_eq!(
+            ApiKey::from_phc("$pbkdf2-sha256$i=1000$c2FsdA$aGFzaA").unwrap_err(),
+            ApiKeyError::InvalidHash
+        );
+    }
+
+    #[test]
+    fn test_hash_api_key() {
+        let hash = super::hash_api_key("cool");
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(ApiKey::from_phc(hash).unwrap().verify(b"cool"));
+    }
+
+    #[test]
+    fn test_debug_hides_key() {
+        let key = ApiKey::from_phc(cheap_hash("cool")).unwrap();
+
+        assert_eq!(format!("{key:?}"), "ApiKey::Argon2(..)");
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Remembers recently verified, or rejected, api-keys, so expensive hashes
+/// are only checked once per `ttl`. Entries are keyed by a digest of the site, the
+/// stored key and the presented key, never by the key itself.
+#[derive(Debug)]
+pub(crate) struct VerifiedKeyCache {
+    entries: Mutex<HashMap<[u8; 32], Instant>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl VerifiedKeyCache {
+    pub(crate) fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+            capacity,
+        }
+    }
+
+    pub(crate) fn contains(&self, site_id: &Uuid, fingerprint: &[u8], key: &[u8]) -> bool {
+        let digest = Self::digest(site_id, fingerprint, key);
+        let entries = self.entries.lock().expect("Key cache poisoned");
+
+        entries
+            .get(&digest)
+            .is_some_and(|expires_at| *expires_at > Instant::now())
+    }
+
+    pub(crate) fn insert(&self, site_id: &Uuid, fingerprint: &[u8], key: &[u8]) {
+        if self.capacity == 0 || self.ttl.is_zero() {
+            return;
+        }
+
+        let digest = Self::digest(site_id, fingerprint, key);
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("Key cache poisoned");
+
+        if entries.len() >= self.capacity {
+            entries.retain(|_, expires_at| *expires_at > now);
+        }
+
+        if entries.len() >= self.capacity {
+            entries.clear();
+        }
+
+        entries.insert(digest, now + self.ttl);
+    }
+
+    fn digest(site_id: &Uuid, fingerprint: &[u8], key: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(site_id.as_bytes());
+        hasher.update((fingerprint.len() as u64).to_be_bytes());
+        hasher.update(fingerprint);
+        hasher.update(key);
+
+        hasher.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::VerifiedKeyCache;
+
+    #[test]
+    fn test_hit_and_miss() {
+        let cache = VerifiedKeyCache::new(Duration::from_secs(60), 10);
+        let site_id = Uuid::new_v4();
+
+        cache.insert(&site_id, b"hash", b"key");
+
+        assert!(cache.contains(&site_id, b"hash", b"key"));
+        assert!(!cache.contains(&site_id, b"hash", b"other"));
+        assert!(!cache.contains(&site_id, b"rotated", b"key"));
+        assert!(!cache.contains(&Uuid::new_v4(), b"hash", b"key"));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let cache = VerifiedKeyCache::new(Duration::from_millis(1), 10);
+        let site_id = Uuid::new_v4();
+
+        cache.insert(&site_id, b"hash", b"key");
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(!cache.contains(&site_id, b"hash", b"key"));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let cache = VerifiedKeyCache::new(Duration::from_secs(60), 2);
+        let site_id = Uuid::new_v4();
+
+        for key in [b"a", b"b", b"c"] {
+            cache.insert(&site_id, b"hash", key);
+        }
+
+        assert!(cache.contains(&site_id, b"hash", b"c"));
+        assert!(cache.entries.lock().unwrap().len() <= 2);
+    }
+}
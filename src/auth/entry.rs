@@ -0,0 +1,213 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de, Deserialize, Deserializer};
+
+use super::ApiKey;
+
+/// Id of the key configured through the plain `apiKey`/`apiKeyHash` fields
+pub const DEFAULT_KEY_ID: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Scope {
+    /// Validate solutions
+    Validate,
+    /// Delete challenges
+    Delete,
+    /// Everything
+    Admin,
+}
+
+/// Outcome of checking an [ApiKeyEntry] at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyValidity {
+    Valid,
+    NotYetValid,
+    Expired,
+}
+
+/// One of the api-keys of a site. Multiple keys allow rotating them without
+/// downtime.
+#[derive(Debug, Clone)]
+pub struct ApiKeyEntry {
+    id: String,
+    key: ApiKey,
+    not_before: Option<u64>,
+    expires_at: Option<u64>,
+    scopes: Vec<Scope>,
+}
+
+impl ApiKeyEntry {
+    /// Key valid forever with the admin scope
+    pub fn new(id: impl Into<String>, key: ApiKey) -> Self {
+        Self {
+            id: id.into(),
+            key,
+            not_before: None,
+            expires_at: None,
+            scopes: vec![Scope::Admin],
+        }
+    }
+
+    /// Unix timestamp in seconds before which the key is rejected
+    pub fn with_not_before(mut self, not_before: u64) -> Self {
+        self.not_before = Some(not_before);
+        self
+    }
+
+    /// Unix timestamp in seconds from which the key is rejected
+    pub fn with_expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn with_scopes(mut self, scopes: Vec<Scope>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_key(&self) -> &ApiKey {
+        &self.key
+    }
+
+    pub fn get_not_before(&self) -> Option<u64> {
+        self.not_before
+    }
+
+    pub fn get_expires_at(&self) -> Option<u64> {
+        self.expires_at
+    }
+
+    pub fn get_scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes
+            .iter()
+            .any(|granted| *granted == scope || *granted == Scope::Admin)
+    }
+
+    pub fn validity_at(&self, now: u64) -> KeyValidity {
+        if self.not_before.is_some_and(|not_before| now < not_before) {
+            return KeyValidity::NotYetValid;
+        }
+
+        if self.expires_at.is_some_and(|expires_at| now >= expires_at) {
+            return KeyValidity::Expired;
+        }
+
+        KeyValidity::Valid
+    }
+
+    pub fn validity(&self) -> KeyValidity {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("System time before unix epoch")
+            .as_secs();
+
+        self.validity_at(now)
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiKeyEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Entry {
+            id: String,
+            key: Option<String>,
+            #[serde(rename = "keyHash")]
+            key_hash: Option<ApiKey>,
+            #[serde(rename = "notBefore")]
+            not_before: Option<u64>,
+            #[serde(rename = "expiresAt")]
+            expires_at: Option<u64>,
+            scopes: Option<Vec<Scope>>,
+        }
+
+        let entry = Entry::deserialize(deserializer)?;
+
+        let key = match (entry.key, entry.key_hash) {
+            (Some(key), None) => ApiKey::from_plaintext(&key),
+            (None, Some(key_hash)) => key_hash,
+            (Some(_), Some(_)) => {
+                return Err(de::Error::custom(
+                    "only one of `key` and `keyHash` may be set",
+                ))
+            }
+            (None, None) => return Err(de::Error::missing_field("keyHash")),
+        };
+
+        let mut result = ApiKeyEntry::new(entry.id, key);
+        result.not_before = entry.not_before;
+        result.expires_at = entry.expires_at;
+
+        if let Some(scopes) = entry.scopes {
+            result.scopes = scopes;
+        }
+
+        Ok(result)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Validate => f.write_str("validate"),
+            Scope::Delete => f.write_str("delete"),
+            Scope::Admin => f.write_str("admin"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiKeyEntry, KeyValidity, Scope};
+
+    #[test]
+    fn test_deserialize() {
+        let entry: ApiKeyEntry = serde_json::from_str(
+            r#"{ "id": "ci", "key": "cool", "notBefore": 100, "expiresAt": 200, "scopes": ["validate"] }"#,
+        )
+        .expect("Failed parsing json");
+
+        assert_eq!(entry.get_id(), "ci");
+        assert!(entry.get_key().verify(b"cool"));
+        assert_eq!(entry.get_scopes(), &[Scope::Validate]);
+        assert_eq!(entry.validity_at(99), KeyValidity::NotYetValid);
+        assert_eq!(entry.validity_at(100), KeyValidity::Valid);
+        assert_eq!(entry.validity_at(200), KeyValidity::Expired);
+    }
+
+    #[test]
+    fn test_deserialize_requires_one_key() {
+        assert!(serde_json::from_str::<ApiKeyEntry>(r#"{ "id": "ci" }"#).is_err());
+        assert!(serde_json::from_str::<ApiKeyEntry>(
+            r#"{ "id": "ci", "key": "cool", "keyHash": "$argon2id$v=19$m=8,t=1,p=1$c2FsdHNhbHRzYWx0$qOAVMUBAC45Z7ZapMJ0aNZPCsCi/JIzIN6NxaJUgKHk" }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_scopes() {
+        let admin = ApiKeyEntry::new("admin", super::ApiKey::from_plaintext("a"));
+        let delete = admin.clone().with_scopes(vec![Scope::Delete]);
+
+        assert!(admin.has_scope(Scope::Validate));
+        assert!(admin.has_scope(Scope::Delete));
+        assert!(delete.has_scope(Scope::Delete));
+        assert!(!delete.has_scope(Scope::Validate));
+        assert!(!delete.has_scope(Scope::Admin));
+    }
+}
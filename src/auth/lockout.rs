@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use crate::config::BruteForceConfig;
+
+/// Entries kept before forgotten ones are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Pruning scans every entry, so it runs at most this often
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Entries kept at most, the ones with the oldest failure are evicted beyond
+/// that. Clients rotating addresses would grow the map without bound otherwise.
+const MAX_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Subject {
+    Client(Uuid, IpAddr),
+    Site(Uuid),
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct FailureTable {
+    entries: HashMap<Subject, Failures>,
+    pruned_at: Instant,
+}
+
+impl FailureTable {
+    /// Prunes forgotten entries and evicts the oldest ones until `needed`
+    /// more fit into `capacity`
+    fn make_room(&mut self, needed: usize, capacity: usize, now: Instant, window: Duration) {
+        if self.entries.len() >= PRUNE_THRESHOLD.min(capacity)
+            && now.duration_since(self.pruned_at) >= PRUNE_INTERVAL
+        {
+            self.entries
+                .retain(|_, entry| BruteForceGuard::is_active(entry, now, window));
+            self.pruned_at = now;
+        }
+
+        let Some(excess) = (self.entries.len() + needed).checked_sub(capacity) else {
+            return;
+        };
+
+        // Evicting a tenth at once keeps the sort off most failures
+        let evict = excess.max(capacity / 10).min(self.entries.len());
+
+        if evict == 0 {
+            return;
+        }
+
+        let mut oldest: Vec<(Instant, Subject)> = self
+            .entries
+            .iter()
+            .map(|(subject, entry)| (entry.last_failure, *subject))
+            .collect();
+        oldest.select_nth_unstable_by_key(evict - 1, |(last_failure, _)| *last_failure);
+
+        for (_, subject) in &oldest[..evict] {
+            self.entries.remove(subject);
+        }
+    }
+}
+
+/// What a recorded failure caused
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RecordedFailure {
+    /// Lockout of the client, if the failure started one
+    pub(crate) lockout: Option<Duration>,
+    /// Whether this failure took the site over `siteAttempts`
+    pub(crate) site_threshold_reached: bool,
+}
+
+/// Tracks failed api-key checks and locks out clients guessing keys. Failures
+/// across all clients of a site are only counted for alerting, locking out
+/// the site would let anyone lock out its backends.
+#[derive(Debug)]
+pub(crate) struct BruteForceGuard {
+    config: BruteForceConfig,
+    failures: Mutex<FailureTable>,
+    capacity: usize,
+}
+
+impl BruteForceGuard {
+    pub(crate) fn new(config: BruteForceConfig) -> Self {
+        Self::with_capacity(config, MAX_ENTRIES)
+    }
+
+    pub(crate) fn with_capacity(config: BruteForceConfig, capacity: usize) -> Self {
+        Self {
+            config,
+            failures: Mutex::new(FailureTable {
+                entries: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+            capacity,
+        }
+    }
+
+    /// Remaining lockout of the client, clients without a known address are
+    /// never locked out
+    pub(crate) fn locked_out(&self, site_id: &Uuid, client: Option<IpAddr>) -> Option<Duration> {
+        let client = client?;
+        let now = Instant::now();
+        let failures = self.failures.lock().expect("Brute force guard poisoned");
+
+        failures
+            .entries
+            .get(&Subject::Client(*site_id, client))?
+            .locked_until?
+            .checked_duration_since(now)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Records a failure of the client and the site
+    pub(crate) fn record_failure(&self, site_id: &Uuid, client: Option<IpAddr>) -> RecordedFailure {
+        let now = Instant::now();
+        let window = self.config.get_window();
+        let mut failures = self.failures.lock().expect("Brute force guard poisoned");
+
+        let needed = [
+            client.map(|client| Subject::Client(*site_id, client)),
+            Some(Subject::Site(*site_id)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|subject| !failures.entries.contains_key(subject))
+        .count();
+        failures.make_room(needed, self.capacity, now, window);
+
+        let lockout = client.and_then(|client| {
+            let entry =
+                Self::count(&mut failures.entries, Subject::Client(*site_id, client), now, window);
+            let lockout = self.lockout(entry.count, self.config.client_attempts)?;
+            entry.locked_until = Some(now + lockout);
+
+            Some(lockout)
+        });
+
+        let site = Self::count(&mut failures.entries, Subject::Site(*site_id), now, window);
+        let site_threshold_reached = site.count == self.config.site_attempts.saturating_add(1);
+
+        RecordedFailure {
+            lockout,
+            site_threshold_reached,
+        }
+    }
+
+    /// Forgets the failures of a client after it authenticated. Failures of
+    /// the site are kept, they only expire with the window.
+    pub(crate) fn record_success(&self, site_id: &Uuid, client: Option<IpAddr>) {
+        if let Some(client) = client {
+            self.failures
+                .lock()
+                .expect("Brute force guard poisoned")
+                .entries
+                .remove(&Subject::Client(*site_id, client));
+        }
+    }
+
+    fn count(
+        failures: &mut HashMap<Subject, Failures>,
+        subject: Subject,
+        now: Instant,
+        window: Duration,
+    ) -> &mut Failures {
+        let entry = failures.entry(subject).or_insert(Failures {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+
+        if !Self::is_active(entry, now, window) {
+            entry.count = 0;
+            entry.locked_until = None;
+        }
+
+        entry.count = entry.count.saturating_add(1);
+        entry.last_failure = now;
+
+        entry
+    }
+
+    fn lockout(&self, count: u32, attempts: u32) -> Option<Duration> {
+        let excess = count.checked_sub(attempts)?;
+
+        let lockout = self
+            .config
+            .get_initial_lockout()
+            .saturating_mul(2u32.saturating_pow(excess));
+
+        Some(lockout.min(self.config.get_max_lockout()))
+    }
+
+    fn is_active(entry: &Failures, now: Instant, window: Duration) -> bool {
+        now.duration_since(entry.last_failure) < window
+            || entry.locked_until.is_some_and(|locked_until| locked_until > now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, time::Duration};
+
+    use uuid::Uuid;
+
+    use crate::config::BruteForceConfig;
+
+    use super::{BruteForceGuard, RecordedFailure, Subject};
+
+    fn guard() -> BruteForceGuard {
+        BruteForceGuard::new(BruteForceConfig {
+            client_attempts: 2,
+            site_attempts: 4,
+            initial_lockout: Duration::from_secs(1).into(),
+            max_lockout: Duration::from_secs(3).into(),
+            window: Duration::from_secs(60).into(),
+        })
+    }
+
+    const CLIENT: IpAddr = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+    const OTHER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::BROADCAST);
+
+    #[test]
+    fn test_client_backoff() {
+        let guard = guard();
+        let site = Uuid::new_v4();
+
+        assert_eq!(guard.record_failure(&site, Some(CLIENT)).lockout, None);
+        assert!(guard.locked_out(&site, Some(CLIENT)).is_none());
+
+        assert_eq!(guard.record_failure(&site, Some(CLIENT)).lockout, Some(Duration::from_secs(1)));
+        assert_eq!(guard.record_failure(&site, Some(CLIENT)).lockout, Some(Duration::from_secs(2)));
+        // Capped at max_lockout
+        assert_eq!(guard.record_failure(&site, Some(CLIENT)).lockout, Some(Duration::from_secs(3)));
+
+        assert!(guard.locked_out(&site, Some(CLIENT)).is_some());
+        assert!(guard.locked_out(&Uuid::new_v4(), Some(CLIENT)).is_none());
+    }
+
+    #[test]
+    fn test_site_threshold_only_alerts() {
+        let guard = guard();
+        let site = Uuid::new_v4();
+
+        for i in 0..4u8 {
+            let client = IpAddr::V4([10, 0, 0, i].into());
+            assert_eq!(guard.record_failure(&site, Some(client)), RecordedFailure::default());
+        }
+
+        assert!(guard.record_failure(&site, None).site_threshold_reached);
+        // Reported once per window
+        assert!(!guard.record_failure(&site, Some(OTHER)).site_threshold_reached);
+
+        assert!(guard.locked_out(&site, Some(OTHER)).is_none());
+        assert!(guard.locked_out(&site, None).is_none());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let guard = BruteForceGuard::with_capacity(guard().config, 10);
+        let site = Uuid::new_v4();
+
+        for i in 0..20u8 {
+            guard.record_failure(&site, Some(IpAddr::V4([10, 0, 0, i].into())));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let failures = guard.failures.lock().unwrap();
+
+        assert!(failures.entries.len() <= 10);
+        // The latest client and the site are kept, the oldest clients evicted
+        assert!(failures
+            .entries
+            .contains_key(&Subject::Client(site, IpAddr::V4([10, 0, 0, 19].into()))));
+        assert!(!failures
+            .entries
+            .contains_key(&Subject::Client(site, IpAddr::V4([10, 0, 0, 0].into()))));
+    }
+
+    #[test]
+    fn test_success_resets_client() {
+        let guard = guard();
+        let site = Uuid::new_v4();
+
+        guard.record_failure(&site, Some(CLIENT));
+        guard.record_success(&site, Some(CLIENT));
+
+        assert_eq!(guard.record_failure(&site, Some(CLIENT)).lockout, None);
+    }
+}
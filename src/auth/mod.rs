@@ -0,0 +1,16 @@
+mod apikey;
+mod cache;
+mod entry;
+mod lockout;
+mod nonce;
+mod signing;
+
+pub use apikey::{hash_api_key, ApiKey, ApiKeyError};
+pub(crate) use cache::VerifiedKeyCache;
+pub use entry::{ApiKeyEntry, KeyValidity, Scope, DEFAULT_KEY_ID};
+pub(crate) use lockout::BruteForceGuard;
+pub(crate) use nonce::{NonceCache, NonceError};
+pub use signing::{
+    RequestSigningConfig, SignatureError, SignedRequest, NONCE_HEADER, SIGNATURE_HEADER,
+    TIMESTAMP_HEADER,
+};
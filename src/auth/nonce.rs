@@ -0,0 +1,113 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+/// Entries kept before expired ones are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Unexpired entries kept at most
+const MAX_NONCES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NonceError {
+    /// The nonce was already seen
+    Replayed,
+    /// Too many unexpired nonces, evicting one would allow replaying it
+    Full,
+}
+
+/// Nonces of signed requests seen recently, to reject replays
+#[derive(Debug)]
+pub(crate) struct NonceCache {
+    nonces: Mutex<HashMap<(Uuid, String), Instant>>,
+    capacity: usize,
+}
+
+impl Default for NonceCache {
+    fn default() -> Self {
+        Self::with_capacity(MAX_NONCES)
+    }
+}
+
+impl NonceCache {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nonces: Mutex::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Remembers the nonce for `ttl`
+    pub(crate) fn insert(&self, site_id: &Uuid, nonce: &str, ttl: Duration) -> Result<(), NonceError> {
+        let now = Instant::now();
+        let mut nonces = self.nonces.lock().expect("Nonce cache poisoned");
+
+        if nonces.len() >= PRUNE_THRESHOLD.min(self.capacity) {
+            nonces.retain(|_, expires_at| *expires_at > now);
+        }
+
+        let key = (*site_id, nonce.to_owned());
+
+        match nonces.get(&key) {
+            Some(expires_at) if *expires_at > now => return Err(NonceError::Replayed),
+            Some(_) => (),
+            None if nonces.len() >= self.capacity => return Err(NonceError::Full),
+            None => (),
+        }
+
+        nonces.insert(key, now + ttl);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::{NonceCache, NonceError};
+
+    #[test]
+    fn test_replay() {
+        let cache = NonceCache::default();
+        let site = Uuid::new_v4();
+
+        assert_eq!(cache.insert(&site, "nonce", Duration::from_secs(60)), Ok(()));
+        assert_eq!(
+            cache.insert(&site, "nonce", Duration::from_secs(60)),
+            Err(NonceError::Replayed)
+        );
+        assert_eq!(cache.insert(&Uuid::new_v4(), "nonce", Duration::from_secs(60)), Ok(()));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let cache = NonceCache::default();
+        let site = Uuid::new_v4();
+
+        assert_eq!(cache.insert(&site, "nonce", Duration::ZERO), Ok(()));
+        assert_eq!(cache.insert(&site, "nonce", Duration::from_secs(60)), Ok(()));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let cache = NonceCache::with_capacity(2);
+        let site = Uuid::new_v4();
+
+        assert_eq!(cache.insert(&site, "first", Duration::from_secs(60)), Ok(()));
+        assert_eq!(cache.insert(&site, "second", Duration::ZERO), Ok(()));
+        // The expired entry makes room
+        assert_eq!(cache.insert(&site, "third", Duration::from_secs(60)), Ok(()));
+        assert_eq!(cache.insert(&site, "fourth", Duration::from_secs(60)), Err(NonceError::Full));
+        assert_eq!(
+            cache.insert(&site, "first", Duration::from_secs(60)),
+            Err(NonceError::Replayed)
+        );
+    }
+}
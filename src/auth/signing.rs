@@ -0,0 +1,241 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use hmac::{Hmac, Mac};
+use kale_duration::AbsoluteDuration;
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const TIMESTAMP_HEADER: &str = "x-oxidecaptcha-timestamp";
+pub const NONCE_HEADER: &str = "x-oxidecaptcha-nonce";
+pub const SIGNATURE_HEADER: &str = "x-oxidecaptcha-signature";
+
+const MIN_NONCE_LENGTH: usize = 16;
+const MAX_NONCE_LENGTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    Missing,
+    Malformed,
+    Stale,
+    BadSignature,
+    Replayed,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let error = match self {
+            SignatureError::Missing => "Request signature headers missing",
+            SignatureError::Malformed => "Request signature malformed",
+            SignatureError::Stale => "Request timestamp outside the allowed window",
+            SignatureError::BadSignature => "Request signature wrong",
+            SignatureError::Replayed => "Request nonce was already used",
+        };
+
+        f.write_str(error)
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Headers of a signed request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRequest<'a> {
+    pub timestamp: u64,
+    pub nonce: &'a str,
+    pub signature: &'a str,
+}
+
+/// Requires backends to sign their requests with a secret shared with the
+/// site, on top of sending their api-key. The signature covers
+/// `<METHOD>\n<path and query>\n<timestamp>\n<nonce>\n<hex sha256 of body>`.
+#[derive(Debug, Clone)]
+pub struct RequestSigningConfig {
+    secret: Vec<u8>,
+    max_skew: Duration,
+}
+
+impl RequestSigningConfig {
+    pub fn new(secret: impl Into<Vec<u8>>, max_skew: Duration) -> Self {
+        Self {
+            secret: secret.into(),
+            max_skew,
+        }
+    }
+
+    /// Timestamps further than this from the server time are rejected, nonces
+    /// are remembered twice as long
+    pub fn get_max_skew(&self) -> &Duration {
+        &self.max_skew
+    }
+
+    /// Hex encoded signature
+    pub fn sign(&self, method: &str, path: &str, timestamp: u64, nonce: &str, body: &[u8]) -> String {
+        self.mac(method, path, timestamp, nonce, body)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Checks timestamp and signature, the nonce has to be checked for
+    /// replays by the caller
+    pub fn verify(
+        &self,
+        method: &str,
+        path: &str,
+        request: &SignedRequest,
+        body: &[u8],
+    ) -> Result<(), SignatureError> {
+        let nonce_valid = (MIN_NONCE_LENGTH..=MAX_NONCE_LENGTH).contains(&request.nonce.len())
+            && request.nonce.chars().all(|c| c.is_ascii_graphic());
+
+        if !nonce_valid {
+            return Err(SignatureError::Malformed);
+        }
+
+        let signature = decode_hex(request.signature).ok_or(SignatureError::Malformed)?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Duration since failed")
+            .as_secs();
+
+        if now.abs_diff(request.timestamp) > self.max_skew.as_secs() {
+            return Err(SignatureError::Stale);
+        }
+
+        self.mac(method, path, request.timestamp, request.nonce, body)
+            .verify_slice(&signature)
+            .map_err(|_| SignatureError::BadSignature)
+    }
+
+    fn mac(&self, method: &str, path: &str, timestamp: u64, nonce: &str, body: &[u8]) -> HmacSha256 {
+        let body_hash: String = Sha256::digest(body)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("Hmac accepts any key size");
+        mac.update(format!("{method}\n{path}\n{timestamp}\n{nonce}\n{body_hash}").as_bytes());
+        mac
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl<'de> Deserialize<'de> for RequestSigningConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawRequestSigningConfig {
+            secret: String,
+            #[serde(rename = "maxSkew")]
+            max_skew: Option<AbsoluteDuration>,
+        }
+
+        let raw = RawRequestSigningConfig::deserialize(deserializer)?;
+
+        if raw.secret.len() < 32 {
+            return Err(serde::de::Error::custom(
+                "requestSigning secret must be at least 32 bytes long",
+            ));
+        }
+
+        let max_skew = raw
+            .max_skew
+            .map(Duration::from)
+            .unwrap_or(Duration::from_secs(300));
+
+        Ok(Self::new(raw.secret, max_skew))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{RequestSigningConfig, SignatureError, SignedRequest};
+
+    const NONCE: &str = "0123456789abcdef";
+
+    fn config() -> RequestSigningConfig {
+        RequestSigningConfig::new("0123456789abcdef0123456789abcdef", Duration::from_secs(60))
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let timestamp = now();
+        let signature = config().sign("POST", "/v1/site/x", timestamp, NONCE, b"{}");
+        let request = SignedRequest {
+            timestamp,
+            nonce: NONCE,
+            signature: &signature,
+        };
+
+        assert_eq!(config().verify("POST", "/v1/site/x", &request, b"{}"), Ok(()));
+        assert_eq!(
+            config().verify("POST", "/v1/site/x", &request, b"{ }"),
+            Err(SignatureError::BadSignature)
+        );
+        assert_eq!(
+            config().verify("DELETE", "/v1/site/x", &request, b"{}"),
+            Err(SignatureError::BadSignature)
+        );
+    }
+
+    #[test]
+    fn test_stale() {
+        let timestamp = now() - 120;
+        let signature = config().sign("POST", "/", timestamp, NONCE, b"");
+        let request = SignedRequest {
+            timestamp,
+            nonce: NONCE,
+            signature: &signature,
+        };
+
+        assert_eq!(config().verify("POST", "/", &request, b""), Err(SignatureError::Stale));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let request = SignedRequest {
+            timestamp: now(),
+            nonce: NONCE,
+            signature: "zz",
+        };
+
+        assert_eq!(config().verify("POST", "/", &request, b""), Err(SignatureError::Malformed));
+
+        let request = SignedRequest {
+            timestamp: now(),
+            nonce: "short",
+            signature: "00",
+        };
+
+        assert_eq!(config().verify("POST", "/", &request, b""), Err(SignatureError::Malformed));
+    }
+}
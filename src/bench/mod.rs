@@ -0,0 +1,249 @@
+use std::{
+    fmt,
+    hint::black_box,
+    num::NonZeroUsize,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use oxidecaptcha_core::PrefixHasher;
+
+use crate::site::Site;
+
+mod model;
+
+pub use model::{recommend, Recommendation, SolveTimeModel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha256,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 1] = [Algorithm::Sha256];
+
+    fn run(&self, duration: Duration) -> u64 {
+        match self {
+            Algorithm::Sha256 => {
+                let hasher = PrefixHasher::new(&[0x5a; 16]);
+                let start = Instant::now();
+                let mut hashes = 0u64;
+
+                while start.elapsed() < duration {
+                    for counter in hashes..hashes + 1024 {
+                        black_box(hasher.hash(&counter.to_le_bytes()));
+                    }
+
+                    hashes += 1024;
+                }
+
+                hashes
+            }
+        }
+    }
+
+    /// Hashes per second on a single thread
+    pub fn measure(&self, duration: Duration) -> f64 {
+        self.run(duration) as f64 / duration.as_secs_f64()
+    }
+
+    /// Hashes per second on `threads` threads
+    pub fn measure_parallel(&self, duration: Duration, threads: usize) -> f64 {
+        let hashes: u64 = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|_| scope.spawn(|| self.run(duration)))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Benchmark thread panicked"))
+                .sum()
+        });
+
+        hashes as f64 / duration.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Sha256 => f.write_str("sha256"),
+        }
+    }
+}
+
+/// Device speed relative to the machine running the benchmark, e.g.
+/// `low-end-phone=0.1`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceProfile {
+    pub name: String,
+    pub factor: f64,
+}
+
+impl DeviceProfile {
+    pub fn defaults() -> Vec<DeviceProfile> {
+        vec![
+            DeviceProfile {
+                name: "desktop".to_string(),
+                factor: 1.0,
+            },
+            DeviceProfile {
+                name: "low-end-phone".to_string(),
+                factor: 0.1,
+            },
+        ]
+    }
+}
+
+impl FromStr for DeviceProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, factor) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <name>=<factor>, got {s}"))?;
+
+        let factor: f64 = factor
+            .parse()
+            .map_err(|_| format!("Invalid factor {factor}"))?;
+
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(format!("Factor must be positive, got {factor}"));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            factor,
+        })
+    }
+}
+
+pub struct Bench {
+    pub duration: Duration,
+    /// Target median solve time on the slowest profile
+    pub target_median: Duration,
+    pub profiles: Vec<DeviceProfile>,
+    /// Sites to print solve times for
+    pub sites: Vec<Site>,
+}
+
+impl Bench {
+    pub fn run(&self) {
+        let threads = thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(1);
+
+        println!("Hash throughput, {threads} thread(s)");
+
+        let mut reference = 0.0;
+
+        for algorithm in Algorithm::ALL {
+            let single = algorithm.measure(self.duration);
+            let parallel = algorithm.measure_parallel(self.duration, threads);
+
+            println!(
+                "  {algorithm}: {} per thread, {} total",
+                format_rate(single),
+                format_rate(parallel)
+            );
+
+            if algorithm == Algorithm::Sha256 {
+                reference = parallel;
+            }
+        }
+
+        let Some(slowest) = self
+            .profiles
+            .iter()
+            .min_by(|a, b| a.factor.total_cmp(&b.factor))
+        else {
+            return;
+        };
+
+        let target = self.target_median.as_secs_f64();
+        let recommendation = recommend(reference * slowest.factor, target);
+
+        println!();
+        println!(
+            "Recommended for a median of {} on {}",
+            format_seconds(target),
+            slowest.name
+        );
+        println!(
+            "  \"difficulty\": {}, \"prefixesToSolve\": {}, \"prefixes\": {}",
+            recommendation.difficulty, recommendation.prefixes_to_solve, recommendation.prefixes
+        );
+
+        self.print_profiles(
+            &SolveTimeModel::new(recommendation.prefixes_to_solve, recommendation.difficulty),
+            reference,
+        );
+
+        for site in &self.sites {
+            println!();
+            println!(
+                "Site {}: difficulty {}, {} of {} prefixes",
+                site.get_id(),
+                site.get_difficulty(),
+                site.get_prefixes_to_solve(),
+                site.get_prefix_count()
+            );
+
+            self.print_profiles(
+                &SolveTimeModel::new(site.get_prefixes_to_solve(), site.get_difficulty()),
+                reference,
+            );
+        }
+    }
+
+    fn print_profiles(&self, model: &SolveTimeModel, reference: f64) {
+        let width = self.profiles.iter().map(|p| p.name.len()).max().unwrap_or(0);
+
+        for profile in &self.profiles {
+            let rate = reference * profile.factor;
+
+            println!(
+                "  {:width$}  expected {:>9}  median {:>9}  p95 {:>9}",
+                profile.name,
+                format_seconds(model.expected_seconds(rate)),
+                format_seconds(model.quantile_seconds(0.5, rate)),
+                format_seconds(model.quantile_seconds(0.95, rate)),
+            );
+        }
+    }
+}
+
+fn format_rate(hashes_per_second: f64) -> String {
+    match hashes_per_second {
+        r if r >= 1e9 => format!("{:.2} GH/s", r / 1e9),
+        r if r >= 1e6 => format!("{:.2} MH/s", r / 1e6),
+        r if r >= 1e3 => format!("{:.2} kH/s", r / 1e3),
+        r => format!("{r:.0} H/s"),
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    match seconds {
+        s if s < 1e-3 => format!("{:.0}µs", s * 1e6),
+        s if s < 1.0 => format!("{:.0}ms", s * 1e3),
+        s if s < 120.0 => format!("{s:.2}s"),
+        s => format!("{:.1}min", s / 60.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceProfile;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile: DeviceProfile = "low-end-phone=0.1".parse().expect("Failed parsing profile");
+
+        assert_eq!(profile.name, "low-end-phone");
+        assert_eq!(profile.factor, 0.1);
+
+        "phone".parse::<DeviceProfile>().expect_err("Accepted missing factor");
+        "phone=0".parse::<DeviceProfile>().expect_err("Accepted zero factor");
+    }
+}
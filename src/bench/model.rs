@@ -0,0 +1,157 @@
+/// Solve time model for the k-of-n rule of `validate_challenges`. Each prefix
+/// takes a geometric number of attempts with `p = 2^-difficulty`, so solving
+/// `k` prefixes takes `k` of them in sum, which is approximated by an
+/// Erlang(k) distribution. As attempts are memoryless, the result holds for a
+/// single solver as well as for workers racing on separate prefixes, as long
+/// as there are spare prefixes to pick up.
+#[derive(Debug, Clone, Copy)]
+pub struct SolveTimeModel {
+    prefixes_to_solve: usize,
+    difficulty: u8,
+}
+
+/// Upper bound for `p95 / median` of a recommendation
+const MAX_SPREAD: f64 = 2.0;
+const MAX_PREFIXES_TO_SOLVE: usize = 16;
+
+impl SolveTimeModel {
+    pub fn new(prefixes_to_solve: usize, difficulty: u8) -> Self {
+        Self {
+            prefixes_to_solve,
+            difficulty,
+        }
+    }
+
+    fn attempts_per_unit(&self) -> f64 {
+        2f64.powi(self.difficulty as i32)
+    }
+
+    pub fn expected_attempts(&self) -> f64 {
+        self.prefixes_to_solve as f64 * self.attempts_per_unit()
+    }
+
+    /// Number of attempts after which a fraction `q` of the clients is done
+    pub fn attempts_quantile(&self, q: f64) -> f64 {
+        erlang_quantile(self.prefixes_to_solve, q) * self.attempts_per_unit()
+    }
+
+    pub fn expected_seconds(&self, hashes_per_second: f64) -> f64 {
+        self.expected_attempts() / hashes_per_second
+    }
+
+    pub fn quantile_seconds(&self, q: f64, hashes_per_second: f64) -> f64 {
+        self.attempts_quantile(q) / hashes_per_second
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recommendation {
+    pub difficulty: u8,
+    pub prefixes_to_solve: usize,
+    pub prefixes: usize,
+}
+
+/// Picks the smallest `prefixesToSolve` keeping the p95 within
+/// [`MAX_SPREAD`] times the median, then the difficulty whose median is
+/// closest to `target_median` seconds at `hashes_per_second`
+pub fn recommend(hashes_per_second: f64, target_median: f64) -> Recommendation {
+    let prefixes_to_solve = (1..=MAX_PREFIXES_TO_SOLVE)
+        .find(|k| erlang_quantile(*k, 0.95) / erlang_quantile(*k, 0.5) <= MAX_SPREAD)
+        .unwrap_or(MAX_PREFIXES_TO_SOLVE);
+
+    let attempts = hashes_per_second * target_median / erlang_quantile(prefixes_to_solve, 0.5);
+    let difficulty = attempts.log2().round().clamp(1.0, u8::MAX as f64) as u8;
+
+    Recommendation {
+        difficulty,
+        prefixes_to_solve,
+        prefixes: 2 * prefixes_to_solve,
+    }
+}
+
+/// Quantile of Erlang(k, 1), found by bisection on its cdf
+fn erlang_quantile(k: usize, q: f64) -> f64 {
+    let mut low = 0.0;
+    let mut high = k as f64 + 50.0 * (k as f64).sqrt() + 50.0;
+
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+
+        if erlang_cdf(k, mid) < q {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+/// `P(X <= x) = P(Poisson(x) >= k)`, Erlang(0, 1) is 0 with certainty
+fn erlang_cdf(k: usize, x: f64) -> f64 {
+    if k == 0 {
+        return 1.0;
+    }
+
+    let mut term = (-x).exp();
+    let mut below_k = term;
+
+    for i in 1..k {
+        term *= x / i as f64;
+        below_k += term;
+    }
+
+    1.0 - below_k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{erlang_cdf, erlang_quantile, recommend, SolveTimeModel};
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_exponential_quantiles() {
+        assert_close(erlang_quantile(1, 0.5), 2f64.ln());
+        assert_close(erlang_quantile(1, 0.95), 20f64.ln());
+    }
+
+    #[test]
+    fn test_no_prefixes() {
+        assert_eq!(erlang_cdf(0, 0.0), 1.0);
+        assert_close(erlang_quantile(0, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_erlang_quantiles() {
+        assert_close(erlang_quantile(4, 0.5), 3.672);
+        assert_close(erlang_quantile(4, 0.95), 7.754);
+    }
+
+    #[test]
+    fn test_model_scales_with_difficulty() {
+        let model = SolveTimeModel::new(2, 10);
+
+        assert_eq!(model.expected_attempts(), 2048.0);
+        assert_close(model.expected_seconds(1024.0), 2.0);
+        assert_close(
+            model.attempts_quantile(0.5),
+            SolveTimeModel::new(2, 0).attempts_quantile(0.5) * 1024.0,
+        );
+    }
+
+    #[test]
+    fn test_recommend_hits_target() {
+        let hashes_per_second = 1_000_000.0;
+        let recommendation = recommend(hashes_per_second, 1.0);
+
+        assert_eq!(recommendation.prefixes_to_solve, 5);
+
+        let median = SolveTimeModel::new(recommendation.prefixes_to_solve, recommendation.difficulty)
+            .quantile_seconds(0.5, hashes_per_second);
+
+        assert!((0.7..1.42).contains(&median), "median {median}");
+    }
+}
@@ -1,10 +1,17 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::Result;
 use axum::response::{IntoResponse, Response};
 use serde::{ser::SerializeStruct, Serialize};
 use timestamp::Timestamp;
 use siteparameter::SiteParameter;
+use utoipa::{
+    openapi::{
+        schema::SchemaType, ArrayBuilder, KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat,
+        Type,
+    },
+    PartialSchema, ToSchema,
+};
 use uuid::Uuid;
 
 pub use prefix::Prefix;
@@ -19,8 +26,11 @@ mod siteparameter;
 pub struct Challenge {
     id: Uuid,
     prefixes: Vec<Prefix>,
+    issued_at: Timestamp,
     expires_at: Timestamp,
-    site_parameter: SiteParameter
+    site_parameter: SiteParameter,
+    action: Option<String>,
+    origin: Option<String>,
 }
 
 impl Challenge {
@@ -31,8 +41,8 @@ impl Challenge {
             .map(|_| Prefix::generate(site.get_prefix_length()))
             .collect();
 
-        let expires_at = SystemTime::now();
-        let expires_at = expires_at + *site.get_lifetime();
+        let issued_at = SystemTime::now();
+        let expires_at = issued_at + *site.get_lifetime();
         let expires_at = expires_at.into();
 
         let site_parameter = SiteParameter{
@@ -44,11 +54,44 @@ impl Challenge {
         Challenge {
             id,
             prefixes,
+            issued_at: issued_at.into(),
             expires_at,
             site_parameter,
+            action: None,
+            origin: None,
         }
     }
 
+    /// Restarts the lifetime of a pre-generated challenge when it is handed
+    /// out
+    pub(crate) fn reissue(mut self, site: &Site) -> Self {
+        let issued_at = SystemTime::now();
+
+        self.issued_at = issued_at.into();
+        self.expires_at = (issued_at + *site.get_lifetime()).into();
+        self
+    }
+
+    /// Moves the issue time `age` into the past, for tests of lifetimes
+    #[cfg(test)]
+    pub(crate) fn aged(mut self, age: Duration) -> Self {
+        self.issued_at = (SystemTime::now() - age).into();
+        self
+    }
+
+    /// Tags the challenge with the action it protects, e.g. `login`
+    pub fn with_action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+
+
+    /// Remembers the origin the challenge was requested from, not exposed to
+    /// the solver
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
 
     pub fn get_id(&self) -> &Uuid {
         &self.id
@@ -58,37 +101,204 @@ impl Challenge {
         self.prefixes.get(n)
     }
 
+    pub fn get_prefixes(&self) -> &[Prefix] {
+        &self.prefixes
+    }
+
+    pub fn get_action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    pub fn get_origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    pub fn get_difficulty(&self) -> u8 {
+        self.site_parameter.difficulty
+    }
+
+    pub fn get_prefixes_to_solve(&self) -> usize {
+        self.site_parameter.prefixes_to_solve
+    }
+
+    pub fn get_solution_length(&self) -> usize {
+        self.site_parameter.solution_length
+    }
+
+    pub fn get_issued_at(&self) -> u64 {
+        u64::from(&self.issued_at)
+    }
+
+    pub fn get_expires_at(&self) -> u64 {
+        u64::from(&self.expires_at)
+    }
+
+    /// Time since the challenge was issued
+    pub fn get_age(&self) -> Duration {
+        self.issued_at.elapsed()
+    }
+
     pub fn is_expired(&self) -> bool {
         self.expires_at.is_expired()
     }
 }
 
 
-impl Serialize for Challenge {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+impl Challenge {
+    /// Shared by [`Challenge`] and [`LegacyChallenge`], which differ only in
+    /// the name of the `prefixesToSolve` field
+    fn serialize_with_field_names<S>(
+        &self,
+        serializer: S,
+        prefixes_to_solve: &'static str,
+    ) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("Challenge", 6)?;
-        state.serialize_field("id", &self.id)?;
+        // Cbor and msgpack write this as the length of the map, so a skipped
+        // `action` must not be counted
+        let len = 7 + usize::from(self.action.is_some());
+
+        let mut state = serializer.serialize_struct("Challenge", len)?;
+        state.serialize_field("id", &self.id.hyphenated())?;
         state.serialize_field("prefixes", &self.prefixes)?;
         state.serialize_field("difficulty", &self.site_parameter.difficulty )?;
-        state.serialize_field("challegesToSolve", &self.site_parameter.prefixes_to_solve)?;
+        state.serialize_field(prefixes_to_solve, &self.site_parameter.prefixes_to_solve)?;
         state.serialize_field("solutionLength", &self.site_parameter.solution_length)?;
+        state.serialize_field("issuedAt", &self.issued_at)?;
         state.serialize_field("expiresAt", &self.expires_at)?;
+        match &self.action {
+            Some(action) => state.serialize_field("action", action)?,
+            None => state.skip_field("action")?,
+        }
         state.end()
     }
 }
 
+impl Serialize for Challenge {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.serialize_with_field_names(serializer, "prefixesToSolve")
+    }
+}
+
+/// Representation of a challenge for the unversioned routes, which still use
+/// the misspelled `challegesToSolve` field
+pub(crate) struct LegacyChallenge<'a>(pub &'a Challenge);
+
+impl Serialize for LegacyChallenge<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize_with_field_names(serializer, "challegesToSolve")
+    }
+}
+
+impl PartialSchema for Challenge {
+    fn schema() -> RefOr<Schema> {
+        let integer = || ObjectBuilder::new().schema_type(SchemaType::new(Type::Integer));
+
+        ObjectBuilder::new()
+            .property(
+                "id",
+                ObjectBuilder::new()
+                    .schema_type(SchemaType::new(Type::String))
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::Uuid))),
+            )
+            .property("prefixes", ArrayBuilder::new().items(Prefix::schema()))
+            .property(
+                "difficulty",
+                integer().description(Some("Leading zero bits the hash must have")),
+            )
+            .property(
+                "prefixesToSolve",
+                integer().description(Some("Number of prefixes that have to be solved")),
+            )
+            .property(
+                "solutionLength",
+                integer().description(Some("Length of a solution in bytes")),
+            )
+            .property("issuedAt", Timestamp::schema())
+            .property("expiresAt", Timestamp::schema())
+            .property(
+                "action",
+                ObjectBuilder::new()
+                    .schema_type(SchemaType::new(Type::String))
+                    .description(Some("Action the challenge was requested for")),
+            )
+            .required("id")
+            .required("prefixes")
+            .required("difficulty")
+            .required("prefixesToSolve")
+            .required("solutionLength")
+            .required("issuedAt")
+            .required("expiresAt")
+            .into()
+    }
+}
+
+impl ToSchema for Challenge {}
+
 impl IntoResponse for Challenge {
     fn into_response(self) -> Response {
         let body = serde_json::to_string(&self)
             .expect("Unable to serialize boyd")
             .into();
 
-        Response::builder()
+        let mut response = Response::builder()
             .header("Content-Type", "application/json")
             .body(body)
-            .expect("Unable ot create body")
+            .expect("Unable ot create body");
+
+        response.extensions_mut().insert(self);
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use crate::site::Site;
+
+    use super::LegacyChallenge;
+
+    fn test_site() -> Site {
+        Site::new(
+            Uuid::new_v4(),
+            "key".to_string(),
+            4,
+            8,
+            2,
+            10,
+            12,
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn test_serialize_field_names() {
+        let challenge = test_site().generate_challenge();
+
+        let json = serde_json::to_value(&challenge).expect("Unable to serialize");
+
+        assert_eq!(json["prefixesToSolve"], 2);
+        assert!(json.get("challegesToSolve").is_none());
+    }
+
+    #[test]
+    fn test_serialize_legacy_field_names() {
+        let challenge = test_site().generate_challenge();
+
+        let json = serde_json::to_value(LegacyChallenge(&challenge)).expect("Unable to serialize");
+
+        assert_eq!(json["challegesToSolve"], 2);
+        assert!(json.get("prefixesToSolve").is_none());
     }
 }
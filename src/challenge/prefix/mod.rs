@@ -1,6 +1,10 @@
 use base64::prelude::*;
 use bytes::{BufMut, Bytes, BytesMut};
 use serde::Serialize;
+use utoipa::{
+    openapi::{schema::SchemaType, KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type},
+    PartialSchema,
+};
 
 use rand::{thread_rng, Rng};
 
@@ -12,14 +16,28 @@ impl Serialize for Prefix {
     where
         S: serde::Serializer,
     {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(&self.0);
+        }
+
         let encoded = BASE64_STANDARD.encode(&self.0);
 
         serializer.serialize_str(&encoded)
     }
 }
 
+impl PartialSchema for Prefix {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::new(Type::String))
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Byte)))
+            .description(Some("Base64 encoded prefix, a byte string in CBOR and MessagePack"))
+            .into()
+    }
+}
+
 impl Prefix {
-    pub fn _new(bytes: Bytes) -> Self {
+    pub fn new(bytes: Bytes) -> Self {
         Self(bytes)
     }
 
@@ -35,7 +53,7 @@ impl Prefix {
         Prefix(bytes.into())
     }
 
-    pub fn _get_bytes(&self) -> &Bytes {
+    pub fn get_bytes(&self) -> &Bytes {
         &self.0
     }
 }
@@ -1,6 +1,10 @@
 use std::time::{Duration, SystemTime};
 
 use serde::Serialize;
+use utoipa::{
+    openapi::{schema::SchemaType, KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type},
+    PartialSchema,
+};
 
 #[derive(Debug, Clone)]
 pub struct Timestamp(SystemTime);
@@ -42,6 +46,10 @@ impl Timestamp {
         let now = SystemTime::now();
         self.0 < now
     }
+
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed().unwrap_or_default()
+    }
 }
 
 impl Serialize for Timestamp {
@@ -53,6 +61,16 @@ impl Serialize for Timestamp {
     }
 }
 
+impl PartialSchema for Timestamp {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::new(Type::Integer))
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64)))
+            .description(Some("Seconds since the unix epoch"))
+            .into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::{Duration, SystemTime};
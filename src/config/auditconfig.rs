@@ -0,0 +1,34 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use serde::Deserialize;
+
+/// Json-lines file receiving the audit log, in addition to the `audit`
+/// tracing target
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditConfig {
+    pub path: PathBuf,
+    /// Bytes after which the file is rotated
+    #[serde(rename = "maxSize", default = "default_max_size")]
+    pub max_size: u64,
+    /// Rotated files kept, at least one
+    #[serde(rename = "maxFiles", default = "default_max_files")]
+    pub max_files: NonZeroUsize,
+}
+
+impl AuditConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_size: default_max_size(),
+            max_files: default_max_files(),
+        }
+    }
+}
+
+fn default_max_size() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_files() -> NonZeroUsize {
+    NonZeroUsize::new(5).expect("5 is not zero")
+}
@@ -0,0 +1,130 @@
+use std::{num::NonZeroUsize, thread, time::Duration};
+
+use kale_duration::AbsoluteDuration;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthConfig {
+    /// How long a verified api-key is remembered, so `apiKeyHash` is not
+    /// rehashed on every request. Zero disables the cache
+    #[serde(rename = "cacheTtl", default = "default_cache_ttl")]
+    pub cache_ttl: AbsoluteDuration,
+    #[serde(rename = "cacheSize", default = "default_cache_size")]
+    pub cache_size: usize,
+    /// How long a key that matched no `apiKeyHash` is remembered, so
+    /// repeating a wrong key doesn't rehash. Zero disables the cache
+    #[serde(rename = "rejectedCacheTtl", default = "default_rejected_cache_ttl")]
+    pub rejected_cache_ttl: AbsoluteDuration,
+    /// Argon2 verifications running at the same time, each takes the memory
+    /// configured in the hash. Defaults to the number of cpus
+    #[serde(rename = "hashConcurrency", default = "default_hash_concurrency")]
+    pub hash_concurrency: NonZeroUsize,
+    #[serde(rename = "bruteForce", default)]
+    pub brute_force: BruteForceConfig,
+}
+
+/// Failed api-key checks are tracked per client ip and site, and per site
+/// across all clients. Once a client used up its free attempts every further
+/// failure locks it out, doubling the lockout each time. Sites are never
+/// locked out, going over `siteAttempts` is only reported.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BruteForceConfig {
+    /// Failures of a client ip on a site before it gets locked out
+    #[serde(rename = "clientAttempts", default = "default_client_attempts")]
+    pub client_attempts: u32,
+    /// Failures on a site from all clients before it is reported in the
+    /// metrics and the audit log
+    #[serde(rename = "siteAttempts", default = "default_site_attempts")]
+    pub site_attempts: u32,
+    #[serde(rename = "initialLockout", default = "default_initial_lockout")]
+    pub initial_lockout: AbsoluteDuration,
+    #[serde(rename = "maxLockout", default = "default_max_lockout")]
+    pub max_lockout: AbsoluteDuration,
+    /// Failures are forgotten after this long without a new one
+    #[serde(default = "default_window")]
+    pub window: AbsoluteDuration,
+}
+
+impl BruteForceConfig {
+    pub fn get_initial_lockout(&self) -> Duration {
+        self.initial_lockout.into()
+    }
+
+    pub fn get_max_lockout(&self) -> Duration {
+        self.max_lockout.into()
+    }
+
+    pub fn get_window(&self) -> Duration {
+        self.window.into()
+    }
+}
+
+impl Default for BruteForceConfig {
+    fn default() -> Self {
+        Self {
+            client_attempts: default_client_attempts(),
+            site_attempts: default_site_attempts(),
+            initial_lockout: default_initial_lockout(),
+            max_lockout: default_max_lockout(),
+            window: default_window(),
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn get_cache_ttl(&self) -> Duration {
+        self.cache_ttl.into()
+    }
+
+    pub fn get_rejected_cache_ttl(&self) -> Duration {
+        self.rejected_cache_ttl.into()
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl: default_cache_ttl(),
+            cache_size: default_cache_size(),
+            rejected_cache_ttl: default_rejected_cache_ttl(),
+            hash_concurrency: default_hash_concurrency(),
+            brute_force: BruteForceConfig::default(),
+        }
+    }
+}
+
+fn default_cache_ttl() -> AbsoluteDuration {
+    Duration::from_secs(60).into()
+}
+
+fn default_cache_size() -> usize {
+    10_000
+}
+
+fn default_rejected_cache_ttl() -> AbsoluteDuration {
+    Duration::from_secs(10).into()
+}
+
+fn default_hash_concurrency() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+fn default_client_attempts() -> u32 {
+    5
+}
+
+fn default_site_attempts() -> u32 {
+    100
+}
+
+fn default_initial_lockout() -> AbsoluteDuration {
+    Duration::from_secs(1).into()
+}
+
+fn default_max_lockout() -> AbsoluteDuration {
+    Duration::from_secs(15 * 60).into()
+}
+
+fn default_window() -> AbsoluteDuration {
+    Duration::from_secs(15 * 60).into()
+}
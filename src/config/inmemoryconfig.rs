@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use kale_duration::AbsoluteDuration;
 use serde::Deserialize;
 
@@ -10,6 +12,15 @@ pub struct HousekeepingConfig {
     pub batch_size: usize,
 }
 
+impl HousekeepingConfig {
+    pub fn new(interval: Duration, batch_size: usize) -> Self {
+        Self {
+            interval: interval.into(),
+            batch_size,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct InMemoryConfig {
     housekeeping: HousekeepingConfig,
@@ -17,6 +28,13 @@ pub struct InMemoryConfig {
 }
 
 impl InMemoryConfig {
+    pub fn new(housekeeping: HousekeepingConfig, sites: Vec<Site>) -> Self {
+        Self {
+            housekeeping,
+            sites,
+        }
+    }
+
     pub fn get_sites(&self) -> &Vec<Site> {
         &self.sites
     }
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+/// The unversioned routes kept for clients predating `/v1`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LegacyRoutesConfig {
+    /// Unix timestamp in seconds after which the unversioned routes may be
+    /// removed, sent as the `Sunset` header
+    pub sunset: Option<u64>,
+}
@@ -0,0 +1,125 @@
+use std::{collections::BTreeSet, fmt, net::SocketAddr, path::PathBuf};
+
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RouteGroup {
+    Public,
+    Backend,
+    Admin,
+    Proxy,
+    Grpc,
+}
+
+impl RouteGroup {
+    /// All api route groups, the proxy and grpc have to be enabled explicitly
+    pub fn all() -> BTreeSet<RouteGroup> {
+        BTreeSet::from([RouteGroup::Public, RouteGroup::Backend, RouteGroup::Admin])
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    #[serde(default, deserialize_with = "deserialize_permissions")]
+    pub permissions: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(UnixSocketConfig),
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(addr) => write!(f, "tcp://{addr}"),
+            ListenAddress::Unix(unix) => write!(f, "unix://{}", unix.path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListenerConfig {
+    #[serde(flatten)]
+    pub address: ListenAddress,
+    #[serde(default = "RouteGroup::all")]
+    pub routes: BTreeSet<RouteGroup>,
+}
+
+fn deserialize_permissions<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    // Setuid, setgid and sticky bits have no meaning on a socket
+    u32::from_str_radix(&value, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .map(Some)
+        .ok_or_else(|| {
+            de::Error::custom("permissions must be an octal string up to \"777\" like \"660\"")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{ListenAddress, ListenerConfig, RouteGroup};
+
+    #[test]
+    fn test_deserialize_tcp() {
+        let testee = r#"{ "tcp": "127.0.0.1:8080", "routes": ["public"] }"#;
+
+        let listener: ListenerConfig = serde_json::from_str(testee).expect("Failed parsing json");
+
+        assert!(matches!(listener.address, ListenAddress::Tcp(addr) if addr.port() == 8080));
+        assert_eq!(listener.routes, BTreeSet::from([RouteGroup::Public]));
+    }
+
+    #[test]
+    fn test_deserialize_unix() {
+        let testee = r#"{
+            "unix": { "path": "/run/oxidecaptcha.sock", "permissions": "660" },
+            "routes": ["backend", "admin"]
+        }"#;
+
+        let listener: ListenerConfig = serde_json::from_str(testee).expect("Failed parsing json");
+
+        let ListenAddress::Unix(unix) = listener.address else {
+            panic!("Expected a unix listener");
+        };
+
+        assert_eq!(unix.path.to_str(), Some("/run/oxidecaptcha.sock"));
+        assert_eq!(unix.permissions, Some(0o660));
+        assert_eq!(
+            listener.routes,
+            BTreeSet::from([RouteGroup::Backend, RouteGroup::Admin])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_default_routes() {
+        let testee = r#"{ "tcp": "127.0.0.1:8080" }"#;
+
+        let listener: ListenerConfig = serde_json::from_str(testee).expect("Failed parsing json");
+
+        assert_eq!(listener.routes, RouteGroup::all());
+    }
+
+    #[test]
+    fn test_deserialize_bad_permissions() {
+        let testee = r#"{ "unix": { "path": "/tmp/a.sock", "permissions": "rw" } }"#;
+
+        serde_json::from_str::<ListenerConfig>(testee).expect_err("Parsed invalid permissions");
+
+        let testee = r#"{ "unix": { "path": "/tmp/a.sock", "permissions": "7777" } }"#;
+
+        serde_json::from_str::<ListenerConfig>(testee).expect_err("Parsed special mode bits");
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Deserializer};
+use tracing_subscriber::filter::Targets;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OtlpConfig {
+    pub endpoint: String,
+    #[serde(rename = "serviceName", default = "default_service_name")]
+    pub service_name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// `RUST_LOG` style directives like `info,oxidecaptcha=debug`, without
+    /// span or field filters. `RUST_LOG` takes precedence, defaults to `info`.
+    #[serde(default, deserialize_with = "deserialize_level")]
+    pub level: Option<Targets>,
+    #[serde(default)]
+    pub otlp: Option<OtlpConfig>,
+}
+
+fn deserialize_level<'de, D>(deserializer: D) -> Result<Option<Targets>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let level = String::deserialize(deserializer)?;
+
+    level.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+fn default_service_name() -> String {
+    "oxidecaptcha".to_string()
+}
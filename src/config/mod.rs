@@ -1,8 +1,28 @@
-use std::net::SocketAddr;
+use std::path::Path;
 
+use anyhow::{Context, Result};
+
+mod auditconfig;
+mod authconfig;
 mod inmemoryconfig;
+mod legacyroutesconfig;
+mod listenerconfig;
+mod loggingconfig;
+mod proxyconfig;
+mod verificationconfig;
+mod webhookconfig;
+mod widgetconfig;
 
-pub use inmemoryconfig::InMemoryConfig;
+pub use auditconfig::AuditConfig;
+pub use authconfig::{AuthConfig, BruteForceConfig};
+pub use inmemoryconfig::{HousekeepingConfig, InMemoryConfig};
+pub use legacyroutesconfig::LegacyRoutesConfig;
+pub use listenerconfig::{ListenAddress, ListenerConfig, RouteGroup, UnixSocketConfig};
+pub use loggingconfig::{LogFormat, LoggingConfig, OtlpConfig};
+pub use proxyconfig::{normalize_path, ProxyConfig, ProxySiteMapping};
+pub use verificationconfig::VerificationConfig;
+pub use webhookconfig::WebhookDeliveryConfig;
+pub use widgetconfig::WidgetConfig;
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -11,19 +31,168 @@ pub enum StorageTypeConfig {
     Memory(InMemoryConfig),
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorFormat {
+    #[default]
+    Default,
+    ProblemJson,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
-    #[serde(rename = "listenSocket")]
-    listen_socket: SocketAddr,
-    storage: StorageTypeConfig,
+    listeners: Vec<ListenerConfig>,
+    /// Optional when the storage is supplied with [`crate::RouterBuilder::storage`]
+    /// or [`crate::Application::with_storage`]
+    #[serde(default)]
+    storage: Option<StorageTypeConfig>,
+    #[serde(rename = "errorFormat", default)]
+    error_format: ErrorFormat,
+    #[serde(default)]
+    logging: LoggingConfig,
+    #[serde(default)]
+    proxy: Option<ProxyConfig>,
+    #[serde(default)]
+    widget: Option<WidgetConfig>,
+    #[serde(default)]
+    verification: VerificationConfig,
+    #[serde(default)]
+    webhooks: WebhookDeliveryConfig,
+    #[serde(default)]
+    auth: AuthConfig,
+    #[serde(default)]
+    audit: Option<AuditConfig>,
+    #[serde(rename = "legacyRoutes", default)]
+    legacy_routes: LegacyRoutesConfig,
 }
 
 impl Config {
-    pub fn get_storage(&self) -> &StorageTypeConfig {
-        &self.storage
+    pub fn new(storage: StorageTypeConfig) -> Self {
+        Self {
+            storage: Some(storage),
+            ..Self::without_storage()
+        }
+    }
+
+    /// Config for a storage supplied in code, see [`crate::RouterBuilder::storage`]
+    pub fn without_storage() -> Self {
+        Self {
+            listeners: Vec::new(),
+            storage: None,
+            error_format: ErrorFormat::default(),
+            logging: LoggingConfig::default(),
+            proxy: None,
+            widget: None,
+            verification: VerificationConfig::default(),
+            webhooks: WebhookDeliveryConfig::default(),
+            auth: AuthConfig::default(),
+            audit: None,
+            legacy_routes: LegacyRoutesConfig::default(),
+        }
+    }
+
+    pub fn with_listener(mut self, listener: ListenerConfig) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    pub fn with_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    pub fn with_logging(mut self, logging: LoggingConfig) -> Self {
+        self.logging = logging;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn with_widget(mut self, widget: WidgetConfig) -> Self {
+        self.widget = Some(widget);
+        self
+    }
+
+    pub fn with_verification(mut self, verification: VerificationConfig) -> Self {
+        self.verification = verification;
+        self
+    }
+
+    pub fn with_webhooks(mut self, webhooks: WebhookDeliveryConfig) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_audit(mut self, audit: AuditConfig) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn with_legacy_routes(mut self, legacy_routes: LegacyRoutesConfig) -> Self {
+        self.legacy_routes = legacy_routes;
+        self
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to open {}", path.display()))?;
+        let config: Config = serde_json::from_str(&config)
+            .with_context(|| format!("Unable to parse {}", path.display()))?;
+
+        Ok(config)
+    }
+
+    pub fn get_storage(&self) -> Option<&StorageTypeConfig> {
+        self.storage.as_ref()
+    }
+
+    pub fn get_listeners(&self) -> &[ListenerConfig] {
+        &self.listeners
+    }
+
+    pub fn get_error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+
+    pub fn get_logging(&self) -> &LoggingConfig {
+        &self.logging
+    }
+
+    pub fn get_proxy(&self) -> Option<&ProxyConfig> {
+        self.proxy.as_ref()
+    }
+
+    pub fn get_widget(&self) -> Option<&WidgetConfig> {
+        self.widget.as_ref()
+    }
+
+    pub fn get_verification(&self) -> &VerificationConfig {
+        &self.verification
+    }
+
+    pub fn get_webhooks(&self) -> &WebhookDeliveryConfig {
+        &self.webhooks
+    }
+
+    pub fn get_auth(&self) -> &AuthConfig {
+        &self.auth
+    }
+
+    pub fn get_audit(&self) -> Option<&AuditConfig> {
+        self.audit.as_ref()
     }
 
-    pub fn get_listen_socket(&self) -> &SocketAddr {
-        &self.listen_socket
+    pub fn get_legacy_routes(&self) -> &LegacyRoutesConfig {
+        &self.legacy_routes
     }
 }
@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use axum::http::Uri;
+use kale_duration::AbsoluteDuration;
+use serde::{de, Deserialize, Deserializer};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxySiteMapping {
+    #[serde(rename = "pathPrefix")]
+    pub path_prefix: String,
+    #[serde(rename = "siteId")]
+    pub site_id: Uuid,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(deserialize_with = "deserialize_uri")]
+    pub upstream: Uri,
+    /// Prefix of the clearance cookies, each site gets its own cookie
+    #[serde(rename = "cookieName", default = "default_cookie_name")]
+    pub cookie_name: String,
+    #[serde(rename = "secureCookie", default)]
+    pub secure_cookie: bool,
+    #[serde(rename = "upstreamTimeout", default = "default_upstream_timeout")]
+    pub upstream_timeout: AbsoluteDuration,
+    /// Path prefixes forwarded without a clearance cookie
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+    /// Path prefixes protected by a site, the longest matching prefix wins
+    pub sites: Vec<ProxySiteMapping>,
+}
+
+impl ProxyConfig {
+    /// Whether a path returned by [`normalize_path`] is forwarded without a
+    /// clearance cookie
+    pub fn is_allowlisted(&self, path: &str) -> bool {
+        self.allowlist
+            .iter()
+            .any(|prefix| matches_prefix(path, prefix))
+    }
+
+    /// Site protecting a path returned by [`normalize_path`]
+    pub fn site_for_path(&self, path: &str) -> Option<&Uuid> {
+        self.sites
+            .iter()
+            .filter(|mapping| matches_prefix(path, &mapping.path_prefix))
+            .max_by_key(|mapping| mapping.path_prefix.len())
+            .map(|mapping| &mapping.site_id)
+    }
+
+    /// Name of the clearance cookie of a site
+    pub fn cookie_name_for(&self, site_id: &Uuid) -> String {
+        format!("{}_{}", self.cookie_name, site_id.simple())
+    }
+
+    /// Whether `name` is the clearance cookie of any site
+    pub fn is_clearance_cookie(&self, name: &str) -> bool {
+        match name.strip_prefix(&self.cookie_name) {
+            Some("") => true,
+            Some(rest) => rest
+                .strip_prefix('_')
+                .is_some_and(|site_id| Uuid::try_parse(site_id).is_ok()),
+            None => false,
+        }
+    }
+
+    pub fn get_upstream_timeout(&self) -> Duration {
+        self.upstream_timeout.into()
+    }
+}
+
+/// Percent-decodes `path` and drops empty segments, so prefixes are matched
+/// against the path the upstream serves. Paths with dot segments or encoded
+/// separators are refused, the upstream could resolve them to another path.
+pub fn normalize_path(path: &str) -> Option<String> {
+    let mut segments = Vec::new();
+
+    for segment in path.split('/') {
+        let segment = percent_decode(segment)?;
+
+        if segment == "." || segment == ".." || segment.contains(['/', '\\']) {
+            return None;
+        }
+
+        if !segment.is_empty() {
+            segments.push(segment);
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+
+    if path.ends_with('/') && !segments.is_empty() {
+        normalized.push('/');
+    }
+
+    Some(normalized)
+}
+
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Matches whole segments, `/login` covers `/login` and `/login/form` but not
+/// `/loginx`
+fn matches_prefix(path: &str, prefix: &str) -> bool {
+    if prefix.ends_with('/') && path == prefix.trim_end_matches('/') {
+        return true;
+    }
+
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || prefix.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+fn default_cookie_name() -> String {
+    "oxidecaptcha_clearance".to_string()
+}
+
+fn default_upstream_timeout() -> AbsoluteDuration {
+    Duration::from_secs(30).into()
+}
+
+fn deserialize_uri<'de, D>(deserializer: D) -> Result<Uri, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    let uri: Uri = value
+        .parse()
+        .map_err(|_| de::Error::custom("upstream must be a valid uri"))?;
+
+    if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+        return Err(de::Error::custom("upstream must be an absolute http uri"));
+    }
+
+    Ok(uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::uuid;
+
+    use super::{normalize_path, ProxyConfig};
+
+    fn config() -> ProxyConfig {
+        let testee = r#"{
+            "upstream": "http://127.0.0.1:3000",
+            "allowlist": ["/static/", "/robots.txt"],
+            "sites": [
+                { "pathPrefix": "/", "siteId": "60601796-7dc2-4d4f-afae-5728592bba6f" },
+                { "pathPrefix": "/login", "siteId": "0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e" }
+            ]
+        }"#;
+
+        serde_json::from_str(testee).expect("Failed parsing json")
+    }
+
+    #[test]
+    fn test_deserialize_defaults() {
+        let config = config();
+
+        assert_eq!(config.cookie_name, "oxidecaptcha_clearance");
+        assert!(!config.secure_cookie);
+        assert_eq!(config.get_upstream_timeout().as_secs(), 30);
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let config = config();
+
+        assert_eq!(
+            config.site_for_path("/login/form"),
+            Some(&uuid!("0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e"))
+        );
+        assert_eq!(
+            config.site_for_path("/index.html"),
+            Some(&uuid!("60601796-7dc2-4d4f-afae-5728592bba6f"))
+        );
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let config = config();
+
+        assert!(config.is_allowlisted("/static/app.js"));
+        assert!(!config.is_allowlisted("/index.html"));
+        assert!(!config.is_allowlisted("/robots.txt.bak"));
+    }
+
+    #[test]
+    fn test_segment_boundaries() {
+        let config = config();
+
+        assert_eq!(
+            config.site_for_path("/login"),
+            Some(&uuid!("0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e"))
+        );
+        assert_eq!(
+            config.site_for_path("/loginx"),
+            Some(&uuid!("60601796-7dc2-4d4f-afae-5728592bba6f"))
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/static//app.js").as_deref(), Some("/static/app.js"));
+        assert_eq!(normalize_path("/%6cogin/").as_deref(), Some("/login/"));
+        assert_eq!(normalize_path("/").as_deref(), Some("/"));
+
+        for bypass in [
+            "/static/../admin",
+            "/static/%2e%2e/admin",
+            "/static/%2E%2e/admin",
+            "/static/./app.js",
+            "/static%2f..%2fadmin",
+            "/static/..%5cadmin",
+            "/static/%zz",
+        ] {
+            assert_eq!(normalize_path(bypass), None, "{bypass}");
+        }
+    }
+
+    #[test]
+    fn test_clearance_cookie() {
+        let config = config();
+        let site_id = uuid!("60601796-7dc2-4d4f-afae-5728592bba6f");
+
+        let name = config.cookie_name_for(&site_id);
+
+        assert_eq!(name, "oxidecaptcha_clearance_606017967dc24d4fafae5728592bba6f");
+        assert!(config.is_clearance_cookie(&name));
+        assert!(!config.is_clearance_cookie("oxidecaptcha_clearance_theme"));
+        assert!(!config.is_clearance_cookie("session"));
+    }
+
+    #[test]
+    fn test_reject_https_upstream() {
+        let testee = r#"{ "upstream": "https://example.com", "sites": [] }"#;
+
+        serde_json::from_str::<ProxyConfig>(testee).expect_err("Accepted https upstream");
+    }
+}
@@ -0,0 +1,37 @@
+use std::{num::NonZeroUsize, thread};
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VerificationConfig {
+    /// Solution sets verified at the same time on the blocking pool, defaults
+    /// to the number of cpus
+    #[serde(default = "default_concurrency")]
+    pub concurrency: NonZeroUsize,
+    /// Threads sharing the candidates of one solution set, defaults to the
+    /// cpus left per set by `concurrency`
+    #[serde(rename = "threadsPerSet", default)]
+    pub threads_per_set: Option<NonZeroUsize>,
+}
+
+impl VerificationConfig {
+    pub fn get_threads_per_set(&self) -> usize {
+        match self.threads_per_set {
+            Some(threads) => threads.get(),
+            None => (default_concurrency().get() / self.concurrency.get()).max(1),
+        }
+    }
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: default_concurrency(),
+            threads_per_set: None,
+        }
+    }
+}
+
+fn default_concurrency() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
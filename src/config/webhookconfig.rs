@@ -0,0 +1,66 @@
+use std::{num::NonZeroU32, time::Duration};
+
+use kale_duration::AbsoluteDuration;
+use serde::Deserialize;
+
+/// Delivery settings shared by the webhooks of all sites
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookDeliveryConfig {
+    /// Events waiting for delivery, further events are dropped
+    #[serde(rename = "queueSize", default = "default_queue_size")]
+    pub queue_size: usize,
+    /// Requests in flight at the same time, deliveries waiting for a retry
+    /// don't take up a slot
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Attempts per delivery, at least one
+    #[serde(rename = "maxAttempts", default = "default_max_attempts")]
+    pub max_attempts: NonZeroU32,
+    /// Doubled after each failed attempt
+    #[serde(rename = "initialBackoff", default = "default_initial_backoff")]
+    pub initial_backoff: AbsoluteDuration,
+    #[serde(default = "default_timeout")]
+    pub timeout: AbsoluteDuration,
+}
+
+impl WebhookDeliveryConfig {
+    pub fn get_initial_backoff(&self) -> Duration {
+        self.initial_backoff.into()
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout.into()
+    }
+}
+
+impl Default for WebhookDeliveryConfig {
+    fn default() -> Self {
+        Self {
+            queue_size: default_queue_size(),
+            concurrency: default_concurrency(),
+            max_attempts: default_max_attempts(),
+            initial_backoff: default_initial_backoff(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+fn default_queue_size() -> usize {
+    1024
+}
+
+fn default_concurrency() -> usize {
+    16
+}
+
+fn default_max_attempts() -> NonZeroU32 {
+    NonZeroU32::new(5).expect("5 is not zero")
+}
+
+fn default_initial_backoff() -> AbsoluteDuration {
+    Duration::from_secs(1).into()
+}
+
+fn default_timeout() -> AbsoluteDuration {
+    Duration::from_secs(5).into()
+}
@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// Serves the browser widget under `/widget` on listeners with the public
+/// routes
+#[derive(Debug, Clone, Deserialize)]
+pub struct WidgetConfig {
+    /// Directory holding `oxidecaptcha.js`, `worker.js` and the wasm-pack
+    /// output in `pkg/`
+    pub directory: PathBuf,
+}
@@ -1,22 +1,48 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 use axum::{
     body::Body,
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
     http::{Response, StatusCode},
     response::IntoResponse,
 };
 use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-#[derive(Debug)]
+use crate::challenge::Challenge;
+
+mod problem;
+
+pub use problem::ProblemDetails;
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
 pub enum ErrorId {
     MissingApiKey,
     WrongApiKey,
+    ExpiredApiKey,
+    InsufficientScope,
+    TooManyFailedAttempts,
+    InvalidSignature,
+    OriginNotAllowed,
     SiteNotFound,
+    #[serde(rename = "ChallengeNotFound")]
     ChallangeNotFound,
     SolutionWrongSize,
     WrongNumberOfSolutions,
     InternalServerError,
     Timeout,
+    PassTokenRequired,
+    BadGateway,
+    InvalidAction,
+    InvalidBatchSize,
+    UnsupportedMediaType,
+    InvalidBody,
+    InvalidPath,
+    PayloadTooLarge,
+    Overloaded,
+    RouteNotFound,
+    InvalidQuery,
 }
 
 impl From<ErrorId> for StatusCode {
@@ -24,45 +50,197 @@ impl From<ErrorId> for StatusCode {
         match value {
             ErrorId::MissingApiKey => StatusCode::UNAUTHORIZED,
             ErrorId::WrongApiKey => StatusCode::UNAUTHORIZED,
+            ErrorId::ExpiredApiKey => StatusCode::UNAUTHORIZED,
+            ErrorId::InsufficientScope => StatusCode::FORBIDDEN,
+            ErrorId::TooManyFailedAttempts => StatusCode::TOO_MANY_REQUESTS,
+            ErrorId::InvalidSignature => StatusCode::UNAUTHORIZED,
+            ErrorId::OriginNotAllowed => StatusCode::FORBIDDEN,
             ErrorId::SiteNotFound => StatusCode::NOT_FOUND,
             ErrorId::ChallangeNotFound => StatusCode::NOT_FOUND,
             ErrorId::SolutionWrongSize => StatusCode::BAD_REQUEST,
             ErrorId::WrongNumberOfSolutions => StatusCode::BAD_REQUEST,
             ErrorId::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             ErrorId::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorId::PassTokenRequired => StatusCode::UNAUTHORIZED,
+            ErrorId::BadGateway => StatusCode::BAD_GATEWAY,
+            ErrorId::InvalidAction => StatusCode::BAD_REQUEST,
+            ErrorId::InvalidBatchSize => StatusCode::BAD_REQUEST,
+            ErrorId::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorId::InvalidBody => StatusCode::BAD_REQUEST,
+            ErrorId::InvalidPath => StatusCode::BAD_REQUEST,
+            ErrorId::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorId::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorId::RouteNotFound => StatusCode::NOT_FOUND,
+            ErrorId::InvalidQuery => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl From<ErrorId> for tonic::Code {
+    fn from(value: ErrorId) -> Self {
+        match value {
+            ErrorId::MissingApiKey => tonic::Code::Unauthenticated,
+            ErrorId::WrongApiKey => tonic::Code::Unauthenticated,
+            ErrorId::ExpiredApiKey => tonic::Code::Unauthenticated,
+            ErrorId::InsufficientScope => tonic::Code::PermissionDenied,
+            ErrorId::TooManyFailedAttempts => tonic::Code::ResourceExhausted,
+            ErrorId::InvalidSignature => tonic::Code::Unauthenticated,
+            ErrorId::OriginNotAllowed => tonic::Code::PermissionDenied,
+            ErrorId::SiteNotFound => tonic::Code::NotFound,
+            ErrorId::ChallangeNotFound => tonic::Code::NotFound,
+            ErrorId::SolutionWrongSize => tonic::Code::InvalidArgument,
+            ErrorId::WrongNumberOfSolutions => tonic::Code::InvalidArgument,
+            ErrorId::InternalServerError => tonic::Code::Internal,
+            ErrorId::Timeout => tonic::Code::DeadlineExceeded,
+            ErrorId::PassTokenRequired => tonic::Code::Unauthenticated,
+            ErrorId::BadGateway => tonic::Code::Unavailable,
+            ErrorId::InvalidAction => tonic::Code::InvalidArgument,
+            ErrorId::InvalidBatchSize => tonic::Code::InvalidArgument,
+            ErrorId::UnsupportedMediaType => tonic::Code::InvalidArgument,
+            ErrorId::InvalidBody => tonic::Code::InvalidArgument,
+            ErrorId::InvalidPath => tonic::Code::InvalidArgument,
+            ErrorId::PayloadTooLarge => tonic::Code::ResourceExhausted,
+            ErrorId::Overloaded => tonic::Code::Unavailable,
+            ErrorId::RouteNotFound => tonic::Code::NotFound,
+            ErrorId::InvalidQuery => tonic::Code::InvalidArgument,
         }
     }
 }
 
-impl Serialize for ErrorId {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
+impl ErrorId {
+    pub fn title(&self) -> &'static str {
         match self {
-            ErrorId::MissingApiKey => serializer.serialize_str("MissingApiKey"),
-            ErrorId::WrongApiKey => serializer.serialize_str("WrongApiKey"),
-            ErrorId::SiteNotFound => serializer.serialize_str("SiteNotFound"),
-            ErrorId::ChallangeNotFound => serializer.serialize_str("ChallengeNotFound"),
-            ErrorId::SolutionWrongSize => serializer.serialize_str("SolutionWrongSize"),
-            ErrorId::WrongNumberOfSolutions => serializer.serialize_str("WrongNumberOfSolutions"),
-            ErrorId::InternalServerError => serializer.serialize_str("InternalServerError"),
-            ErrorId::Timeout => serializer.serialize_str("Timeout"),
+            ErrorId::MissingApiKey => "Api-key missing",
+            ErrorId::WrongApiKey => "Api-key wrong",
+            ErrorId::ExpiredApiKey => "Api-key expired",
+            ErrorId::InsufficientScope => "Api-key lacks the required scope",
+            ErrorId::TooManyFailedAttempts => "Too many failed attempts",
+            ErrorId::InvalidSignature => "Request signature invalid",
+            ErrorId::OriginNotAllowed => "Origin not allowed",
+            ErrorId::SiteNotFound => "Site not found",
+            ErrorId::ChallangeNotFound => "Challenge not found",
+            ErrorId::SolutionWrongSize => "Solution has the wrong size",
+            ErrorId::WrongNumberOfSolutions => "Wrong number of solutions",
+            ErrorId::InternalServerError => "Internal server error",
+            ErrorId::Timeout => "Timeout",
+            ErrorId::PassTokenRequired => "Pass token required",
+            ErrorId::BadGateway => "Upstream unavailable",
+            ErrorId::InvalidAction => "Invalid action",
+            ErrorId::InvalidBatchSize => "Invalid batch size",
+            ErrorId::UnsupportedMediaType => "Unsupported media type",
+            ErrorId::InvalidBody => "Invalid request body",
+            ErrorId::InvalidPath => "Invalid request path",
+            ErrorId::PayloadTooLarge => "Request body too large",
+            ErrorId::Overloaded => "Server overloaded",
+            ErrorId::RouteNotFound => "Route not found",
+            ErrorId::InvalidQuery => "Invalid query string",
         }
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct ErrorResponse {
     id: ErrorId,
     context: String,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Challenge to solve to obtain a pass token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    challenge: Option<Box<Challenge>>,
+    #[serde(skip)]
+    site_id: Option<Uuid>,
+    #[serde(skip)]
+    challenge_id: Option<Uuid>,
+    #[serde(skip)]
+    retry_after: Option<Duration>,
 }
 
 impl ErrorResponse {
     pub fn new(id: ErrorId, context: impl Display) -> Self {
         let context = context.to_string();
 
-        Self { id, context }
+        Self {
+            id,
+            context,
+            request_id: None,
+            challenge: None,
+            site_id: None,
+            challenge_id: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn with_site(mut self, site_id: &Uuid) -> Self {
+        self.site_id = Some(*site_id);
+        self
+    }
+
+    pub fn with_challenge(mut self, challenge_id: &Uuid) -> Self {
+        self.challenge_id = Some(*challenge_id);
+        self
+    }
+
+    pub fn with_issued_challenge(mut self, challenge: Challenge) -> Self {
+        self.challenge = Some(Box::new(challenge));
+        self
+    }
+
+    /// Sent as `Retry-After`, rounded up to whole seconds
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn get_id(&self) -> ErrorId {
+        self.id
+    }
+
+    pub fn get_context(&self) -> &str {
+        &self.context
+    }
+
+    pub fn get_request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    pub fn get_site_id(&self) -> Option<&Uuid> {
+        self.site_id.as_ref()
+    }
+
+    pub fn get_challenge_id(&self) -> Option<&Uuid> {
+        self.challenge_id.as_ref()
+    }
+
+    pub fn get_retry_after(&self) -> Option<&Duration> {
+        self.retry_after.as_ref()
+    }
+}
+
+impl From<JsonRejection> for ErrorResponse {
+    fn from(rejection: JsonRejection) -> Self {
+        let id = match rejection {
+            JsonRejection::MissingJsonContentType(_) => ErrorId::UnsupportedMediaType,
+            _ => ErrorId::InvalidBody,
+        };
+
+        Self::new(id, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ErrorResponse {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(ErrorId::InvalidPath, rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ErrorResponse {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(ErrorId::InvalidQuery, rejection.body_text())
     }
 }
 
@@ -72,10 +250,19 @@ impl IntoResponse for ErrorResponse {
             .expect("Unable to build json")
             .into();
 
-        Response::builder()
+        let mut response = Response::builder()
             .header("Content-Type", "application/json")
-            .status(StatusCode::from(self.id))
-            .body(body)
-            .expect("Unable to build response")
+            .status(StatusCode::from(self.id));
+
+        if let Some(retry_after) = self.retry_after {
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response = response.header("Retry-After", seconds);
+        }
+
+        let mut response = response.body(body).expect("Unable to build response");
+
+        response.extensions_mut().insert(self);
+
+        response
     }
 }
@@ -0,0 +1,120 @@
+use axum::{
+    body::Body,
+    http::{Response, StatusCode},
+    response::IntoResponse,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::ErrorResponse;
+
+const PROBLEM_TYPE_PREFIX: &str = "urn:oxidecaptcha:problem:";
+
+/// RFC 7807 representation of an [`ErrorResponse`]
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: String,
+    status: u16,
+    detail: String,
+    instance: String,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::wire::serialize_optional_id"
+    )]
+    site_id: Option<Uuid>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::wire::serialize_optional_id"
+    )]
+    challenge_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+impl ProblemDetails {
+    pub fn new(error: &ErrorResponse, instance: impl Into<String>) -> Self {
+        let id = error.get_id();
+
+        let name = serde_json::to_value(id)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_owned))
+            .unwrap_or_default();
+
+        Self {
+            problem_type: format!("{PROBLEM_TYPE_PREFIX}{name}"),
+            title: id.title().to_string(),
+            status: StatusCode::from(id).as_u16(),
+            detail: error.get_context().to_string(),
+            instance: instance.into(),
+            site_id: error.get_site_id().copied(),
+            challenge_id: error.get_challenge_id().copied(),
+            request_id: error.get_request_id().map(str::to_owned),
+        }
+    }
+}
+
+impl IntoResponse for ProblemDetails {
+    fn into_response(self) -> axum::response::Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body: Body = serde_json::to_string(&self)
+            .expect("Unable to build json")
+            .into();
+
+        Response::builder()
+            .header("Content-Type", "application/problem+json")
+            .status(status)
+            .body(body)
+            .expect("Unable to build response")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::uuid;
+
+    use crate::error_response::{ErrorId, ErrorResponse};
+
+    use super::ProblemDetails;
+
+    #[test]
+    fn test_serialize() {
+        let error = ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge not Found")
+            .with_site(&uuid!("60601796-7dc2-4d4f-afae-5728592bba6f"))
+            .with_challenge(&uuid!("0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e"))
+            .with_request_id("abc");
+
+        let problem = ProblemDetails::new(&error, "/v1/site/x/challenge/y");
+
+        assert_eq!(
+            serde_json::to_value(problem).expect("Unable to serialize"),
+            json!({
+                "type": "urn:oxidecaptcha:problem:ChallengeNotFound",
+                "title": "Challenge not found",
+                "status": 404,
+                "detail": "Challenge not Found",
+                "instance": "/v1/site/x/challenge/y",
+                "siteId": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "challengeId": "0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e",
+                "requestId": "abc"
+            })
+        );
+    }
+
+    #[test]
+    fn test_serialize_without_extensions() {
+        let error = ErrorResponse::new(ErrorId::Timeout, "Application has timed-out");
+
+        let problem = serde_json::to_value(ProblemDetails::new(&error, "/v1/health"))
+            .expect("Unable to serialize");
+
+        assert_eq!(problem["status"], 503);
+        assert!(problem.get("siteId").is_none());
+        assert!(problem.get("requestId").is_none());
+    }
+}
@@ -0,0 +1,532 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{extract::ConnectInfo, http::HeaderValue};
+use bytes::Bytes;
+use tonic::{metadata::MetadataValue, Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{
+    auth::{ApiKeyEntry, Scope},
+    challenge::Challenge,
+    error_response::{ErrorId, ErrorResponse},
+    middleware::{authenticate, find_challenge, record_failure, record_key_use},
+    routes::{delete, is_valid_action, issue_challenge, validate, ResponseBody},
+    site::Site,
+    solution::Solution,
+    state::State,
+    storage::Storage,
+};
+
+use proto::{
+    captcha_server::{Captcha, CaptchaServer},
+    DeleteChallengeRequest, DeleteChallengeResponse, HealthRequest, HealthResponse,
+    IssueChallengeRequest, ValidateChallengeRequest, ValidateChallengeResponse,
+};
+
+/// Generated from `proto/oxidecaptcha/v1/captcha.proto`
+pub mod proto {
+    tonic::include_proto!("oxidecaptcha.v1");
+}
+
+/// Metadata carrying the api-key, named like the http header
+const API_KEY_METADATA: &str = "api-key";
+
+/// Metadata naming the [`ErrorId`] of a failed call
+pub const ERROR_ID_METADATA: &str = "error-id";
+
+/// Serves the `Captcha` service for listeners with the `grpc` route group
+pub(crate) fn router(state: &State) -> axum::Router {
+    let service = CaptchaServer::new(CaptchaService {
+        state: state.clone(),
+    });
+
+    tonic::service::Routes::new(service).into_axum_router()
+}
+
+struct CaptchaService {
+    state: State,
+}
+
+impl CaptchaService {
+    async fn get_site(&self, site_id: &str) -> Result<Site, ErrorResponse> {
+        let site_id: Uuid = site_id
+            .parse()
+            .map_err(|_| ErrorResponse::new(ErrorId::SiteNotFound, "Site not found"))?;
+
+        self.state
+            .get_storage()
+            .await
+            .get_site(&site_id)
+            .await
+            .ok_or_else(|| {
+                ErrorResponse::new(ErrorId::SiteNotFound, "Site not found").with_site(&site_id)
+            })
+    }
+
+    async fn get_challenge(
+        &self,
+        site: &Site,
+        challenge_id: &str,
+    ) -> Result<Challenge, ErrorResponse> {
+        let challenge_id: Uuid = challenge_id.parse().map_err(|_| {
+            ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge not Found")
+                .with_site(site.get_id())
+        })?;
+
+        find_challenge(&self.state, site, &challenge_id).await
+    }
+
+    /// Same api-key checks as the `auth_middleware`, with the key taken from
+    /// the metadata
+    async fn authenticate<T>(
+        &self,
+        site: &Site,
+        request: &Request<T>,
+        scope: Scope,
+        method: &str,
+    ) -> Result<ApiKeyEntry, ErrorResponse> {
+        let key = request
+            .metadata()
+            .get(API_KEY_METADATA)
+            .and_then(|key| HeaderValue::from_bytes(key.as_bytes()).ok());
+
+        let client = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip());
+
+        let entry = authenticate(&self.state, site, key, scope, client).await?;
+
+        // Signatures cover the http body, which has no stable form in protobuf
+        if site.get_request_signing().is_some() {
+            return Err(self.signing_unsupported(site, client));
+        }
+
+        self.state.get_brute_force().record_success(site.get_id(), client);
+
+        record_key_use(&self.state, site, &entry, format!("gRPC {method}"));
+
+        Ok(entry)
+    }
+
+    fn signing_unsupported(&self, site: &Site, client: Option<IpAddr>) -> ErrorResponse {
+        let error = ErrorResponse::new(
+            ErrorId::InvalidSignature,
+            "Sites with requestSigning can't be used over gRPC",
+        )
+        .with_site(site.get_id());
+
+        record_failure(&self.state, site, client, error)
+    }
+}
+
+#[tonic::async_trait]
+impl Captcha for CaptchaService {
+    async fn issue_challenge(
+        &self,
+        request: Request<IssueChallengeRequest>,
+    ) -> Result<Response<proto::Challenge>, Status> {
+        let request = request.into_inner();
+
+        let site = self.get_site(&request.site_id).await?;
+
+        if let Some(action) = &request.action {
+            if !is_valid_action(action) {
+                return Err(ErrorResponse::new(ErrorId::InvalidAction, "Invalid action")
+                    .with_site(site.get_id())
+                    .into());
+            }
+        }
+
+        let challenge = issue_challenge(&self.state, &site, None, request.action).await?;
+
+        Ok(Response::new(proto::Challenge::from(&challenge)))
+    }
+
+    async fn validate_challenge(
+        &self,
+        request: Request<ValidateChallengeRequest>,
+    ) -> Result<Response<ValidateChallengeResponse>, Status> {
+        let site = self.get_site(&request.get_ref().site_id).await?;
+
+        self.authenticate(&site, &request, Scope::Validate, "ValidateChallenge")
+            .await?;
+
+        let request = request.into_inner();
+
+        let challenge = self.get_challenge(&site, &request.challenge_id).await?;
+
+        let solutions = request
+            .solutions
+            .into_iter()
+            .map(|solution| {
+                solution
+                    .value
+                    .map(|value| Solution::new(Bytes::from(value)))
+            })
+            .collect();
+
+        let response = validate(&self.state, &site, &challenge, solutions, request.details).await?;
+
+        Ok(Response::new(response.into()))
+    }
+
+    async fn delete_challenge(
+        &self,
+        request: Request<DeleteChallengeRequest>,
+    ) -> Result<Response<DeleteChallengeResponse>, Status> {
+        let site = self.get_site(&request.get_ref().site_id).await?;
+
+        let entry = self
+            .authenticate(&site, &request, Scope::Delete, "DeleteChallenge")
+            .await?;
+
+        let challenge = self
+            .get_challenge(&site, &request.get_ref().challenge_id)
+            .await?;
+
+        delete(&self.state, &site, &challenge, &entry).await?;
+
+        Ok(Response::new(DeleteChallengeResponse {}))
+    }
+
+    async fn health(
+        &self,
+        _request: Request<HealthRequest>,
+    ) -> Result<Response<HealthResponse>, Status> {
+        if !self.state.get_storage().await.healthy().await {
+            return Err(
+                ErrorResponse::new(ErrorId::InternalServerError, "Storage is not healthy").into(),
+            );
+        }
+
+        Ok(Response::new(HealthResponse {}))
+    }
+}
+
+impl From<ErrorResponse> for Status {
+    fn from(error: ErrorResponse) -> Self {
+        let id = error.get_id();
+
+        let mut status = Status::new(id.into(), error.get_context());
+
+        if let Some(name) = serde_json::to_value(id)
+            .ok()
+            .and_then(|name| name.as_str().and_then(|name| name.parse().ok()))
+        {
+            status.metadata_mut().insert(ERROR_ID_METADATA, name);
+        }
+
+        if let Some(retry_after) = error.get_retry_after() {
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+            status
+                .metadata_mut()
+                .insert("retry-after", MetadataValue::from(seconds));
+        }
+
+        status
+    }
+}
+
+impl From<&Challenge> for proto::Challenge {
+    fn from(challenge: &Challenge) -> Self {
+        Self {
+            id: challenge.get_id().to_string(),
+            prefixes: challenge
+                .get_prefixes()
+                .iter()
+                .map(|prefix| prefix.get_bytes().to_vec())
+                .collect(),
+            difficulty: challenge.get_difficulty().into(),
+            prefixes_to_solve: challenge.get_prefixes_to_solve() as u32,
+            solution_length: challenge.get_solution_length() as u32,
+            issued_at: challenge.get_issued_at(),
+            expires_at: challenge.get_expires_at(),
+            action: challenge.get_action().map(str::to_string),
+        }
+    }
+}
+
+impl From<ResponseBody> for ValidateChallengeResponse {
+    fn from(response: ResponseBody) -> Self {
+        Self {
+            valid: response.valid,
+            pass_token: response.pass_token,
+            details: response.details.map(|details| proto::ValidationDetails {
+                verified_prefixes: details
+                    .verified_prefixes
+                    .into_iter()
+                    .map(|index| index as u32)
+                    .collect(),
+                prefixes_to_solve: details.prefixes_to_solve as u32,
+                difficulty: details.difficulty.into(),
+                site_id: details.site_id.to_string(),
+                action: details.action,
+                issued_at: details.issued_at,
+                solve_time_ms: details.solve_time_ms,
+                suspiciously_fast: details.suspiciously_fast,
+                origin: details.origin,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use axum::extract::ConnectInfo;
+    use bytes::Bytes;
+    use tonic::{Code, Request};
+
+    use crate::{
+        auth::{ApiKey, ApiKeyEntry, RequestSigningConfig, Scope},
+        challenge::Prefix,
+        config::{AuthConfig, BruteForceConfig, Config, RouteGroup},
+        test_support::{config, site, solve, DIFFICULTY, SITE_ID},
+        RouterBuilder,
+    };
+
+    use super::{
+        proto::{
+            captcha_client::CaptchaClient, DeleteChallengeRequest, HealthRequest,
+            IssueChallengeRequest, ValidateChallengeRequest,
+        },
+        ERROR_ID_METADATA,
+    };
+
+    fn client_with(config: Config) -> CaptchaClient<axum::Router> {
+        let router = RouterBuilder::new(config)
+            .routes([RouteGroup::Grpc])
+            .build()
+            .expect("Unable to build router");
+
+        CaptchaClient::new(router)
+    }
+
+    fn client() -> CaptchaClient<axum::Router> {
+        client_with(config(vec![site()]))
+    }
+
+    async fn challenge_id(client: &mut CaptchaClient<axum::Router>) -> String {
+        client
+            .issue_challenge(IssueChallengeRequest {
+                site_id: SITE_ID.to_string(),
+                action: None,
+            })
+            .await
+            .expect("Unable to issue challenge")
+            .into_inner()
+            .id
+    }
+
+    /// Delete request for a fresh challenge with `key` in the metadata
+    async fn delete_request(
+        client: &mut CaptchaClient<axum::Router>,
+        key: &str,
+    ) -> Request<DeleteChallengeRequest> {
+        let mut request = Request::new(DeleteChallengeRequest {
+            site_id: SITE_ID.to_string(),
+            challenge_id: challenge_id(client).await,
+        });
+        request
+            .metadata_mut()
+            .insert("api-key", key.parse().unwrap());
+
+        request
+    }
+
+    fn solution(prefix: &[u8]) -> super::proto::Solution {
+        let prefix = Prefix::new(Bytes::copy_from_slice(prefix));
+
+        super::proto::Solution {
+            value: Some(solve(&prefix, DIFFICULTY).get_bytes().to_vec()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_validate() {
+        let mut client = client();
+
+        let challenge = client
+            .issue_challenge(IssueChallengeRequest {
+                site_id: SITE_ID.to_string(),
+                action: Some("login".to_string()),
+            })
+            .await
+            .expect("Unable to issue challenge")
+            .into_inner();
+
+        assert_eq!(challenge.prefixes.len(), 4);
+        assert_eq!(challenge.action.as_deref(), Some("login"));
+
+        let validate = ValidateChallengeRequest {
+            site_id: SITE_ID.to_string(),
+            challenge_id: challenge.id.clone(),
+            solutions: challenge
+                .prefixes
+                .iter()
+                .map(|prefix| solution(prefix))
+                .collect(),
+            details: true,
+        };
+
+        let status = client
+            .validate_challenge(validate.clone())
+            .await
+            .expect_err("Validated without api-key");
+
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(
+            status.metadata().get(ERROR_ID_METADATA).unwrap(),
+            "MissingApiKey"
+        );
+
+        let mut request = Request::new(validate.clone());
+        request
+            .metadata_mut()
+            .insert("api-key", "key".parse().unwrap());
+
+        let response = client
+            .validate_challenge(request)
+            .await
+            .expect("Unable to validate")
+            .into_inner();
+
+        assert!(response.valid);
+        assert_eq!(response.details.unwrap().verified_prefixes.len(), 4);
+
+        let mut request = Request::new(validate);
+        request
+            .metadata_mut()
+            .insert("api-key", "key".parse().unwrap());
+
+        let status = client
+            .validate_challenge(request)
+            .await
+            .expect_err("Challenge validated twice");
+
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        client()
+            .health(HealthRequest {})
+            .await
+            .expect("Storage is not healthy");
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_scopes() {
+        let site = site().with_api_keys(vec![
+            ApiKeyEntry::new("cleanup", ApiKey::from_plaintext("cleanup"))
+                .with_scopes(vec![Scope::Delete]),
+            ApiKeyEntry::new("backend", ApiKey::from_plaintext("backend"))
+                .with_scopes(vec![Scope::Validate]),
+        ]);
+        let mut client = client_with(config(vec![site]));
+
+        let request = delete_request(&mut client, "cleanup").await;
+        let challenge_id = request.get_ref().challenge_id.clone();
+
+        client
+            .delete_challenge(request)
+            .await
+            .expect("Unable to delete challenge");
+
+        let mut request = Request::new(DeleteChallengeRequest {
+            site_id: SITE_ID.to_string(),
+            challenge_id,
+        });
+        request
+            .metadata_mut()
+            .insert("api-key", "cleanup".parse().unwrap());
+
+        let status = client
+            .delete_challenge(request)
+            .await
+            .expect_err("Challenge deleted twice");
+
+        assert_eq!(status.code(), Code::NotFound);
+
+        let request = delete_request(&mut client, "backend").await;
+
+        let status = client
+            .delete_challenge(request)
+            .await
+            .expect_err("Deleted without the delete scope");
+
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(
+            status.metadata().get(ERROR_ID_METADATA).unwrap(),
+            "InsufficientScope"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_brute_force_lockout() {
+        let auth = AuthConfig {
+            brute_force: BruteForceConfig {
+                client_attempts: 2,
+                initial_lockout: Duration::from_secs(30).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut client = client_with(config(vec![site()]).with_auth(auth));
+
+        for _ in 0..2 {
+            let mut request = delete_request(&mut client, "wrong").await;
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+            let status = client
+                .delete_challenge(request)
+                .await
+                .expect_err("Deleted with a wrong api-key");
+
+            assert_eq!(status.code(), Code::Unauthenticated);
+        }
+
+        // Locked out even with the right key
+        let mut request = delete_request(&mut client, "key").await;
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let status = client
+            .delete_challenge(request)
+            .await
+            .expect_err("Deleted while locked out");
+
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(
+            status.metadata().get(ERROR_ID_METADATA).unwrap(),
+            "TooManyFailedAttempts"
+        );
+        assert_eq!(status.metadata().get("retry-after").unwrap(), "30");
+    }
+
+    #[tokio::test]
+    async fn test_request_signing_rejected() {
+        let signing =
+            RequestSigningConfig::new("0123456789abcdef0123456789abcdef", Duration::from_secs(60));
+        let mut client = client_with(config(vec![site().with_request_signing(signing)]));
+
+        let request = delete_request(&mut client, "key").await;
+
+        let status = client
+            .delete_challenge(request)
+            .await
+            .expect_err("Deleted without a signature");
+
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(
+            status.metadata().get(ERROR_ID_METADATA).unwrap(),
+            "InvalidSignature"
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    http::{header::COOKIE, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use tower::{Layer, Service, ServiceExt};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::{
+    error_response::{ErrorId, ErrorResponse},
+    pass_token::PassTokenClaims,
+    site::Site,
+    state::State,
+    storage::Storage,
+    webhook::WebhookEvent,
+};
+
+pub const DEFAULT_PASS_TOKEN_HEADER: &str = "x-captcha-pass";
+pub const DEFAULT_PASS_TOKEN_COOKIE: &str = "oxidecaptcha_pass";
+
+/// Handle to a running oxidecaptcha instance, used to create guard layers
+/// sharing its storage
+#[derive(Debug, Clone)]
+pub struct Captcha {
+    state: State,
+}
+
+impl Captcha {
+    pub(crate) fn new(state: State) -> Self {
+        Self { state }
+    }
+
+    /// Creates a layer requiring a pass token for the site `site_id`
+    pub async fn guard(&self, site_id: &Uuid) -> Result<CaptchaGuardLayer, ErrorResponse> {
+        let site = self
+            .state
+            .get_storage()
+            .await
+            .get_site(site_id)
+            .await
+            .ok_or_else(|| {
+                ErrorResponse::new(ErrorId::SiteNotFound, "Site not found").with_site(site_id)
+            })?;
+
+        if site.get_pass_token().is_none() {
+            return Err(ErrorResponse::new(
+                ErrorId::InternalServerError,
+                "Site has no passToken config",
+            )
+            .with_site(site_id));
+        }
+
+        Ok(CaptchaGuardLayer {
+            state: self.state.clone(),
+            site,
+            header: DEFAULT_PASS_TOKEN_HEADER.to_string(),
+            cookie: DEFAULT_PASS_TOKEN_COOKIE.to_string(),
+        })
+    }
+}
+
+pub(crate) fn find_cookie<'a>(headers: &'a HeaderMap, cookie: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == cookie)
+        .map(|(_, value)| value)
+}
+
+/// [`tower::Layer`] rejecting requests without a valid pass token. Rejected
+/// requests get an [`ErrorResponse`] carrying a freshly issued challenge.
+#[derive(Debug, Clone)]
+pub struct CaptchaGuardLayer {
+    state: State,
+    site: Site,
+    header: String,
+    cookie: String,
+}
+
+impl CaptchaGuardLayer {
+    pub fn with_header(mut self, header: impl Into<String>) -> Self {
+        self.header = header.into();
+        self
+    }
+
+    pub fn with_cookie(mut self, cookie: impl Into<String>) -> Self {
+        self.cookie = cookie.into();
+        self
+    }
+
+    fn find_token<'a>(&self, headers: &'a HeaderMap) -> Option<&'a str> {
+        if let Some(token) = headers.get(&self.header).and_then(|v| v.to_str().ok()) {
+            return Some(token);
+        }
+
+        find_cookie(headers, &self.cookie)
+    }
+
+    fn verify(&self, headers: &HeaderMap) -> Option<PassTokenClaims> {
+        let config = self.site.get_pass_token()?;
+        let token = self.find_token(headers)?;
+
+        config
+            .verify(self.site.get_id(), token)
+            .map_err(|e| debug!("Rejected pass token: {}", e))
+            .ok()
+    }
+
+    async fn challenge_response(state: State, site: Site) -> Response {
+        let challenge = site.generate_challenge();
+
+        let stored = state
+            .get_storage()
+            .await
+            .store_challenge(&site, &challenge)
+            .await;
+
+        if stored.is_ok() {
+            state
+                .get_webhooks()
+                .emit(&site, WebhookEvent::ChallengeIssued, Some(challenge.get_id()));
+        }
+
+        match stored {
+            Ok(()) => ErrorResponse::new(ErrorId::PassTokenRequired, "Solve the challenge first")
+                .with_site(site.get_id())
+                .with_issued_challenge(challenge)
+                .into_response(),
+            Err(_) => ErrorResponse::new(ErrorId::SiteNotFound, "Site not found")
+                .with_site(site.get_id())
+                .into_response(),
+        }
+    }
+}
+
+impl<S> Layer<S> for CaptchaGuardLayer {
+    type Service = CaptchaGuard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CaptchaGuard {
+            layer: self.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptchaGuard<S> {
+    layer: CaptchaGuardLayer,
+    inner: S,
+}
+
+impl<S> Service<Request> for CaptchaGuard<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    /// Always ready, the inner service is only driven for requests with a
+    /// valid pass token and awaited in `call`, so rejections don't hold on to
+    /// capacity it reserved
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        match self.layer.verify(request.headers()) {
+            Some(claims) => {
+                request.extensions_mut().insert(claims);
+
+                let clone = self.inner.clone();
+                let inner = std::mem::replace(&mut self.inner, clone);
+
+                Box::pin(inner.oneshot(request))
+            }
+            None => {
+                let state = self.layer.state.clone();
+                let site = self.layer.site.clone();
+
+                Box::pin(async move {
+                    Ok(CaptchaGuardLayer::challenge_response(state, site).await)
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use axum::{
+        body::Body,
+        http::Request,
+        response::{IntoResponse, Response},
+        routing::get,
+        Router,
+    };
+    use tower::{Layer, Service, ServiceExt};
+    use uuid::Uuid;
+
+    use crate::{
+        pass_token::PassTokenConfig,
+        test_support::{body_json, config, site, SITE_ID},
+        RouterBuilder,
+    };
+
+    use super::CaptchaGuardLayer;
+
+    fn pass_token_config() -> PassTokenConfig {
+        PassTokenConfig::new("0123456789abcdef0123456789abcdef", Duration::from_secs(60))
+    }
+
+    async fn guard() -> CaptchaGuardLayer {
+        let site = site().with_pass_token(pass_token_config());
+
+        let (_, captcha) = RouterBuilder::new(config(vec![site]))
+            .build_with_captcha()
+            .expect("Unable to build router");
+
+        captcha.guard(&SITE_ID).await.expect("Unable to create guard")
+    }
+
+    async fn protected_router() -> Router {
+        Router::new()
+            .route("/protected", get(|| async { "secret" }))
+            .layer(guard().await)
+    }
+
+    /// Counts the readiness reserved by `poll_ready` and not yet used by `call`
+    #[derive(Clone)]
+    struct Reserving(Arc<AtomicUsize>);
+
+    impl Service<axum::extract::Request> for Reserving {
+        type Response = Response;
+        type Error = Infallible;
+        type Future = Ready<Result<Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: axum::extract::Request) -> Self::Future {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+            ready(Ok("secret".into_response()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_gets_challenge() {
+        let request = Request::get("/protected").body(Body::empty()).unwrap();
+
+        let response = protected_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 401);
+
+        let body = body_json(response).await;
+
+        assert_eq!(body["id"], "PassTokenRequired");
+        assert!(body["challenge"]["id"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_token_in_header() {
+        let token = pass_token_config().issue(&SITE_ID, &Uuid::new_v4());
+
+        let request = Request::get("/protected")
+            .header("x-captcha-pass", token)
+            .body(Body::empty())
+            .unwrap();
+
+        let response = protected_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_token_in_cookie() {
+        let token = pass_token_config().issue(&SITE_ID, &Uuid::new_v4());
+
+        let request = Request::get("/protected")
+            .header("cookie", format!("theme=dark; oxidecaptcha_pass={token}"))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = protected_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token() {
+        let request = Request::get("/protected")
+            .header("x-captcha-pass", "not.valid")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = protected_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn test_rejection_keeps_no_readiness() {
+        let reserved = Arc::new(AtomicUsize::new(0));
+        let mut service = guard().await.layer(Reserving(reserved.clone()));
+
+        let request = Request::get("/protected").body(Body::empty()).unwrap();
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.status(), 401);
+        assert_eq!(reserved.load(Ordering::SeqCst), 0);
+
+        let token = pass_token_config().issue(&SITE_ID, &Uuid::new_v4());
+        let request = Request::get("/protected")
+            .header("x-captcha-pass", token)
+            .body(Body::empty())
+            .unwrap();
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(reserved.load(Ordering::SeqCst), 0);
+    }
+}
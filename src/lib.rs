@@ -0,0 +1,30 @@
+mod application;
+pub mod audit;
+pub mod auth;
+pub mod bench;
+pub mod challenge;
+pub mod config;
+pub mod error_response;
+pub mod grpc;
+pub mod guard;
+pub mod logging;
+pub mod metrics;
+mod middleware;
+mod openapi;
+pub mod pass_token;
+pub mod pool;
+mod proxy;
+mod routes;
+pub mod site;
+pub mod solution;
+mod state;
+pub mod storage;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod webhook;
+pub mod wire;
+mod widget;
+
+pub use application::{Application, RouterBuilder};
+use state::State;
+use storage::Storage;
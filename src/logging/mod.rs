@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace::TracerProvider, Resource};
+use tracing::error;
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use crate::config::{LogFormat, LoggingConfig, OtlpConfig};
+
+mod propagation;
+
+pub use propagation::set_parent_from_headers;
+
+pub struct LoggingGuard {
+    tracer_provider: Option<TracerProvider>,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.tracer_provider.take() {
+            if let Err(e) = provider.shutdown() {
+                error!("Unable to shut down tracer provider: {}", e);
+            }
+        }
+    }
+}
+
+pub fn init(config: &LoggingConfig) -> Result<LoggingGuard> {
+    let filter = filter(config, std::env::var("RUST_LOG").ok())?;
+
+    let fmt_layer = match config.format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+
+    let tracer_provider = config.otlp.as_ref().map(otlp_provider).transpose()?;
+
+    let otel_layer = tracer_provider.as_ref().map(|provider| {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("oxidecaptcha"))
+    });
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(filter.clone()))
+        .with(otel_layer.with_filter(filter))
+        .try_init()
+        .context("Unable to install tracing subscriber")?;
+
+    Ok(LoggingGuard { tracer_provider })
+}
+
+/// `RUST_LOG` if set, the configured level otherwise
+fn filter(config: &LoggingConfig, rust_log: Option<String>) -> Result<Targets> {
+    if let Some(rust_log) = rust_log.filter(|rust_log| !rust_log.is_empty()) {
+        return rust_log.parse().context("Unable to parse RUST_LOG");
+    }
+
+    Ok(config
+        .level
+        .clone()
+        .unwrap_or_else(|| Targets::new().with_default(LevelFilter::INFO)))
+}
+
+fn otlp_provider(config: &OtlpConfig) -> Result<TracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&config.endpoint)
+        .build()
+        .context("Unable to build otlp exporter")?;
+
+    let resource = Resource::new([KeyValue::new("service.name", config.service_name.clone())]);
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(resource)
+        .build();
+
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+    use opentelemetry::trace::{Tracer, TracerProvider as _};
+    use tokio::net::TcpListener;
+
+    use tracing::Level;
+
+    use crate::config::{LoggingConfig, OtlpConfig};
+
+    use super::{filter, otlp_provider};
+
+    #[test]
+    fn test_filter() {
+        let config: LoggingConfig =
+            serde_json::from_str(r#"{ "level": "warn,oxidecaptcha=debug" }"#).unwrap();
+
+        let configured = filter(&config, None).unwrap();
+        assert!(configured.would_enable("oxidecaptcha::routes", &Level::DEBUG));
+        assert!(!configured.would_enable("hyper", &Level::INFO));
+
+        let overridden = filter(&config, Some("trace".to_string())).unwrap();
+        assert!(overridden.would_enable("hyper", &Level::TRACE));
+
+        let default = filter(&LoggingConfig::default(), None).unwrap();
+        assert!(default.would_enable("oxidecaptcha", &Level::INFO));
+        assert!(!default.would_enable("oxidecaptcha", &Level::DEBUG));
+
+        assert!(filter(&config, Some("info,oxidecaptcha=loud".to_string())).is_err());
+        assert!(serde_json::from_str::<LoggingConfig>(r#"{ "level": "oxidecaptcha=loud" }"#).is_err());
+    }
+
+    async fn collector(State(received): State<Arc<AtomicUsize>>, headers: HeaderMap) {
+        assert_eq!(
+            headers.get("content-type").and_then(|v| v.to_str().ok()),
+            Some("application/x-protobuf")
+        );
+
+        received.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_to_collector() {
+        let received = Arc::new(AtomicUsize::new(0));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let router = Router::new()
+            .route("/v1/traces", post(collector))
+            .with_state(received.clone());
+
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let config = OtlpConfig {
+            endpoint: format!("http://{addr}/v1/traces"),
+            service_name: "test".to_string(),
+        };
+
+        let provider = otlp_provider(&config).expect("Unable to build provider");
+
+        provider.tracer("test").in_span("test-span", |_| {});
+
+        let flush = tokio::task::spawn_blocking(move || provider.force_flush());
+        for result in flush.await.unwrap() {
+            result.expect("Flush failed");
+        }
+
+        assert_eq!(received.load(Ordering::SeqCst), 1);
+    }
+}
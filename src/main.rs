@@ -1,29 +1,142 @@
-use std::process::exit;
+use std::{io::BufRead, path::PathBuf, process::exit, time::Duration};
 
-use application::Application;
-use state::State;
-use storage::Storage;
+use clap::{Args, Parser, Subcommand};
+use oxidecaptcha::{
+    auth::hash_api_key,
+    bench::{Bench, DeviceProfile},
+    config::{Config, StorageTypeConfig},
+    logging, Application,
+};
 use tracing::error;
 
-mod application;
-mod challenge;
-mod config;
-mod error_response;
-mod middleware;
-mod routes;
-mod site;
-mod state;
-mod storage;
-mod solution;
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[arg(short, long, global = true, default_value = "config.json")]
+    config: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the server, the default
+    Serve,
+    /// Measure hash throughput and recommend site parameters
+    Bench(BenchArgs),
+    /// Read an api-key from stdin and print it as `apiKeyHash`
+    HashApiKey,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Seconds to measure each algorithm for
+    #[arg(long, default_value = "2", value_parser = parse_seconds)]
+    duration: Duration,
+    /// Target median solve time in seconds on the slowest profile
+    #[arg(long, default_value = "1", value_parser = parse_seconds)]
+    target: Duration,
+    /// Device speed relative to this machine, e.g. `low-end-phone=0.1`.
+    /// Defaults to `desktop=1` and `low-end-phone=0.1`
+    #[arg(long = "profile")]
+    profiles: Vec<DeviceProfile>,
+}
+
+/// A positive, finite number of seconds
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    let duration = Duration::try_from_secs_f64(seconds).map_err(|e| format!("{e}"))?;
+
+    if duration.is_zero() {
+        return Err("must be greater than 0".to_string());
+    }
+
+    Ok(duration)
+}
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt().init();
+    let cli = Cli::parse();
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(cli.config).await,
+        Command::Bench(args) => bench(cli.config, args),
+        Command::HashApiKey => hash(),
+    }
+}
+
+fn hash() {
+    let mut key = String::new();
+
+    if let Err(e) = std::io::stdin().lock().read_line(&mut key) {
+        eprintln!("Unable to read api-key: {:?}", e);
+        exit(1);
+    }
+
+    let key = key.trim_end_matches(['\r', '\n']);
+
+    if key.is_empty() {
+        eprintln!("Api-key is empty");
+        exit(1);
+    }
+
+    println!("{}", hash_api_key(key));
+}
 
-    let application = match Application::new().await {
+fn bench(config: PathBuf, args: BenchArgs) {
+    let sites = if config.exists() {
+        match Config::from_file(&config) {
+            Ok(config) => match config.get_storage() {
+                Some(StorageTypeConfig::Memory(memory)) => memory.get_sites().clone(),
+                None => Vec::new(),
+            },
+            Err(e) => {
+                eprintln!("Unable to load config: {:?}", e);
+                exit(1);
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    let profiles = match args.profiles.is_empty() {
+        true => DeviceProfile::defaults(),
+        false => args.profiles,
+    };
+
+    let bench = Bench {
+        duration: args.duration,
+        target_median: args.target,
+        profiles,
+        sites,
+    };
+
+    bench.run();
+}
+
+async fn serve(config: PathBuf) {
+    let config = match Config::from_file(config) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing_subscriber::fmt().init();
+            error!("App couldnt start: {:?}", e);
+            exit(1);
+        }
+    };
+
+    let logging_guard = match logging::init(config.get_logging()) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("App couldnt start: {:?}", e);
+            exit(1);
+        }
+    };
+
+    let application = match Application::new(config).await {
         Ok(app) => app,
         Err(e) => {
             error!("App couldnt start: {:?}", e);
+            drop(logging_guard);
             exit(1);
         }
     };
@@ -32,9 +145,8 @@ async fn main() {
         Ok(_) => (),
         Err(e) => {
             error!("App crashed: {:?}", e);
+            drop(logging_guard);
             exit(1);
         }
     }
-
-    //let storage = storage::StorageProvider::new(&config);
 }
@@ -0,0 +1,87 @@
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+#[derive(Debug, Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Process wide metrics, exposed in the prometheus text format on `/metrics`
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Requests with a missing or wrong api-key
+    pub auth_failures: Counter,
+    /// Lockouts started by failed api-key checks
+    pub auth_lockouts: Counter,
+    /// Requests rejected because the client is locked out
+    pub auth_locked_out: Counter,
+    /// Times a site went over `siteAttempts` failures within the window
+    pub auth_site_alerts: Counter,
+}
+
+impl Metrics {
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        Self::render_counter(
+            &mut output,
+            "oxidecaptcha_auth_failures_total",
+            "Requests with a missing or wrong api-key",
+            &self.auth_failures,
+        );
+        Self::render_counter(
+            &mut output,
+            "oxidecaptcha_auth_lockouts_total",
+            "Lockouts started by failed api-key checks",
+            &self.auth_lockouts,
+        );
+        Self::render_counter(
+            &mut output,
+            "oxidecaptcha_auth_locked_out_total",
+            "Requests rejected during a lockout",
+            &self.auth_locked_out,
+        );
+        Self::render_counter(
+            &mut output,
+            "oxidecaptcha_auth_site_alerts_total",
+            "Sites going over their threshold of failed api-key checks",
+            &self.auth_site_alerts,
+        );
+
+        output
+    }
+
+    fn render_counter(output: &mut String, name: &str, help: &str, counter: &Counter) {
+        let _ = writeln!(output, "# HELP {name} {help}");
+        let _ = writeln!(output, "# TYPE {name} counter");
+        let _ = writeln!(output, "{name} {}", counter.get());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.auth_failures.inc();
+        metrics.auth_failures.inc();
+
+        let output = metrics.render();
+
+        assert!(output.contains("# TYPE oxidecaptcha_auth_failures_total counter\n"));
+        assert!(output.contains("oxidecaptcha_auth_failures_total 2\n"));
+        assert!(output.contains("oxidecaptcha_auth_lockouts_total 0\n"));
+    }
+}
@@ -1,36 +1,723 @@
+use std::net::{IpAddr, SocketAddr};
+
 use axum::{
-    extract::Request,
+    body::Body,
+    extract::{ConnectInfo, OriginalUri, Request, State},
+    http::{HeaderValue, Method},
     middleware::Next,
-    response::Response, Extension,
+    response::Response,
+    Extension,
 };
-use sha2::{Digest, Sha256};
+use tracing::{field::Empty, info_span, Instrument, Span};
 
 use crate::{
-    error_response::{ErrorId, ErrorResponse}, site::Site
+    audit::{AuditAction, AuditEntry, AuditOutcome},
+    auth::{
+        ApiKeyEntry, KeyValidity, NonceError, RequestSigningConfig, Scope, SignatureError,
+        SignedRequest, NONCE_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    },
+    error_response::{ErrorId, ErrorResponse},
+    site::Site,
+    webhook::WebhookEvent,
 };
 
+/// Same as the default body limit of the json extractor
+const MAX_SIGNED_BODY: usize = 2 * 1024 * 1024;
+
 pub async fn auth_middleware(
+    State(state): State<crate::state::State>,
     Extension(site): Extension<Site>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, ErrorResponse> {
+    let key = request.headers().get("api-key").cloned();
+    let scope = required_scope(request.method());
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
 
-    let key = request.headers().get("api-key")
-        .ok_or(ErrorResponse::new(ErrorId::MissingApiKey, "Header api-key missing"))?;
+    let entry = authenticate(&state, &site, key, scope, client).await?;
 
-    let mut hasher = Sha256::new();
-    hasher.update(key.as_bytes());
-    let keyhash = hasher.finalize().to_vec();
-
-    if site.get_api_key_hash() != &keyhash {
-        return Err(ErrorResponse::new(ErrorId::WrongApiKey, "Api-key wrong"));
+    if let Some(signing) = site.get_request_signing() {
+        request = check_signature(&state, &site, signing, request)
+            .await
+            .map_err(|error| record_failure(&state, &site, client, error))?;
     }
 
-    if site.get_api_key().as_bytes() != key.as_bytes() {
-        return Err(ErrorResponse::new(ErrorId::WrongApiKey, "Api-key wrong"));
-    }
+    state.get_brute_force().record_success(site.get_id(), client);
+
+    Span::current().record("api_key_id", entry.get_id());
+
+    record_key_use(
+        &state,
+        &site,
+        &entry,
+        format!("{} {}", request.method(), request.uri().path()),
+    );
+
+    request.extensions_mut().insert(entry);
 
     let response = next.run(request).await;
 
     Ok(response)
 }
+
+/// Checks `key` against the site's api-keys, refusing locked out clients and
+/// counting failures towards a lockout. Failures of other clients never
+/// refuse a key. Shared with the gRPC service, callers record the success
+/// once the rest of the request checked out.
+pub(crate) async fn authenticate(
+    state: &crate::state::State,
+    site: &Site,
+    key: Option<HeaderValue>,
+    scope: Scope,
+    client: Option<IpAddr>,
+) -> Result<ApiKeyEntry, ErrorResponse> {
+    if let Some(remaining) = state.get_brute_force().locked_out(site.get_id(), client) {
+        state.get_metrics().auth_locked_out.inc();
+
+        return Err(ErrorResponse::new(
+            ErrorId::TooManyFailedAttempts,
+            "Too many failed api-key checks, retry later",
+        )
+        .with_site(site.get_id())
+        .with_retry_after(remaining));
+    }
+
+    let entry = check_api_key(state, site, key, scope)
+        .instrument(info_span!("auth_middleware", site_id = %site.get_id(), api_key_id = Empty))
+        .await
+        .map_err(|error| record_failure(state, site, client, error))?;
+
+    Ok(entry)
+}
+
+/// Audits a successful request, `detail` names the operation
+pub(crate) fn record_key_use(
+    state: &crate::state::State,
+    site: &Site,
+    entry: &ApiKeyEntry,
+    detail: String,
+) {
+    state.get_audit().record(
+        AuditEntry::new(
+            format!("apiKey:{}", entry.get_id()),
+            AuditAction::ApiKeyUsed,
+            AuditOutcome::Success,
+        )
+        .with_site(site.get_id())
+        .with_detail(detail),
+    );
+}
+
+/// Audits the failure, and counts it towards a lockout of the client if the
+/// key was missing or wrong
+pub(crate) fn record_failure(
+    state: &crate::state::State,
+    site: &Site,
+    client: Option<IpAddr>,
+    error: ErrorResponse,
+) -> ErrorResponse {
+    state
+        .get_webhooks()
+        .emit(site, WebhookEvent::AuthFailed, None);
+
+    state.get_audit().record(
+        AuditEntry::new(client_actor(client), AuditAction::ApiKeyFailed, AuditOutcome::Failure)
+            .with_site(site.get_id())
+            .with_detail(format!("{:?}: {}", error.get_id(), error.get_context())),
+    );
+
+    if !matches!(error.get_id(), ErrorId::MissingApiKey | ErrorId::WrongApiKey) {
+        return error;
+    }
+
+    state.get_metrics().auth_failures.inc();
+
+    let recorded = state.get_brute_force().record_failure(site.get_id(), client);
+
+    if recorded.site_threshold_reached {
+        state.get_metrics().auth_site_alerts.inc();
+
+        state.get_audit().record(
+            AuditEntry::new(client_actor(client), AuditAction::FailureThreshold, AuditOutcome::Failure)
+                .with_site(site.get_id())
+                .with_detail("Site went over its siteAttempts failed api-key checks".to_string()),
+        );
+    }
+
+    let Some(lockout) = recorded.lockout else {
+        return error;
+    };
+
+    state.get_metrics().auth_lockouts.inc();
+
+    state.get_audit().record(
+        AuditEntry::new(client_actor(client), AuditAction::Lockout, AuditOutcome::Success)
+            .with_site(site.get_id())
+            .with_detail(format!("Locked out for {}ms", lockout.as_millis())),
+    );
+
+    error.with_retry_after(lockout)
+}
+
+fn client_actor(client: Option<IpAddr>) -> String {
+    match client {
+        Some(client) => format!("client:{client}"),
+        None => "client:unknown".to_string(),
+    }
+}
+
+/// Verifies the signature over the buffered body and hands on the request
+/// with the body restored
+async fn check_signature(
+    state: &crate::state::State,
+    site: &Site,
+    signing: &RequestSigningConfig,
+    request: Request,
+) -> Result<Request, ErrorResponse> {
+    let invalid = |error: SignatureError| {
+        ErrorResponse::new(ErrorId::InvalidSignature, error).with_site(site.get_id())
+    };
+
+    let (parts, body) = request.into_parts();
+
+    let path = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map(|OriginalUri(uri)| uri)
+        .unwrap_or(&parts.uri)
+        .path_and_query()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_default();
+
+    let header = |name: &str| parts.headers.get(name).map(|value| value.to_str());
+
+    let (Some(timestamp), Some(nonce), Some(signature)) = (
+        header(TIMESTAMP_HEADER),
+        header(NONCE_HEADER),
+        header(SIGNATURE_HEADER),
+    ) else {
+        return Err(invalid(SignatureError::Missing));
+    };
+
+    let (Ok(timestamp), Ok(nonce), Ok(signature)) = (timestamp, nonce, signature) else {
+        return Err(invalid(SignatureError::Malformed));
+    };
+
+    let signed = SignedRequest {
+        timestamp: timestamp
+            .parse()
+            .map_err(|_| invalid(SignatureError::Malformed))?,
+        nonce,
+        signature,
+    };
+
+    let body = axum::body::to_bytes(body, MAX_SIGNED_BODY).await.map_err(|_| {
+        ErrorResponse::new(
+            ErrorId::PayloadTooLarge,
+            format!("Signed request bodies are limited to {MAX_SIGNED_BODY} bytes"),
+        )
+        .with_site(site.get_id())
+    })?;
+
+    signing
+        .verify(parts.method.as_str(), &path, &signed, &body)
+        .map_err(invalid)?;
+
+    let ttl = signing.get_max_skew().saturating_mul(2);
+
+    match state.get_nonces().insert(site.get_id(), signed.nonce, ttl) {
+        Ok(()) => (),
+        Err(NonceError::Replayed) => return Err(invalid(SignatureError::Replayed)),
+        Err(NonceError::Full) => {
+            return Err(ErrorResponse::new(
+                ErrorId::Overloaded,
+                "Too many signed requests in flight, retry later",
+            )
+            .with_site(site.get_id()))
+        }
+    }
+
+    Ok(Request::from_parts(parts, Body::from(body)))
+}
+
+fn required_scope(method: &Method) -> Scope {
+    match *method {
+        Method::DELETE => Scope::Delete,
+        _ => Scope::Validate,
+    }
+}
+
+async fn check_api_key(
+    state: &crate::state::State,
+    site: &Site,
+    key: Option<HeaderValue>,
+    scope: Scope,
+) -> Result<ApiKeyEntry, ErrorResponse> {
+    let key = key.ok_or_else(|| {
+        ErrorResponse::new(ErrorId::MissingApiKey, "Header api-key missing")
+            .with_site(site.get_id())
+    })?;
+
+    let entry = find_api_key(state, site, key).await?.ok_or_else(|| {
+        ErrorResponse::new(ErrorId::WrongApiKey, "Api-key wrong").with_site(site.get_id())
+    })?;
+
+    Span::current().record("api_key_id", entry.get_id());
+
+    match entry.validity() {
+        KeyValidity::Valid => (),
+        KeyValidity::NotYetValid => {
+            return Err(ErrorResponse::new(ErrorId::WrongApiKey, "Api-key not valid yet")
+                .with_site(site.get_id()))
+        }
+        KeyValidity::Expired => {
+            return Err(ErrorResponse::new(
+                ErrorId::ExpiredApiKey,
+                format!("Api-key {} expired", entry.get_id()),
+            )
+            .with_site(site.get_id()))
+        }
+    }
+
+    if !entry.has_scope(scope) {
+        return Err(ErrorResponse::new(
+            ErrorId::InsufficientScope,
+            format!("Api-key {} lacks the {scope} scope", entry.get_id()),
+        )
+        .with_site(site.get_id()));
+    }
+
+    Ok(entry)
+}
+
+/// Cheap keys and cached verifications are checked first, argon2 hashes only
+/// if none of them match. Hashing is limited to `hashConcurrency` at a time,
+/// and keys matching none of the hashes are remembered for
+/// `rejectedCacheTtl`.
+async fn find_api_key(
+    state: &crate::state::State,
+    site: &Site,
+    key: HeaderValue,
+) -> Result<Option<ApiKeyEntry>, ErrorResponse> {
+    let cache = state.get_verified_keys();
+    let presented = key.as_bytes();
+
+    let found = site.get_api_keys().iter().find(|entry| {
+        let api_key = entry.get_key();
+
+        match api_key.is_expensive() {
+            true => cache.contains(site.get_id(), api_key.fingerprint(), presented),
+            false => api_key.verify(presented),
+        }
+    });
+
+    if let Some(entry) = found {
+        return Ok(Some(entry.clone()));
+    }
+
+    let expensive: Vec<ApiKeyEntry> = site
+        .get_api_keys()
+        .iter()
+        .filter(|entry| entry.get_key().is_expensive())
+        .cloned()
+        .collect();
+
+    if expensive.is_empty() {
+        return Ok(None);
+    }
+
+    // Covers all hashes, so rotating a key forgets the rejections
+    let fingerprint: Vec<u8> = expensive
+        .iter()
+        .flat_map(|entry| entry.get_key().fingerprint().iter().copied())
+        .collect();
+
+    if state
+        .get_rejected_keys()
+        .contains(site.get_id(), &fingerprint, presented)
+    {
+        return Ok(None);
+    }
+
+    let internal_error = |context: String| {
+        ErrorResponse::new(ErrorId::InternalServerError, context).with_site(site.get_id())
+    };
+
+    let permit = state
+        .get_hash_permits()
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|_| internal_error("Api-key verification pool closed".to_string()))?;
+
+    let verified = key.clone();
+    let found = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+
+        expensive
+            .into_iter()
+            .find(|entry| entry.get_key().verify(verified.as_bytes()))
+    })
+    .await
+    .map_err(|e| internal_error(format!("Api-key verification failed: {e}")))?;
+
+    match &found {
+        Some(entry) => cache.insert(site.get_id(), entry.get_key().fingerprint(), presented),
+        None => state
+            .get_rejected_keys()
+            .insert(site.get_id(), &fingerprint, presented),
+    }
+
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use axum::{body::Body, extract::ConnectInfo, http::Request, Router};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::{
+        auth::{ApiKey, ApiKeyEntry, RequestSigningConfig, Scope},
+        config::{AuthConfig, BruteForceConfig},
+        site::Site,
+        test_support::{body_json, config, site, SITE_ID},
+        RouterBuilder,
+    };
+
+    /// Argon2id hash of `cool` with cheap parameters
+    const API_KEY_HASH: &str =
+        "$argon2id$v=19$m=8,t=1,p=1$c2FsdHNhbHRzYWx0$qOAVMUBAC45Z7ZapMJ0aNZPCsCi/JIzIN6NxaJUgKHk";
+
+    fn router_with(site: Site) -> Router {
+        router_with_config(site, AuthConfig::default())
+    }
+
+    fn router_with_config(site: Site, auth: AuthConfig) -> Router {
+        RouterBuilder::new(config(vec![site]).with_auth(auth))
+            .build()
+            .expect("Unable to build router")
+    }
+
+    fn router() -> Router {
+        router_with(site().with_api_key(ApiKey::from_phc(API_KEY_HASH).unwrap()))
+    }
+
+    async fn challenge_id(router: &Router) -> String {
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let challenge = body_json(router.clone().oneshot(request).await.unwrap()).await;
+
+        challenge["id"].as_str().unwrap().to_string()
+    }
+
+    async fn delete(router: &Router, key: &str) -> axum::response::Response {
+        let request = Request::delete(format!(
+            "/v1/site/{SITE_ID}/challenge/{}",
+            challenge_id(router).await
+        ))
+        .header("api-key", key)
+        .body(Body::empty())
+        .unwrap();
+
+        router.clone().oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_key_hash() {
+        let router = router();
+
+        assert_eq!(delete(&router, "wrong").await.status(), 401);
+        assert_eq!(delete(&router, "cool").await.status(), 200);
+        // Served from the cache
+        assert_eq!(delete(&router, "cool").await.status(), 200);
+        assert_eq!(delete(&router, "wrong").await.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn test_key_rotation_and_scopes() {
+        let site = site().with_api_keys(vec![
+                ApiKeyEntry::new("old", ApiKey::from_plaintext("old")).with_expires_at(1),
+                ApiKeyEntry::new("future", ApiKey::from_plaintext("future"))
+                    .with_not_before(u64::MAX),
+                ApiKeyEntry::new("cleanup", ApiKey::from_plaintext("cleanup"))
+                    .with_scopes(vec![Scope::Delete]),
+                ApiKeyEntry::new("backend", ApiKey::from_plaintext("backend"))
+                    .with_scopes(vec![Scope::Validate]),
+            ]);
+        let router = router_with(site);
+
+        let response = delete(&router, "old").await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(body_json(response).await["id"], "ExpiredApiKey");
+
+        let response = delete(&router, "future").await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(body_json(response).await["id"], "WrongApiKey");
+
+        assert_eq!(delete(&router, "cleanup").await.status(), 200);
+
+        let response = delete(&router, "backend").await;
+        assert_eq!(response.status(), 403);
+        assert_eq!(body_json(response).await["id"], "InsufficientScope");
+
+        let request = Request::post(format!(
+            "/v1/site/{SITE_ID}/challenge/{}",
+            challenge_id(&router).await
+        ))
+        .header("api-key", "cleanup")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{ "solutions": [] }"#))
+        .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_brute_force_lockout() {
+        let auth = AuthConfig {
+            brute_force: BruteForceConfig {
+                client_attempts: 2,
+                initial_lockout: Duration::from_secs(30).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let router = router_with_config(
+            site().with_api_key(ApiKey::from_plaintext("cool")),
+            auth,
+        );
+
+        let attempt = |key: &'static str| {
+            let router = router.clone();
+
+            async move {
+                let mut request = Request::delete(format!(
+                    "/v1/site/{SITE_ID}/challenge/{}",
+                    challenge_id(&router).await
+                ))
+                .header("api-key", key)
+                .body(Body::empty())
+                .unwrap();
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+                router.oneshot(request).await.unwrap()
+            }
+        };
+
+        let response = attempt("wrong").await;
+        assert_eq!(response.status(), 401);
+        assert!(response.headers().get("retry-after").is_none());
+
+        let response = attempt("wrong").await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers()["retry-after"], "30");
+
+        // Locked out even with the right key
+        let response = attempt("cool").await;
+        assert_eq!(response.status(), 429);
+        assert_eq!(body_json(response).await["id"], "TooManyFailedAttempts");
+
+        // Other clients are not affected
+        assert_eq!(delete(&router, "cool").await.status(), 200);
+
+        let request = Request::get("/v1/metrics").body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let metrics = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(metrics.contains("oxidecaptcha_auth_failures_total 2\n"));
+        assert!(metrics.contains("oxidecaptcha_auth_lockouts_total 1\n"));
+        assert!(metrics.contains("oxidecaptcha_auth_locked_out_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_site_threshold_does_not_lock_out() {
+        let auth = AuthConfig {
+            brute_force: BruteForceConfig {
+                site_attempts: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let router =
+            router_with_config(site().with_api_key(ApiKey::from_plaintext("cool")), auth);
+
+        let attempt = |key: &'static str, client: Option<[u8; 4]>| {
+            let router = router.clone();
+
+            async move {
+                let mut request = Request::delete(format!(
+                    "/v1/site/{SITE_ID}/challenge/{}",
+                    challenge_id(&router).await
+                ))
+                .header("api-key", key)
+                .body(Body::empty())
+                .unwrap();
+
+                if let Some(client) = client {
+                    request
+                        .extensions_mut()
+                        .insert(ConnectInfo(SocketAddr::from((client, 4000))));
+                }
+
+                router.oneshot(request).await.unwrap()
+            }
+        };
+
+        for i in 0..4 {
+            assert_eq!(attempt("wrong", Some([10, 0, 0, i])).await.status(), 401);
+            assert_eq!(attempt("wrong", None).await.status(), 401);
+        }
+
+        // The site is over its threshold, but correct keys still verify
+        assert_eq!(attempt("cool", Some([10, 0, 0, 0])).await.status(), 200);
+        assert_eq!(attempt("cool", Some([10, 0, 0, 99])).await.status(), 200);
+        assert_eq!(attempt("cool", None).await.status(), 200);
+
+        let request = Request::get("/v1/metrics").body(Body::empty()).unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let metrics = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(metrics.contains("oxidecaptcha_auth_site_alerts_total 1\n"));
+        assert!(metrics.contains("oxidecaptcha_auth_locked_out_total 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_request_signing() {
+        let signing =
+            RequestSigningConfig::new("0123456789abcdef0123456789abcdef", Duration::from_secs(60));
+        let router = router_with(
+            site().with_api_key(ApiKey::from_plaintext("cool"))
+                .with_request_signing(signing.clone()),
+        );
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let path = format!("/v1/site/{SITE_ID}/challenge/{}", challenge_id(&router).await);
+        let signed = |nonce: &str| {
+            Request::delete(&path)
+                .header("api-key", "cool")
+                .header("x-oxidecaptcha-timestamp", timestamp)
+                .header("x-oxidecaptcha-nonce", nonce)
+                .header(
+                    "x-oxidecaptcha-signature",
+                    signing.sign("DELETE", &path, timestamp, nonce, b""),
+                )
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        // Api-key alone is not enough
+        let response = delete(&router, "cool").await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(body_json(response).await["id"], "InvalidSignature");
+
+        let response = router.clone().oneshot(signed("nonce-0123456789ab")).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        // The body is covered by the signature and replays are rejected
+        let path = format!("/v1/site/{SITE_ID}/challenge/{}", challenge_id(&router).await);
+        let body = r#"{ "solutions": [] }"#;
+        let validate = |body: &'static str| {
+            Request::post(&path)
+                .header("api-key", "cool")
+                .header("content-type", "application/json")
+                .header("x-oxidecaptcha-timestamp", timestamp)
+                .header("x-oxidecaptcha-nonce", "nonce-validate-0123")
+                .header(
+                    "x-oxidecaptcha-signature",
+                    signing.sign("POST", &path, timestamp, "nonce-validate-0123", body.as_bytes()),
+                )
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let mut tampered = validate(body);
+        *tampered.body_mut() = Body::from(r#"{ "solutions": [null] }"#);
+        let response = router.clone().oneshot(tampered).await.unwrap();
+        assert_eq!(response.status(), 401);
+
+        let response = router.clone().oneshot(validate(body)).await.unwrap();
+        assert_eq!(body_json(response).await["id"], "WrongNumberOfSolutions");
+
+        let response = router.clone().oneshot(validate(body)).await.unwrap();
+        assert_eq!(response.status(), 401);
+        assert_eq!(body_json(response).await["id"], "InvalidSignature");
+
+        // Signature bound to the path
+        let mut request = signed("nonce-ba9876543210");
+        *request.uri_mut() = format!("/v1/site/{SITE_ID}/challenge/{}", challenge_id(&router).await)
+            .parse()
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 401);
+
+        // Too large to buffer for verification
+        let mut request = signed("nonce-large-0123456");
+        *request.uri_mut() = format!("/v1/site/{SITE_ID}/challenge/{}", challenge_id(&router).await)
+            .parse()
+            .unwrap();
+        *request.body_mut() = Body::from(vec![b' '; 3 * 1024 * 1024]);
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 413);
+        assert_eq!(body_json(response).await["id"], "PayloadTooLarge");
+    }
+
+    #[tokio::test]
+    async fn test_bad_signature_keeps_failures() {
+        let auth = AuthConfig {
+            brute_force: BruteForceConfig {
+                client_attempts: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let signing =
+            RequestSigningConfig::new("0123456789abcdef0123456789abcdef", Duration::from_secs(60));
+        let router = router_with_config(
+            site().with_api_key(ApiKey::from_plaintext("cool"))
+                .with_request_signing(signing),
+            auth,
+        );
+
+        let attempt = |key: &'static str| {
+            let router = router.clone();
+
+            async move {
+                let mut request = Request::delete(format!(
+                    "/v1/site/{SITE_ID}/challenge/{}",
+                    challenge_id(&router).await
+                ))
+                .header("api-key", key)
+                .body(Body::empty())
+                .unwrap();
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+                router.oneshot(request).await.unwrap()
+            }
+        };
+
+        assert!(attempt("wrong").await.headers().get("retry-after").is_none());
+
+        // The right key without a signature does not reset the failures
+        let response = attempt("cool").await;
+        assert_eq!(body_json(response).await["id"], "InvalidSignature");
+
+        assert!(attempt("wrong").await.headers().get("retry-after").is_some());
+    }
+}
@@ -0,0 +1,215 @@
+use axum::{
+    extract::Request,
+    http::{
+        header::{
+            ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+            ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD,
+            ORIGIN, REFERER, VARY,
+        },
+        HeaderValue, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use tracing::debug;
+
+use crate::{
+    error_response::{ErrorId, ErrorResponse},
+    site::{origin_of_referer, Site},
+};
+
+const ALLOWED_METHODS: &str = "GET, POST, OPTIONS";
+
+/// Request headers browsers may send besides the safelisted ones
+const ALLOWED_HEADERS: &str = "content-type";
+
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+/// Origin a browser request came from, taken from `Origin` or else `Referer`.
+/// Browsers send at least one of them, so `None` is a weak bot signal.
+#[derive(Debug, Clone)]
+pub struct RequestOrigin(pub Option<String>);
+
+pub async fn cors_middleware(
+    Extension(site): Extension<Site>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+
+    let origin = headers
+        .get(ORIGIN)
+        .and_then(|origin| origin.to_str().ok())
+        .map(str::to_owned);
+
+    let request_origin = origin.clone().or_else(|| {
+        headers
+            .get(REFERER)
+            .and_then(|referer| referer.to_str().ok())
+            .and_then(origin_of_referer)
+    });
+
+    // Without a configured list the site does not opt into cross-origin use.
+    // Rejected origins get it too, so the page can read why.
+    let allow_origin = origin
+        .filter(|_| site.get_allowed_origins().is_some())
+        .and_then(|origin| HeaderValue::from_str(&origin).ok());
+
+    if let Some(request_origin) = &request_origin {
+        if !site.is_origin_allowed(request_origin) {
+            let error = ErrorResponse::new(
+                ErrorId::OriginNotAllowed,
+                format!("Origin {request_origin} not allowed"),
+            )
+            .with_site(site.get_id());
+
+            return with_allow_origin(error.into_response(), allow_origin);
+        }
+    } else {
+        debug!(site_id = %site.get_id(), "Request without Origin or Referer");
+    }
+
+    if request.method() == Method::OPTIONS {
+        let method_allowed = request
+            .headers()
+            .get(ACCESS_CONTROL_REQUEST_METHOD)
+            .is_some_and(|method| method == "GET" || method == "POST");
+
+        let mut response = StatusCode::NO_CONTENT.into_response();
+
+        // Browsers fail the preflight without the allow headers
+        if allow_origin.is_none() || !method_allowed {
+            return response;
+        }
+
+        let headers = response.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOWED_METHODS));
+        headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOWED_HEADERS));
+        headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE));
+
+        return with_allow_origin(response, allow_origin);
+    }
+
+    request.extensions_mut().insert(RequestOrigin(request_origin));
+
+    with_allow_origin(next.run(request).await, allow_origin)
+}
+
+fn with_allow_origin(mut response: Response, allow_origin: Option<HeaderValue>) -> Response {
+    if let Some(allow_origin) = allow_origin {
+        let headers = response.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, Router};
+    use tower::ServiceExt;
+    use uuid::{uuid, Uuid};
+
+    use crate::{
+        test_support::{config, site, site_with_id, SITE_ID},
+        RouterBuilder,
+    };
+
+    const OPEN_SITE_ID: Uuid = uuid!("0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e");
+
+    fn router() -> Router {
+        let site = site()
+            .with_allowed_origins(vec!["https://example.com".to_string()])
+            .expect("Valid origin");
+
+        RouterBuilder::new(config(vec![site, site_with_id(OPEN_SITE_ID)]))
+            .build()
+            .expect("Unable to build router")
+    }
+
+    #[tokio::test]
+    async fn test_preflight() {
+        let request = Request::options(format!("/v1/site/{SITE_ID}/challenge"))
+            .header("origin", "https://example.com")
+            .header("access-control-request-method", "GET")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 204);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://example.com");
+        assert_eq!(response.headers()["access-control-allow-methods"], "GET, POST, OPTIONS");
+        assert_eq!(response.headers()["access-control-allow-headers"], "content-type");
+    }
+
+    #[tokio::test]
+    async fn test_preflight_rejects_method() {
+        let request = Request::options(format!("/v1/site/{SITE_ID}/challenge"))
+            .header("origin", "https://example.com")
+            .header("access-control-request-method", "DELETE")
+            .header("access-control-request-headers", "api-key")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 204);
+        assert!(response.headers().get("access-control-allow-origin").is_none());
+        assert!(response.headers().get("access-control-allow-headers").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origin() {
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .header("origin", "https://example.com")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://example.com");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_origin() {
+        let router = router();
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .header("origin", "https://evil.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 403);
+        // Readable by the page
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://evil.com");
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .header("referer", "https://evil.com/login")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(router.clone().oneshot(request).await.unwrap().status(), 403);
+
+        // Non-browser clients send neither
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(router.oneshot(request).await.unwrap().status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_unrestricted_site() {
+        let request = Request::get(format!("/v1/site/{OPEN_SITE_ID}/challenge"))
+            .header("origin", "https://example.org")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert!(response.headers().get("access-control-allow-origin").is_none());
+    }
+}
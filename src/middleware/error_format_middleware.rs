@@ -0,0 +1,108 @@
+use axum::{
+    extract::{Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    config::ErrorFormat,
+    error_response::{ErrorResponse, ProblemDetails},
+    wire::{accepted_media_types, WireFormat},
+};
+
+use super::RequestId;
+
+const PROBLEM_JSON: &str = "application/problem+json";
+
+pub async fn error_format_middleware(
+    State(state): State<crate::state::State>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let accepts_problem = accepted_media_types(request.headers())
+        .any(|(media_type, quality)| media_type == PROBLEM_JSON && quality > 0.0);
+
+    let use_problem =
+        accepts_problem || state.get_config().get_error_format() == ErrorFormat::ProblemJson;
+
+    let format = WireFormat::from_accept(request.headers());
+
+    let instance = request.uri().path().to_owned();
+
+    let request_id = request.extensions().get::<RequestId>().cloned();
+
+    let mut response = next.run(request).await;
+
+    let Some(mut error) = response.extensions_mut().remove::<ErrorResponse>() else {
+        return response;
+    };
+
+    if let Some(RequestId(request_id)) = request_id {
+        error = error.with_request_id(request_id);
+    }
+
+    // The status and headers like Retry-After are taken from `response`
+    let rendered = match (format, use_problem) {
+        (WireFormat::Json, true) => ProblemDetails::new(&error, instance).into_response(),
+        (WireFormat::Json, false) => error.into_response(),
+        (format, true) => format.render(&ProblemDetails::new(&error, instance)),
+        (format, false) => format.render(&error),
+    };
+
+    let (mut parts, _) = response.into_parts();
+    let (rendered_parts, body) = rendered.into_parts();
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.extend(rendered_parts.headers);
+
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header::CONTENT_TYPE, Request},
+    };
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use crate::test_support::{body_json, router, site, SITE_ID};
+
+    async fn not_found(accept: &str) -> axum::response::Response {
+        let request = Request::get(format!("/v1/site/{}/challenge", Uuid::new_v4()))
+            .header("accept", accept)
+            .body(Body::empty())
+            .unwrap();
+
+        router(site()).oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_accept_problem_json() {
+        let response = not_found("application/json, application/problem+json").await;
+
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+        assert_eq!(body_json(response).await["status"], 404);
+
+        for accept in ["application/problem+jsonx", "application/problem+json;q=0"] {
+            let response = not_found(accept).await;
+
+            assert_eq!(response.headers()[CONTENT_TYPE], "application/json", "{accept}");
+            assert_eq!(body_json(response).await["id"], "SiteNotFound", "{accept}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejection_is_error_response() {
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge?action=a&action=b"))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router(site()).oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 400);
+        assert_eq!(body_json(response).await["id"], "InvalidQuery");
+    }
+}
@@ -1,42 +1,57 @@
 use axum::{
-    extract::{Path, Request, State},
+    extract::{rejection::PathRejection, Path, Request, State},
     middleware::Next,
     response::Response,
 };
 
 use crate::{
+    challenge::Challenge,
     error_response::{ErrorId, ErrorResponse},
+    site::Site,
     storage::Storage,
+    webhook::WebhookEvent,
 };
+use tracing::{info_span, Instrument, Span};
+use uuid::Uuid;
 
 pub async fn get_challenge_middleware(
     State(state): State<crate::state::State>,
-    Path((site_id, challenge_id)): Path<(String, String)>,
+    path: Result<Path<(String, String)>, PathRejection>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, ErrorResponse> {
-    let site_id = site_id
+    let Path((site_id, challenge_id)) = path?;
+
+    let site_id: Uuid = site_id
         .parse()
         .map_err(|_| ErrorResponse::new(ErrorId::SiteNotFound, "Site not found"))?;
 
-    let challenge_id = challenge_id
-        .parse()
-        .map_err(|_| ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge not Found"))?;
+    let challenge_id: Uuid = challenge_id.parse().map_err(|_| {
+        ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge not Found").with_site(&site_id)
+    })?;
 
-    let storage = state.get_storage().await;
+    let span = Span::current();
+    span.record("site_id", site_id.to_string());
+    span.record("challenge_id", challenge_id.to_string());
 
-    let site = storage
-        .get_site(&site_id)
-        .await
-        .ok_or(ErrorResponse::new(ErrorId::SiteNotFound, "Site not found"))?;
+    let lookup = async {
+        let storage = state.get_storage().await;
 
-    let challenge = storage
-        .get_challange(&challenge_id, &site)
-        .await
-        .ok_or(ErrorResponse::new(
-            ErrorId::ChallangeNotFound,
-            "Challenge not Found",
-        ))?;
+        let site = storage
+            .get_site(&site_id)
+            .await
+            .ok_or_else(|| {
+                ErrorResponse::new(ErrorId::SiteNotFound, "Site not found").with_site(&site_id)
+            })?;
+
+        let challenge = find_challenge(&state, &site, &challenge_id).await?;
+
+        Ok::<_, ErrorResponse>((site, challenge))
+    };
+
+    let (site, challenge) = lookup
+        .instrument(info_span!("get_challenge_middleware", %site_id, %challenge_id))
+        .await?;
 
     request.extensions_mut().insert(site);
     request.extensions_mut().insert(challenge);
@@ -45,3 +60,37 @@ pub async fn get_challenge_middleware(
 
     Ok(response)
 }
+
+/// Loads a challenge of `site`, deleting it if it expired
+pub(crate) async fn find_challenge(
+    state: &crate::state::State,
+    site: &Site,
+    challenge_id: &Uuid,
+) -> Result<Challenge, ErrorResponse> {
+    let storage = state.get_storage().await;
+
+    let challenge = storage
+        .get_challange(challenge_id, site)
+        .await
+        .ok_or_else(|| {
+            ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge not Found")
+                .with_site(site.get_id())
+                .with_challenge(challenge_id)
+        })?;
+
+    if challenge.is_expired() {
+        let _ = storage.delete_challenge(site, &challenge).await;
+
+        state
+            .get_webhooks()
+            .emit(site, WebhookEvent::ChallengeExpired, Some(challenge_id));
+
+        return Err(
+            ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge expired")
+                .with_site(site.get_id())
+                .with_challenge(challenge_id),
+        );
+    }
+
+    Ok(challenge)
+}
@@ -1,30 +1,54 @@
 use axum::{
-    extract::{Path, Request, State},
+    extract::{rejection::PathRejection, Path, Request, State},
     middleware::Next,
     response::Response,
 };
 
+use serde::Deserialize;
+
 use crate::{
     error_response::{ErrorId, ErrorResponse},
     storage::Storage,
 };
+use tracing::{info_span, Instrument, Span};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct SitePath {
+    #[serde(rename = "siteId")]
+    site_id: String,
+    /// axum 0.7 reads the `:batch` of `challenges:batch` as a parameter
+    /// matching the rest of the segment, so the literal is checked here
+    batch: Option<String>,
+}
 
 pub async fn get_site_middleware(
     State(state): State<crate::state::State>,
-    Path(site_id): Path<String>,
+    path: Result<Path<SitePath>, PathRejection>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, ErrorResponse> {
-    let site_id = site_id
+    let Path(SitePath { site_id, batch }) = path?;
+
+    if batch.is_some_and(|batch| batch != ":batch") {
+        return Err(ErrorResponse::new(ErrorId::RouteNotFound, "Route not found"));
+    }
+
+    let site_id: Uuid = site_id
         .parse()
         .map_err(|_| ErrorResponse::new(ErrorId::SiteNotFound, "Site not found"))?;
 
+    Span::current().record("site_id", site_id.to_string());
+
     let site = state
         .get_storage()
         .await
         .get_site(&site_id)
+        .instrument(info_span!("get_site_middleware", %site_id))
         .await
-        .ok_or(ErrorResponse::new(ErrorId::SiteNotFound, "Site not found"))?;
+        .ok_or_else(|| {
+            ErrorResponse::new(ErrorId::SiteNotFound, "Site not found").with_site(&site_id)
+        })?;
 
     request.extensions_mut().insert(site);
 
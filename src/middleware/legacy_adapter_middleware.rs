@@ -0,0 +1,104 @@
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderValue,
+    },
+    middleware::Next,
+    response::Response,
+};
+use tracing::warn;
+
+use crate::challenge::{Challenge, LegacyChallenge};
+
+/// Unix timestamp the unversioned routes were deprecated at, with the release
+/// serving the api under `/v1`
+const DEPRECATED_AT: u64 = 1_792_368_000;
+
+pub async fn legacy_adapter_middleware(
+    State(state): State<crate::state::State>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let successor = format!("</v1{}>; rel=\"successor-version\"", request.uri().path());
+
+    let mut response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|value| value == "application/json");
+
+    // Legacy clients predate the binary formats, leave those untouched
+    if let Some(challenge) = response.extensions_mut().remove::<Challenge>().filter(|_| is_json) {
+        match serde_json::to_string(&LegacyChallenge(&challenge)) {
+            Ok(body) => {
+                *response.body_mut() = Body::from(body);
+
+                let headers = response.headers_mut();
+                headers.remove(CONTENT_LENGTH);
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
+            Err(e) => warn!("Unable to serialize legacy challenge: {}", e),
+        }
+    }
+
+    let headers = response.headers_mut();
+
+    // RFC 9745 and RFC 8594
+    if let Ok(deprecation) = HeaderValue::from_str(&format!("@{DEPRECATED_AT}")) {
+        headers.insert("Deprecation", deprecation);
+    }
+
+    if let Some(sunset) = state.get_config().get_legacy_routes().sunset {
+        let sunset = httpdate::fmt_http_date(SystemTime::UNIX_EPOCH + Duration::from_secs(sunset));
+
+        if let Ok(sunset) = HeaderValue::from_str(&sunset) {
+            headers.insert("Sunset", sunset);
+        }
+    }
+
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert("Link", link);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use crate::{
+        config::LegacyRoutesConfig,
+        test_support::{body_json, config, site, SITE_ID},
+        RouterBuilder,
+    };
+
+    #[tokio::test]
+    async fn test_deprecation_headers() {
+        let legacy_routes = LegacyRoutesConfig {
+            sunset: Some(1_798_761_600),
+        };
+        let router = RouterBuilder::new(config(vec![site()]).with_legacy_routes(legacy_routes))
+            .build()
+            .expect("Unable to build router");
+
+        let request = Request::get(format!("/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()["deprecation"], "@1792368000");
+        assert_eq!(response.headers()["sunset"], "Fri, 01 Jan 2027 00:00:00 GMT");
+        assert_eq!(
+            response.headers()["link"],
+            format!("</v1/site/{SITE_ID}/challenge>; rel=\"successor-version\"")
+        );
+        assert!(body_json(response).await["challegesToSolve"].is_number());
+    }
+}
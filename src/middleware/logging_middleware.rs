@@ -1,5 +1,5 @@
 use axum::{extract::Request, middleware::Next, response::Response};
-use tracing::info;
+use tracing::{info, Span};
 
 pub async fn logging_middleware(request: Request, next: Next) -> Response {
     let execution_duration = tokio::time::Instant::now();
@@ -14,6 +14,10 @@ pub async fn logging_middleware(request: Request, next: Next) -> Response {
 
     let statuscode = response.status();
 
+    let span = Span::current();
+    span.record("status", statuscode.as_u16());
+    span.record("latency_ms", execution_duration as u64);
+
     info!("[{statuscode}][{execution_duration}ms] {method} {url}");
 
     response
@@ -1,11 +1,21 @@
+mod cors_middleware;
+mod error_format_middleware;
 mod get_challenge_middleware;
 mod get_site_middleware;
+mod legacy_adapter_middleware;
 mod logging_middleware;
+mod request_id_middleware;
 mod timeout_middleware;
 mod auth_middleware;
 
+pub use cors_middleware::{cors_middleware, RequestOrigin};
+pub use error_format_middleware::error_format_middleware;
+pub(crate) use get_challenge_middleware::find_challenge;
 pub use get_challenge_middleware::get_challenge_middleware;
 pub use get_site_middleware::get_site_middleware;
+pub use legacy_adapter_middleware::legacy_adapter_middleware;
 pub use logging_middleware::logging_middleware;
+pub use request_id_middleware::{request_id_middleware, RequestId};
 pub use timeout_middleware::timeout_middleware;
-pub use auth_middleware::auth_middleware;
\ No newline at end of file
+pub use auth_middleware::auth_middleware;
+pub(crate) use auth_middleware::{authenticate, record_failure, record_key_use};
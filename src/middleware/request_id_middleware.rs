@@ -0,0 +1,92 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tracing::{field::Empty, info_span, Instrument};
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+fn accept_request_id(value: &HeaderValue) -> Option<String> {
+    let value = value.to_str().ok()?;
+
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LENGTH
+        && value.chars().all(|c| c.is_ascii_graphic());
+
+    valid.then(|| value.to_owned())
+}
+
+pub async fn request_id_middleware(
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(accept_request_id)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        client_ip = Empty,
+        site_id = Empty,
+        api_key_id = Empty,
+        challenge_id = Empty,
+        status = Empty,
+        latency_ms = Empty,
+    );
+
+    crate::logging::set_parent_from_headers(&span, request.headers());
+
+    if let Some(ConnectInfo(addr)) = connect_info {
+        span.record("client_ip", addr.ip().to_string());
+    }
+
+    let header = HeaderValue::from_str(&request_id).expect("Request id is a valid header");
+
+    request.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+    request.extensions_mut().insert(RequestId(request_id));
+
+    let mut response = next.run(request).instrument(span).await;
+
+    response.headers_mut().insert(REQUEST_ID_HEADER, header);
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::accept_request_id;
+
+    #[test]
+    fn test_accept_request_id() {
+        let value = HeaderValue::from_static("0e4b2a6c-5d2e-4f0a");
+
+        assert_eq!(accept_request_id(&value).as_deref(), Some("0e4b2a6c-5d2e-4f0a"));
+    }
+
+    #[test]
+    fn test_reject_request_id() {
+        let too_long = "a".repeat(129);
+
+        assert!(accept_request_id(&HeaderValue::from_static("")).is_none());
+        assert!(accept_request_id(&HeaderValue::from_static("with space")).is_none());
+        assert!(accept_request_id(&HeaderValue::from_str(&too_long).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use crate::{
+    error_response::ProblemDetails,
+    wire::{WireFormat, CBOR, MSGPACK},
+};
+
+use utoipa::{
+    openapi::{
+        security::{ApiKey, ApiKeyValue, SecurityScheme},
+        OpenApi as OpenApiSpec, RefOr,
+    },
+    Modify, OpenApi,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "oxidecaptcha", description = "sha256 based proof-of-work captcha"),
+    modifiers(&ApiKeyAddon),
+    components(schemas(ProblemDetails)),
+    tags(
+        (name = "challenge", description = "Issue and validate challenges"),
+        (name = "admin", description = "Operational endpoints"),
+    )
+)]
+pub struct ApiDoc;
+
+struct ApiKeyAddon;
+
+impl Modify for ApiKeyAddon {
+    fn modify(&self, openapi: &mut OpenApiSpec) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("api-key"))),
+        );
+    }
+}
+
+/// Documents the CBOR and MessagePack variants of every json body. They use
+/// the same schemas, except that `format: byte` fields are raw byte strings
+/// instead of base64.
+pub fn add_wire_formats(spec: &mut OpenApiSpec) {
+    let json = WireFormat::Json.content_type();
+
+    for item in spec.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+
+        for operation in operations.into_iter().flatten() {
+            if let Some(body) = &mut operation.request_body {
+                if let Some(content) = body.content.get(json).cloned() {
+                    body.content.insert(CBOR.to_string(), content.clone());
+                    body.content.insert(MSGPACK.to_string(), content);
+                }
+            }
+
+            for response in operation.responses.responses.values_mut() {
+                let RefOr::T(response) = response else {
+                    continue;
+                };
+
+                if let Some(content) = response.content.get(json).cloned() {
+                    response.content.insert(CBOR.to_string(), content.clone());
+                    response.content.insert(MSGPACK.to_string(), content);
+                }
+            }
+        }
+    }
+}
+
+async fn openapi_json(State(spec): State<Arc<str>>) -> Response {
+    ([(CONTENT_TYPE, "application/json")], spec.to_string()).into_response()
+}
+
+pub fn router(spec: &OpenApiSpec) -> anyhow::Result<Router> {
+    let spec: Arc<str> = spec.to_json()?.into();
+
+    let router = Router::new()
+        .route("/openapi.json", get(openapi_json))
+        .with_state(spec);
+
+    #[cfg(feature = "swagger-ui")]
+    let router = router.merge(
+        utoipa_swagger_ui::SwaggerUi::new("/swagger-ui")
+            .config(utoipa_swagger_ui::Config::from("/openapi.json")),
+    );
+
+    Ok(router)
+}
@@ -0,0 +1,234 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use kale_duration::AbsoluteDuration;
+use serde::{Deserialize, Deserializer};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const PAYLOAD_LENGTH: usize = 16 + 16 + 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassTokenError {
+    Malformed,
+    BadSignature,
+    WrongSite,
+    Expired,
+}
+
+impl fmt::Display for PassTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let error = match self {
+            PassTokenError::Malformed => "Pass token malformed",
+            PassTokenError::BadSignature => "Pass token signature wrong",
+            PassTokenError::WrongSite => "Pass token was issued for another site",
+            PassTokenError::Expired => "Pass token expired",
+        };
+
+        f.write_str(error)
+    }
+}
+
+impl std::error::Error for PassTokenError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassTokenClaims {
+    pub site_id: Uuid,
+    pub challenge_id: Uuid,
+    pub expires_at: u64,
+}
+
+/// Signs and verifies the pass tokens handed out after a challenge was solved
+#[derive(Clone)]
+pub struct PassTokenConfig {
+    secret: Vec<u8>,
+    lifetime: Duration,
+}
+
+impl fmt::Debug for PassTokenConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PassTokenConfig")
+            .field("secret", &"..")
+            .field("lifetime", &self.lifetime)
+            .finish()
+    }
+}
+
+impl PassTokenConfig {
+    pub fn new(secret: impl Into<Vec<u8>>, lifetime: Duration) -> Self {
+        Self {
+            secret: secret.into(),
+            lifetime,
+        }
+    }
+
+    pub fn get_lifetime(&self) -> &Duration {
+        &self.lifetime
+    }
+
+    pub fn issue(&self, site_id: &Uuid, challenge_id: &Uuid) -> String {
+        let expires_at = SystemTime::now() + self.lifetime;
+        let expires_at = expires_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Duration since failed")
+            .as_secs();
+
+        self.issue_until(site_id, challenge_id, expires_at)
+    }
+
+    fn issue_until(&self, site_id: &Uuid, challenge_id: &Uuid, expires_at: u64) -> String {
+        let mut payload = Vec::with_capacity(PAYLOAD_LENGTH);
+        payload.extend_from_slice(site_id.as_bytes());
+        payload.extend_from_slice(challenge_id.as_bytes());
+        payload.extend_from_slice(&expires_at.to_be_bytes());
+
+        let signature = self.mac(&payload).finalize().into_bytes();
+
+        format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(&payload),
+            BASE64_URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    pub fn verify(&self, site_id: &Uuid, token: &str) -> Result<PassTokenClaims, PassTokenError> {
+        let (payload, signature) = token.split_once('.').ok_or(PassTokenError::Malformed)?;
+
+        let payload = BASE64_URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| PassTokenError::Malformed)?;
+        let signature = BASE64_URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| PassTokenError::Malformed)?;
+
+        if payload.len() != PAYLOAD_LENGTH {
+            return Err(PassTokenError::Malformed);
+        }
+
+        self.mac(&payload)
+            .verify_slice(&signature)
+            .map_err(|_| PassTokenError::BadSignature)?;
+
+        let claims = PassTokenClaims {
+            site_id: Uuid::from_slice(&payload[0..16]).map_err(|_| PassTokenError::Malformed)?,
+            challenge_id: Uuid::from_slice(&payload[16..32])
+                .map_err(|_| PassTokenError::Malformed)?,
+            expires_at: u64::from_be_bytes(
+                payload[32..40].try_into().map_err(|_| PassTokenError::Malformed)?,
+            ),
+        };
+
+        if &claims.site_id != site_id {
+            return Err(PassTokenError::WrongSite);
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("Duration since failed")
+            .as_secs();
+
+        if claims.expires_at < now {
+            return Err(PassTokenError::Expired);
+        }
+
+        Ok(claims)
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("Hmac accepts any key size");
+        mac.update(payload);
+        mac
+    }
+}
+
+impl<'de> Deserialize<'de> for PassTokenConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawPassTokenConfig {
+            secret: String,
+            lifetime: AbsoluteDuration,
+        }
+
+        let raw = RawPassTokenConfig::deserialize(deserializer)?;
+
+        if raw.secret.len() < 32 {
+            return Err(serde::de::Error::custom(
+                "passToken secret must be at least 32 bytes long",
+            ));
+        }
+
+        Ok(Self::new(raw.secret, raw.lifetime.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::{uuid, Uuid};
+
+    use super::{PassTokenConfig, PassTokenError};
+
+    const SITE_ID: Uuid = uuid!("60601796-7dc2-4d4f-afae-5728592bba6f");
+    const CHALLENGE_ID: Uuid = uuid!("0e4b2a6c-5d2e-4f0a-9c4e-6a1f0b8d2c3e");
+
+    fn config() -> PassTokenConfig {
+        PassTokenConfig::new("0123456789abcdef0123456789abcdef", Duration::from_secs(60))
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let token = config().issue(&SITE_ID, &CHALLENGE_ID);
+
+        let claims = config().verify(&SITE_ID, &token).expect("Token did not verify");
+
+        assert_eq!(claims.site_id, SITE_ID);
+        assert_eq!(claims.challenge_id, CHALLENGE_ID);
+    }
+
+    #[test]
+    fn test_wrong_site() {
+        let token = config().issue(&SITE_ID, &CHALLENGE_ID);
+
+        assert_eq!(config().verify(&CHALLENGE_ID, &token), Err(PassTokenError::WrongSite));
+    }
+
+    #[test]
+    fn test_expired() {
+        let token = config().issue_until(&SITE_ID, &CHALLENGE_ID, 12);
+
+        assert_eq!(config().verify(&SITE_ID, &token), Err(PassTokenError::Expired));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let token = config().issue(&SITE_ID, &CHALLENGE_ID);
+        let other = PassTokenConfig::new("another secret of at least 32 bytes", Duration::from_secs(60));
+
+        assert_eq!(other.verify(&SITE_ID, &token), Err(PassTokenError::BadSignature));
+        assert_eq!(config().verify(&SITE_ID, "garbage"), Err(PassTokenError::Malformed));
+    }
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let debug = format!("{:?}", config());
+
+        assert_eq!(debug, "PassTokenConfig { secret: \"..\", lifetime: 60s }");
+    }
+
+    #[test]
+    fn test_deserialize_short_secret() {
+        let testee = r#"{ "secret": "short", "lifetime": { "minutes": 5 } }"#;
+
+        serde_json::from_str::<PassTokenConfig>(testee).expect_err("Accepted a short secret");
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, Weak},
+};
+
+use serde::Deserialize;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::{challenge::Challenge, site::Site};
+
+/// Challenges generated per refill step on the blocking pool, the queue lock
+/// is only held to append a whole step
+const REFILL_STEP: usize = 32;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ChallengePoolConfig {
+    /// Challenges kept ready for hand-out
+    pub size: NonZeroUsize,
+}
+
+impl ChallengePoolConfig {
+    pub fn new(size: NonZeroUsize) -> Self {
+        Self { size }
+    }
+}
+
+#[derive(Debug)]
+struct SitePool {
+    challenges: Mutex<VecDeque<Challenge>>,
+    capacity: usize,
+    refill: Arc<Notify>,
+}
+
+impl SitePool {
+    fn len(&self) -> usize {
+        self.challenges.lock().expect("Pool poisoned").len()
+    }
+}
+
+/// Pre-generated, not yet issued challenges of the sites with a
+/// `challengePool`. Each pool is created on first use and refilled by a
+/// background task.
+#[derive(Debug, Default)]
+pub struct ChallengePools {
+    pools: Mutex<HashMap<Uuid, Arc<SitePool>>>,
+}
+
+impl ChallengePools {
+    /// Takes a challenge out of the site's pool, `None` if the site has no
+    /// pool or it is drained. Expiry starts at hand-out, not at generation.
+    pub fn take(&self, site: &Site) -> Option<Challenge> {
+        let pool = self.pool(site)?;
+
+        let challenge = pool.challenges.lock().expect("Pool poisoned").pop_front();
+
+        pool.refill.notify_one();
+
+        challenge.map(|challenge| challenge.reissue(site))
+    }
+
+    /// Appends the fill level of every pool in the prometheus text format
+    pub fn render(&self, output: &mut String) {
+        let pools = self.pools.lock().expect("Pools poisoned");
+
+        Self::render_gauge(
+            output,
+            "oxidecaptcha_challenge_pool_size",
+            "Challenges ready in the pool",
+            pools
+                .iter()
+                .map(|(site_id, pool)| (site_id, pool.len())),
+        );
+        Self::render_gauge(
+            output,
+            "oxidecaptcha_challenge_pool_capacity",
+            "Configured size of the pool",
+            pools.iter().map(|(site_id, pool)| (site_id, pool.capacity)),
+        );
+    }
+
+    fn render_gauge<'a>(
+        output: &mut String,
+        name: &str,
+        help: &str,
+        values: impl Iterator<Item = (&'a Uuid, usize)>,
+    ) {
+        let _ = writeln!(output, "# HELP {name} {help}");
+        let _ = writeln!(output, "# TYPE {name} gauge");
+
+        for (site_id, value) in values {
+            let _ = writeln!(output, "{name}{{site_id=\"{site_id}\"}} {value}");
+        }
+    }
+
+    fn pool(&self, site: &Site) -> Option<Arc<SitePool>> {
+        let config = site.get_challenge_pool()?;
+
+        let mut pools = self.pools.lock().expect("Pools poisoned");
+
+        let pool = pools.entry(*site.get_id()).or_insert_with(|| {
+            let pool = Arc::new(SitePool {
+                challenges: Mutex::new(VecDeque::with_capacity(config.size.get())),
+                capacity: config.size.get(),
+                refill: Arc::new(Notify::new()),
+            });
+
+            tokio::spawn(refill(
+                Arc::downgrade(&pool),
+                pool.refill.clone(),
+                site.clone(),
+            ));
+
+            pool
+        });
+
+        Some(pool.clone())
+    }
+}
+
+/// Tops the pool up after every hand-out, stops once the pool is dropped
+async fn refill(pool: Weak<SitePool>, notify: Arc<Notify>, site: Site) {
+    loop {
+        loop {
+            let missing = match pool.upgrade() {
+                Some(pool) => pool.capacity - pool.len(),
+                None => return,
+            };
+
+            if missing == 0 {
+                break;
+            }
+
+            // Prefix generation scales with the site's prefix count and
+            // length, so it is kept off the executor
+            let site = site.clone();
+            let step = tokio::task::spawn_blocking(move || {
+                (0..missing.min(REFILL_STEP))
+                    .map(|_| site.generate_challenge())
+                    .collect::<Vec<Challenge>>()
+            })
+            .await;
+
+            let (Ok(step), Some(pool)) = (step, pool.upgrade()) else {
+                return;
+            };
+
+            pool.challenges.lock().expect("Pool poisoned").extend(step);
+        }
+
+        notify.notified().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroUsize, time::Duration};
+
+    use uuid::uuid;
+
+    use crate::site::Site;
+
+    use super::{ChallengePoolConfig, ChallengePools};
+
+    fn site(pool_size: Option<usize>) -> Site {
+        let site = Site::new(
+            uuid!("60601796-7dc2-4d4f-afae-5728592bba6f"),
+            "key".to_string(),
+            4,
+            8,
+            2,
+            4,
+            8,
+            Duration::from_secs(60),
+        );
+
+        match pool_size {
+            Some(size) => {
+                site.with_challenge_pool(ChallengePoolConfig::new(NonZeroUsize::new(size).unwrap()))
+            }
+            None => site,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_without_pool() {
+        let pools = ChallengePools::default();
+
+        assert!(pools.take(&site(None)).is_none());
+
+        let mut output = String::new();
+        pools.render(&mut output);
+
+        assert!(!output.contains("site_id"));
+    }
+
+    /// Polls until the site's pool holds `size` challenges
+    async fn wait_for_size(pools: &ChallengePools, site: &Site, size: usize) {
+        let pool = pools.pool(site).expect("Site has a pool");
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pool.len() != size {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("Pool was not refilled");
+    }
+
+    #[tokio::test]
+    async fn test_refill() {
+        let pools = ChallengePools::default();
+        let site = site(Some(40));
+
+        assert!(pools.take(&site).is_none());
+
+        wait_for_size(&pools, &site, 40).await;
+
+        let mut output = String::new();
+        pools.render(&mut output);
+
+        assert!(output.contains(
+            "oxidecaptcha_challenge_pool_size{site_id=\"60601796-7dc2-4d4f-afae-5728592bba6f\"} 40\n"
+        ));
+
+        let pool = pools.pool(&site).expect("Site has a pool");
+        let aged = site.generate_challenge().aged(Duration::from_secs(30));
+        *pool.challenges.lock().unwrap().front_mut().unwrap() = aged;
+
+        let challenge = pools.take(&site).expect("Pool is empty");
+
+        assert!(challenge.get_age() < Duration::from_secs(1));
+
+        wait_for_size(&pools, &site, 40).await;
+    }
+}
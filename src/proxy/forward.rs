@@ -0,0 +1,171 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    http::{
+        header::{self, HeaderName},
+        uri::PathAndQuery,
+        HeaderMap, HeaderValue, Uri,
+    },
+    response::{IntoResponse, Response},
+};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use tracing::{info_span, warn, Instrument};
+
+use crate::{
+    config::ProxyConfig,
+    error_response::{ErrorId, ErrorResponse},
+};
+
+const HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+#[derive(Debug, Clone)]
+pub(crate) struct Upstream {
+    client: Client<HttpConnector, Body>,
+    uri: Uri,
+    timeout: std::time::Duration,
+    config: Arc<ProxyConfig>,
+}
+
+impl Upstream {
+    pub(crate) fn new(config: Arc<ProxyConfig>) -> Self {
+        let client = Client::builder(TokioExecutor::new()).build_http();
+
+        Self {
+            client,
+            uri: config.upstream.clone(),
+            timeout: config.get_upstream_timeout(),
+            config,
+        }
+    }
+
+    fn target(&self, request_uri: &Uri) -> Result<Uri, ErrorResponse> {
+        let base_path = self.uri.path().trim_end_matches('/');
+        let path_and_query = request_uri
+            .path_and_query()
+            .map(PathAndQuery::as_str)
+            .unwrap_or("/");
+
+        let mut parts = self.uri.clone().into_parts();
+        parts.path_and_query = Some(
+            format!("{base_path}{path_and_query}")
+                .parse()
+                .map_err(|_| ErrorResponse::new(ErrorId::BadGateway, "Invalid upstream path"))?,
+        );
+
+        Uri::from_parts(parts)
+            .map_err(|_| ErrorResponse::new(ErrorId::BadGateway, "Invalid upstream uri"))
+    }
+
+    pub(crate) async fn forward(&self, request: Request) -> Response {
+        let (mut parts, body) = request.into_parts();
+
+        let target = match self.target(&parts.uri) {
+            Ok(v) => v,
+            Err(e) => return e.into_response(),
+        };
+
+        let client_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip());
+
+        strip_hop_by_hop(&mut parts.headers);
+        self.strip_clearance_cookies(&mut parts.headers);
+
+        if let Some(host) = parts.headers.remove(header::HOST) {
+            parts.headers.insert(X_FORWARDED_HOST, host);
+        }
+
+        if let Some(client_ip) = client_ip {
+            let forwarded_for = match parts.headers.get(X_FORWARDED_FOR).and_then(|v| v.to_str().ok()) {
+                Some(previous) => format!("{previous}, {client_ip}"),
+                None => client_ip.to_string(),
+            };
+
+            if let Ok(value) = HeaderValue::from_str(&forwarded_for) {
+                parts.headers.insert(X_FORWARDED_FOR, value);
+            }
+        }
+
+        let mut upstream_request = Request::from_parts(parts, body);
+        *upstream_request.uri_mut() = target;
+
+        let response = tokio::time::timeout(self.timeout, self.client.request(upstream_request))
+            .instrument(info_span!("proxy.forward"))
+            .await;
+
+        match response {
+            Ok(Ok(response)) => {
+                let (mut parts, body) = response.into_parts();
+                strip_hop_by_hop(&mut parts.headers);
+
+                Response::from_parts(parts, Body::new(body))
+            }
+            Ok(Err(e)) => {
+                warn!("Upstream request failed: {}", e);
+                ErrorResponse::new(ErrorId::BadGateway, "Upstream request failed").into_response()
+            }
+            Err(_) => ErrorResponse::new(ErrorId::Timeout, "Upstream has timed-out").into_response(),
+        }
+    }
+}
+
+impl Upstream {
+    /// Clearance cookies are only meant for the proxy, the upstream must not
+    /// be able to replay them
+    fn strip_clearance_cookies(&self, headers: &mut HeaderMap) {
+        let cookies: Vec<String> = headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .map(str::trim)
+            .filter(|cookie| {
+                let name = cookie.split_once('=').map_or(*cookie, |(name, _)| name);
+                !cookie.is_empty() && !self.config.is_clearance_cookie(name)
+            })
+            .map(str::to_string)
+            .collect();
+
+        headers.remove(header::COOKIE);
+
+        if cookies.is_empty() {
+            return;
+        }
+
+        if let Ok(value) = HeaderValue::from_str(&cookies.join("; ")) {
+            headers.insert(header::COOKIE, value);
+        }
+    }
+}
+
+fn strip_hop_by_hop(headers: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+        .collect();
+
+    for name in listed.iter().chain(HOP_BY_HOP_HEADERS.iter()) {
+        headers.remove(name);
+    }
+}
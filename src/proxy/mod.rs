@@ -0,0 +1,424 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, SET_COOKIE},
+        HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Router,
+};
+use serde::Serialize;
+use tracing::Span;
+
+use crate::{
+    challenge::Challenge,
+    config::{normalize_path, ProxyConfig},
+    error_response::{ErrorId, ErrorResponse},
+    guard::find_cookie,
+    routes::{check_solutions, get_challange, preflight, RequestBody},
+    site::Site,
+    storage::Storage,
+    wire::{Wire, WireFormat},
+};
+
+mod forward;
+
+use forward::Upstream;
+
+const INTERSTITIAL: &str = include_str!("interstitial.html");
+
+#[derive(Debug, Clone)]
+struct ProxyState {
+    state: crate::State,
+    config: Arc<ProxyConfig>,
+    upstream: Upstream,
+}
+
+#[derive(Debug, Serialize)]
+struct VerifyResponse {
+    valid: bool,
+}
+
+/// Routes of the interstitial under `/.oxidecaptcha`, every other request is
+/// forwarded to the upstream once it carries a valid clearance cookie
+pub(crate) fn router(state: &crate::State) -> Result<Router> {
+    let config = state
+        .get_config()
+        .get_proxy()
+        .ok_or_else(|| anyhow::anyhow!("Proxy routes enabled without a proxy config"))?;
+
+    let config = Arc::new(config.clone());
+
+    let proxy_state = ProxyState {
+        state: state.clone(),
+        upstream: Upstream::new(config.clone()),
+        config,
+    };
+
+    let get_site_middleware =
+        axum::middleware::from_fn_with_state(state.clone(), crate::middleware::get_site_middleware);
+
+    let get_challenge_middleware = axum::middleware::from_fn_with_state(
+        state.clone(),
+        crate::middleware::get_challenge_middleware,
+    );
+
+    let cors_middleware = axum::middleware::from_fn(crate::middleware::cors_middleware);
+
+    let challenge_router = Router::new()
+        .route("/site/:siteId/challenge", get(get_challange).options(preflight))
+        .route_layer(cors_middleware.clone())
+        .route_layer(get_site_middleware)
+        .with_state(state.clone());
+
+    let verify_router = Router::new()
+        .route("/site/:siteId/challenge/:challengeId/verify", post(verify_challenge))
+        .route_layer(cors_middleware)
+        .route_layer(get_challenge_middleware)
+        .with_state(proxy_state.clone());
+
+    Ok(Router::new()
+        .nest("/.oxidecaptcha", challenge_router.merge(verify_router))
+        .fallback(proxy)
+        .with_state(proxy_state))
+}
+
+async fn verify_challenge(
+    State(proxy): State<ProxyState>,
+    Extension(site): Extension<Site>,
+    Extension(challenge): Extension<Challenge>,
+    format: WireFormat,
+    Wire(body): Wire<RequestBody>,
+) -> Result<Response, ErrorResponse> {
+    let pass_token = site.get_pass_token().ok_or_else(|| {
+        ErrorResponse::new(ErrorId::InternalServerError, "Site has no passToken config")
+            .with_site(site.get_id())
+    })?;
+
+    let valid = check_solutions(&proxy.state, &site, &challenge, body.solutions, false)
+        .await?
+        .valid;
+
+    let mut response = format.render(&VerifyResponse { valid });
+
+    if valid {
+        let token = pass_token.issue(site.get_id(), challenge.get_id());
+
+        let mut cookie = format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax",
+            proxy.config.cookie_name_for(site.get_id()),
+            token,
+            pass_token.get_lifetime().as_secs()
+        );
+
+        if proxy.config.secure_cookie {
+            cookie.push_str("; Secure");
+        }
+
+        let cookie = HeaderValue::from_str(&cookie).map_err(|_| {
+            ErrorResponse::new(ErrorId::InternalServerError, "Invalid clearance cookie")
+                .with_site(site.get_id())
+        })?;
+
+        response.headers_mut().insert(SET_COOKIE, cookie);
+    }
+
+    Ok(response)
+}
+
+async fn proxy(State(proxy): State<ProxyState>, request: Request) -> Response {
+    let path = match normalize_path(request.uri().path()) {
+        Some(v) => v,
+        None => {
+            return ErrorResponse::new(ErrorId::InvalidPath, "Path with dot segments or encoded separators")
+                .into_response()
+        }
+    };
+
+    if proxy.config.is_allowlisted(&path) {
+        return proxy.upstream.forward(request).await;
+    }
+
+    let site_id = match proxy.config.site_for_path(&path) {
+        Some(v) => *v,
+        None => return proxy.upstream.forward(request).await,
+    };
+
+    Span::current().record("site_id", site_id.to_string());
+
+    let site = match proxy.state.get_storage().await.get_site(&site_id).await {
+        Some(v) => v,
+        None => {
+            return ErrorResponse::new(ErrorId::SiteNotFound, "Site not found")
+                .with_site(&site_id)
+                .into_response()
+        }
+    };
+
+    let pass_token = match site.get_pass_token() {
+        Some(v) => v,
+        None => {
+            return ErrorResponse::new(ErrorId::InternalServerError, "Site has no passToken config")
+                .with_site(&site_id)
+                .into_response()
+        }
+    };
+
+    let cleared = find_cookie(request.headers(), &proxy.config.cookie_name_for(&site_id))
+        .is_some_and(|token| pass_token.verify(&site_id, token).is_ok());
+
+    if cleared {
+        return proxy.upstream.forward(request).await;
+    }
+
+    match *request.method() {
+        Method::GET | Method::HEAD => interstitial(&site),
+        _ => ErrorResponse::new(ErrorId::PassTokenRequired, "Solve the challenge first")
+            .with_site(&site_id)
+            .into_response(),
+    }
+}
+
+fn interstitial(site: &Site) -> Response {
+    let body = INTERSTITIAL.replace("{{siteId}}", &site.get_id().to_string());
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [
+            (CONTENT_TYPE, "text/html; charset=utf-8"),
+            (CACHE_CONTROL, "no-store"),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{
+        body::Body,
+        http::{header::COOKIE, header::SET_COOKIE, HeaderMap, Request},
+        routing::get,
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::{
+        config::{ProxyConfig, RouteGroup},
+        pass_token::PassTokenConfig,
+        test_support::{body_json, config, site, solve_json, SITE_ID},
+        RouterBuilder,
+    };
+
+    async fn spawn_upstream() -> String {
+        let upstream = Router::new()
+            .route("/", get(|| async { "upstream index" }))
+            .route("/static/app.js", get(|| async { "upstream asset" }))
+            .route(
+                "/cookies",
+                get(|headers: HeaderMap| async move {
+                    headers
+                        .get(COOKIE)
+                        .map(|cookie| cookie.to_str().unwrap().to_string())
+                        .unwrap_or_default()
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Unable to bind upstream");
+        let address = listener.local_addr().expect("No local address");
+
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        format!("http://{address}")
+    }
+
+    async fn proxy_router() -> Router {
+        let site = site().with_pass_token(PassTokenConfig::new(
+            "0123456789abcdef0123456789abcdef",
+            Duration::from_secs(60),
+        ));
+
+        let proxy: ProxyConfig = serde_json::from_value(serde_json::json!({
+            "upstream": spawn_upstream().await,
+            "allowlist": ["/static/"],
+            "sites": [{ "pathPrefix": "/", "siteId": SITE_ID }]
+        }))
+        .expect("Failed parsing proxy config");
+
+        RouterBuilder::new(config(vec![site]).with_proxy(proxy))
+            .routes([RouteGroup::Proxy])
+            .build()
+            .expect("Unable to build router")
+    }
+
+    #[tokio::test]
+    async fn test_interstitial_without_cookie() {
+        let request = Request::get("/").body(Body::empty()).unwrap();
+
+        let response = proxy_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 401);
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        assert!(body.contains(&format!("/.oxidecaptcha/site/{SITE_ID}")));
+    }
+
+    #[tokio::test]
+    async fn test_allowlisted_path_is_forwarded() {
+        let request = Request::get("/static/app.js").body(Body::empty()).unwrap();
+
+        let response = proxy_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"upstream asset");
+    }
+
+    #[tokio::test]
+    async fn test_post_without_cookie() {
+        let request = Request::post("/").body(Body::empty()).unwrap();
+
+        let response = proxy_router().await.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 401);
+        assert_eq!(body_json(response).await["id"], "PassTokenRequired");
+    }
+
+    #[tokio::test]
+    async fn test_solve_and_forward() {
+        let router = proxy_router().await;
+
+        let request = Request::get(format!("/.oxidecaptcha/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let challenge = body_json(router.clone().oneshot(request).await.unwrap()).await;
+
+        let difficulty = challenge["difficulty"].as_u64().unwrap() as u8;
+        let prefixes = challenge["prefixes"].as_array().unwrap();
+
+        let mut solutions = vec![serde_json::Value::Null; prefixes.len()];
+        for (index, prefix) in prefixes.iter().enumerate().take(2) {
+            solutions[index] = solve_json(prefix, difficulty);
+        }
+
+        let request = Request::post(format!(
+            "/.oxidecaptcha/site/{SITE_ID}/challenge/{}/verify",
+            challenge["id"].as_str().unwrap()
+        ))
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::json!({ "solutions": solutions }).to_string()))
+        .unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        let cookie = response.headers()[SET_COOKIE].to_str().unwrap().to_string();
+        assert!(cookie.starts_with(&format!("oxidecaptcha_clearance_{}=", SITE_ID.simple())));
+        assert!(cookie.contains("HttpOnly"));
+
+        assert_eq!(body_json(response).await["valid"], true);
+
+        let cookie = cookie.split(';').next().unwrap().to_string();
+        let request = Request::get("/")
+            .header(COOKIE, &cookie)
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"upstream index");
+
+        // The clearance cookie is not handed to the upstream
+        let request = Request::get("/cookies")
+            .header(COOKIE, format!("theme=dark; {cookie}"))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"theme=dark");
+    }
+
+    #[tokio::test]
+    async fn test_verify_cbor() {
+        let router = proxy_router().await;
+
+        let request = Request::get(format!("/.oxidecaptcha/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let challenge = body_json(router.clone().oneshot(request).await.unwrap()).await;
+
+        let solutions = vec![ciborium::Value::Null; challenge["prefixes"].as_array().unwrap().len()];
+
+        let mut body = Vec::new();
+        ciborium::into_writer(
+            &ciborium::Value::Map(vec![("solutions".into(), ciborium::Value::Array(solutions))]),
+            &mut body,
+        )
+        .unwrap();
+
+        let request = Request::post(format!(
+            "/.oxidecaptcha/site/{SITE_ID}/challenge/{}/verify",
+            challenge["id"].as_str().unwrap()
+        ))
+        .header("accept", "application/cbor")
+        .header("content-type", "application/cbor")
+        .body(Body::from(body))
+        .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "application/cbor");
+        assert!(response.headers().get(SET_COOKIE).is_none());
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let response: ciborium::Value = ciborium::from_reader(&body[..]).unwrap();
+
+        assert_eq!(
+            response,
+            ciborium::Value::Map(vec![("valid".into(), false.into())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_allowlist_bypass() {
+        let router = proxy_router().await;
+
+        for path in ["/static/../", "/static/%2e%2e/", "/static%2f..%2f", "/static/.%2E/"] {
+            let request = Request::get(path).body(Body::empty()).unwrap();
+
+            let response = router.clone().oneshot(request).await.unwrap();
+
+            assert_eq!(response.status(), 400, "{path}");
+        }
+
+        // Not below the allowlisted /static/
+        let request = Request::get("/staticx/app.js").body(Body::empty()).unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 401);
+    }
+}
@@ -1,28 +1,75 @@
 use axum::{extract::State, Extension};
 
 use crate::{
+    audit::{AuditAction, AuditEntry, AuditOutcome},
+    auth::ApiKeyEntry,
     challenge::Challenge,
     error_response::{ErrorId, ErrorResponse},
     site::Site,
     Storage,
 };
 
-pub async fn delete_challange<'site>(
+#[utoipa::path(
+    delete,
+    path = "/site/{siteId}/challenge/{challengeId}",
+    tag = "challenge",
+    params(
+        ("siteId" = Uuid, Path, description = "Id of the site"),
+        ("challengeId" = Uuid, Path, description = "Id of the challenge"),
+    ),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Challenge deleted"),
+        (status = 401, description = "Api-key missing, wrong or expired", body = ErrorResponse),
+        (status = 403, description = "Api-key lacks the required scope", body = ErrorResponse),
+        (status = 429, description = "Locked out after too many failed api-key checks", body = ErrorResponse),
+        (status = 404, description = "Site or challenge not found", body = ErrorResponse),
+        (status = 503, description = "Timeout", body = ErrorResponse),
+    )
+)]
+pub async fn delete_challange(
     State(state): State<crate::State>,
     Extension(site): Extension<Site>,
     Extension(challenge): Extension<Challenge>,
+    Extension(entry): Extension<ApiKeyEntry>,
+) -> Result<(), ErrorResponse> {
+    delete(&state, &site, &challenge, &entry).await
+}
+
+/// Deletes the challenge and audits it, shared with the gRPC service
+pub(crate) async fn delete(
+    state: &crate::State,
+    site: &Site,
+    challenge: &Challenge,
+    entry: &ApiKeyEntry,
 ) -> Result<(), ErrorResponse> {
     let store = state.get_storage().await;
 
-    store
-        .delete_challenge(&site, &challenge)
-        .await
-        .map_err(|e| match e {
+    let result = store.delete_challenge(site, challenge).await;
+
+    let outcome = match result {
+        Ok(()) => AuditOutcome::Success,
+        Err(_) => AuditOutcome::Failure,
+    };
+
+    state.get_audit().record(
+        AuditEntry::new(
+            format!("apiKey:{}", entry.get_id()),
+            AuditAction::ChallengeDeleted,
+            outcome,
+        )
+        .with_site(site.get_id())
+        .with_detail(challenge.get_id().to_string()),
+    );
+
+    result.map_err(|e| match e {
             crate::storage::StorageError::SiteNotFoundError => {
-                ErrorResponse::new(ErrorId::SiteNotFound, "Site not found")
+                ErrorResponse::new(ErrorId::SiteNotFound, "Site not found").with_site(site.get_id())
             }
             crate::storage::StorageError::ChallengeNotFound => {
                 ErrorResponse::new(ErrorId::ChallangeNotFound, "Challenge not found")
+                    .with_site(site.get_id())
+                    .with_challenge(challenge.get_id())
             }
         })
 }
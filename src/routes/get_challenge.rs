@@ -1,27 +1,135 @@
 use axum::{
-    extract::State,
-    response::{IntoResponse, Response},
+    extract::{rejection::QueryRejection, Query, State},
+    http::StatusCode,
+    response::Response,
     Extension,
 };
+use serde::Deserialize;
+use utoipa::IntoParams;
 
 use crate::{
-    error_response::{ErrorId::SiteNotFound, ErrorResponse},
+    challenge::Challenge,
+    error_response::{ErrorId, ErrorResponse},
+    middleware::RequestOrigin,
     site::Site,
+    webhook::WebhookEvent,
+    wire::WireFormat,
     Storage,
 };
 
+const MAX_ACTION_LENGTH: usize = 64;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChallengeQuery {
+    /// Action the challenge protects, e.g. `login`. Up to 64 characters out
+    /// of `A-Za-z0-9_-./`, echoed in detailed validation responses
+    action: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/site/{siteId}/challenge",
+    tag = "challenge",
+    params(("siteId" = Uuid, Path, description = "Id of the site"), ChallengeQuery),
+    responses(
+        (status = 200, description = "A new challenge", body = Challenge),
+        (status = 400, description = "Invalid action", body = ErrorResponse),
+        (status = 403, description = "Origin not allowed", body = ErrorResponse),
+        (status = 404, description = "Site not found", body = ErrorResponse),
+        (status = 503, description = "Timeout", body = ErrorResponse),
+    )
+)]
 pub async fn get_challange(
     State(state): State<crate::State>,
     Extension(site): Extension<Site>,
+    query: Result<Query<ChallengeQuery>, QueryRejection>,
+    origin: Option<Extension<RequestOrigin>>,
+    format: WireFormat,
 ) -> Result<Response, ErrorResponse> {
-    let challenge = site.generate_challenge();
+    let Query(query) = query?;
+
+    if let Some(action) = &query.action {
+        if !is_valid_action(action) {
+            return Err(
+                ErrorResponse::new(ErrorId::InvalidAction, "Invalid action").with_site(site.get_id())
+            );
+        }
+    }
+
+    let origin = match origin {
+        Some(Extension(RequestOrigin(origin))) => origin,
+        None => None,
+    };
+
+    let challenge = issue_challenge(&state, &site, origin, query.action).await?;
+
+    let mut response = format.render(&challenge);
+    response.extensions_mut().insert(challenge);
+
+    Ok(response)
+}
+
+/// Takes a challenge from the pool or generates one, stores it and announces
+/// it. `action` has to be checked with [`is_valid_action`] beforehand.
+pub(crate) async fn issue_challenge(
+    state: &crate::State,
+    site: &Site,
+    origin: Option<String>,
+    action: Option<String>,
+) -> Result<Challenge, ErrorResponse> {
+    let mut challenge = state
+        .get_pools()
+        .take(site)
+        .unwrap_or_else(|| site.generate_challenge());
+
+    if let Some(origin) = origin {
+        challenge = challenge.with_origin(origin);
+    }
+
+    if let Some(action) = action {
+        challenge = challenge.with_action(action);
+    }
 
     state
         .get_storage()
         .await
-        .store_challenge(&site, &challenge)
+        .store_challenge(site, &challenge)
         .await
-        .map_err(|_| ErrorResponse::new(SiteNotFound, "Site not found"))?;
+        .map_err(|_| {
+            ErrorResponse::new(ErrorId::SiteNotFound, "Site not found").with_site(site.get_id())
+        })?;
+
+    state
+        .get_webhooks()
+        .emit(site, WebhookEvent::ChallengeIssued, Some(challenge.get_id()));
+
+    Ok(challenge)
+}
+
+/// Answered by the cors middleware, which runs before this
+pub async fn preflight() -> StatusCode {
+    StatusCode::NO_CONTENT
+}
+
+pub(crate) fn is_valid_action(action: &str) -> bool {
+    !action.is_empty()
+        && action.len() <= MAX_ACTION_LENGTH
+        && action
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"_-./".contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_valid_action;
 
-    Ok(challenge.into_response())
+    #[test]
+    fn test_valid_action() {
+        assert!(is_valid_action("login"));
+        assert!(is_valid_action("checkout/step-2"));
+        assert!(!is_valid_action(""));
+        assert!(!is_valid_action("<script>"));
+        assert!(!is_valid_action(&"a".repeat(65)));
+    }
 }
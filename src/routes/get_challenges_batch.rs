@@ -0,0 +1,80 @@
+use axum::{extract::State, response::Response, Extension};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    challenge::Challenge,
+    error_response::{ErrorId, ErrorResponse},
+    middleware::RequestOrigin,
+    site::Site,
+    wire::{Wire, WireFormat},
+};
+
+use super::get_challenge::{is_valid_action, issue_challenge};
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BatchChallengeRequest {
+    /// Challenges to issue, at most the site's `maxBatchSize`
+    count: usize,
+    /// Action all challenges protect, see `GET /site/{siteId}/challenge`
+    action: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchChallengeResponse {
+    challenges: Vec<Challenge>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/site/{siteId}/challenges:batch",
+    tag = "challenge",
+    params(("siteId" = Uuid, Path, description = "Id of the site")),
+    request_body = BatchChallengeRequest,
+    responses(
+        (status = 200, description = "New challenges", body = BatchChallengeResponse),
+        (status = 400, description = "Invalid count or action", body = ErrorResponse),
+        (status = 403, description = "Origin not allowed", body = ErrorResponse),
+        (status = 404, description = "Site not found", body = ErrorResponse),
+        (status = 503, description = "Timeout", body = ErrorResponse),
+    )
+)]
+pub async fn get_challenges_batch(
+    State(state): State<crate::State>,
+    Extension(site): Extension<Site>,
+    origin: Option<Extension<RequestOrigin>>,
+    format: WireFormat,
+    Wire(body): Wire<BatchChallengeRequest>,
+) -> Result<Response, ErrorResponse> {
+    let max_batch_size = site.get_max_batch_size();
+
+    if body.count == 0 || body.count > max_batch_size {
+        return Err(ErrorResponse::new(
+            ErrorId::InvalidBatchSize,
+            format!("Expected 1 to {max_batch_size} challenges, got {}", body.count),
+        )
+        .with_site(site.get_id()));
+    }
+
+    if let Some(action) = &body.action {
+        if !is_valid_action(action) {
+            return Err(
+                ErrorResponse::new(ErrorId::InvalidAction, "Invalid action").with_site(site.get_id())
+            );
+        }
+    }
+
+    let origin = match origin {
+        Some(Extension(RequestOrigin(origin))) => origin,
+        None => None,
+    };
+
+    let mut challenges = Vec::with_capacity(body.count);
+
+    for _ in 0..body.count {
+        challenges.push(issue_challenge(&state, &site, origin.clone(), body.action.clone()).await?);
+    }
+
+    Ok(format.render(&BatchChallengeResponse { challenges }))
+}
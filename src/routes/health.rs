@@ -1,18 +1,36 @@
-use axum::extract::State;
+use axum::{extract::State, response::Response};
+use serde::Serialize;
+use utoipa::ToSchema;
 
-use crate::{error_response::{ErrorId, ErrorResponse}, storage::Storage};
+use crate::{error_response::{ErrorId, ErrorResponse}, storage::Storage, wire::WireFormat};
 
-pub async fn _health(
-    State(state): State<crate::State>
-) -> Result<(), ErrorResponse> {
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HealthResponse {
+    healthy: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Storage is healthy", body = HealthResponse),
+        (status = 500, description = "Storage is not healthy", body = ErrorResponse),
+        (status = 503, description = "Timeout", body = ErrorResponse),
+    )
+)]
+pub async fn health(
+    State(state): State<crate::State>,
+    format: WireFormat,
+) -> Result<Response, ErrorResponse> {
     let storage_healthy = state.get_storage()
         .await
         .healthy()
         .await;
 
-    if storage_healthy == false {
+    if !storage_healthy {
        return Err(ErrorResponse::new(ErrorId::InternalServerError, "Storage is not healthy"));
     }
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(format.render(&HealthResponse { healthy: true }))
+}
@@ -0,0 +1,20 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Metrics in the prometheus text format", content_type = "text/plain"),
+    )
+)]
+pub async fn metrics(State(state): State<crate::State>) -> impl IntoResponse {
+    let mut output = state.get_metrics().render();
+    state.get_pools().render(&mut output);
+    state.get_audit().render(&mut output);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        output,
+    )
+}
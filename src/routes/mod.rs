@@ -1,8 +1,18 @@
 mod delete_challenge;
 mod get_challenge;
+mod get_challenges_batch;
 mod validate_challenge;
+mod validate_challenges_batch;
 mod health;
+mod metrics;
 
-pub use delete_challenge::delete_challange;
-pub use get_challenge::get_challange;
-pub use validate_challenge::validate_challenges;
+pub use delete_challenge::{__path_delete_challange, delete_challange};
+pub(crate) use delete_challenge::delete;
+pub use get_challenge::{__path_get_challange, get_challange, preflight};
+pub(crate) use get_challenge::{is_valid_action, issue_challenge};
+pub use get_challenges_batch::{__path_get_challenges_batch, get_challenges_batch};
+pub use validate_challenge::{__path_validate_challenges, validate_challenges, RequestBody};
+pub(crate) use validate_challenge::{check_solutions, validate, ResponseBody};
+pub use validate_challenges_batch::{__path_validate_challenges_batch, validate_challenges_batch};
+pub use health::{__path_health, health};
+pub use metrics::{__path_metrics, metrics};
@@ -1,69 +1,551 @@
-use axum::{extract::State, Extension, Json};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use axum::{extract::{rejection::QueryRejection, Query, State}, response::Response, Extension};
 use serde::{Deserialize, Serialize};
-use tracing::{info, warn};
+use tracing::{info, info_span, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{challenge::{Challenge, Prefix}, error_response::{ErrorId, ErrorResponse}, site::Site, solution::Solution, storage::Storage, webhook::WebhookEvent, wire::{Wire, WireFormat}};
 
-use crate::{challenge::Challenge, error_response::{ErrorId, ErrorResponse}, site::Site, solution::Solution, storage::Storage};
+/// Candidates a verification thread checks at least, smaller chunks cost
+/// more to spawn than to check
+const MIN_CHUNK: usize = 8;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RequestBody {
-    solutions: Vec<Option<Solution>>
+    /// One entry per prefix, `null` for prefixes that were not solved
+    pub(crate) solutions: Vec<Option<Solution>>
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ResponseBody{
-    valid: bool
+    pub(crate) valid: bool,
+    /// Only issued for valid solutions on sites with a `passToken` config
+    #[serde(rename = "passToken", skip_serializing_if = "Option::is_none")]
+    pub(crate) pass_token: Option<String>,
+    /// Only included when requested with `details=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) details: Option<ValidationDetails>,
+}
+
+impl ResponseBody {
+    /// Answer for solutions that could not be checked
+    pub(crate) fn invalid() -> Self {
+        Self { valid: false, pass_token: None, details: None }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationDetails {
+    /// Indices of the prefixes whose solution verified
+    #[serde(rename = "verifiedPrefixes")]
+    pub(crate) verified_prefixes: Vec<usize>,
+    #[serde(rename = "prefixesToSolve")]
+    pub(crate) prefixes_to_solve: usize,
+    pub(crate) difficulty: u8,
+    #[serde(rename = "siteId", serialize_with = "crate::wire::serialize_id")]
+    pub(crate) site_id: Uuid,
+    /// Action the challenge was requested for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) action: Option<String>,
+    /// Seconds since the unix epoch
+    #[serde(rename = "issuedAt")]
+    pub(crate) issued_at: u64,
+    /// Milliseconds between issuing the challenge and receiving the solutions
+    #[serde(rename = "solveTimeMs")]
+    pub(crate) solve_time_ms: u64,
+    /// Solved faster than the site's `fastSolveThreshold`
+    #[serde(rename = "suspiciouslyFast")]
+    pub(crate) suspiciously_fast: bool,
+    /// Origin the challenge was requested from. Browsers always send it, so
+    /// `null` is a weak bot signal
+    pub(crate) origin: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ValidateQuery {
+    /// Include [`ValidationDetails`], verifying every submitted solution
+    /// instead of stopping once the outcome is known
+    #[serde(default)]
+    pub(crate) details: bool,
 }
 
+/// Outcome of [`check_solutions`]
+pub(crate) struct SolutionCheck {
+    pub(crate) valid: bool,
+    pub(crate) verified_prefixes: Vec<usize>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/site/{siteId}/challenge/{challengeId}",
+    tag = "challenge",
+    params(
+        ("siteId" = Uuid, Path, description = "Id of the site"),
+        ("challengeId" = Uuid, Path, description = "Id of the challenge"),
+        ValidateQuery,
+    ),
+    request_body = RequestBody,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Solutions were checked", body = ResponseBody),
+        (status = 400, description = "Wrong number of solutions", body = ErrorResponse),
+        (status = 401, description = "Api-key missing, wrong or expired", body = ErrorResponse),
+        (status = 403, description = "Api-key lacks the required scope", body = ErrorResponse),
+        (status = 429, description = "Locked out after too many failed api-key checks", body = ErrorResponse),
+        (status = 404, description = "Site or challenge not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+        (status = 503, description = "Timeout", body = ErrorResponse),
+    )
+)]
 pub async fn validate_challenges(
     State(state): State<crate::State>,
     Extension(site): Extension<Site>,
     Extension(challenge): Extension<Challenge>,
-    Json(body): Json<RequestBody>
-) -> Result<String, ErrorResponse> {
+    query: Result<Query<ValidateQuery>, QueryRejection>,
+    format: WireFormat,
+    Wire(body): Wire<RequestBody>
+) -> Result<Response, ErrorResponse> {
+    let Query(query) = query?;
+
+    let response = validate(&state, &site, &challenge, body.solutions, query.details).await?;
+
+    Ok(format.render(&response))
+}
+
+/// Checks the solutions and deletes the challenge, shared with batch validation
+pub(crate) async fn validate(
+    state: &crate::State,
+    site: &Site,
+    challenge: &Challenge,
+    solutions: Vec<Option<Solution>>,
+    details: bool,
+) -> Result<ResponseBody, ErrorResponse> {
+    let solve_time = challenge.get_age();
+
+    let check = check_solutions(state, site, challenge, solutions, details).await?;
+    let valid = check.valid;
+
+    let suspiciously_fast = valid && is_suspiciously_fast(site, solve_time);
+
+    if suspiciously_fast {
+        warn!(
+            site_id = %site.get_id(),
+            challenge_id = %challenge.get_id(),
+            solve_time_ms = solve_time.as_millis() as u64,
+            "Suspiciously fast solve"
+        );
+    }
+
+    let pass_token = site
+        .get_pass_token()
+        .filter(|_| valid)
+        .map(|config| config.issue(site.get_id(), challenge.get_id()));
+
+    let details = details.then(|| ValidationDetails {
+        verified_prefixes: check.verified_prefixes,
+        prefixes_to_solve: challenge.get_prefixes_to_solve(),
+        difficulty: challenge.get_difficulty(),
+        site_id: *site.get_id(),
+        action: challenge.get_action().map(str::to_string),
+        issued_at: challenge.get_issued_at(),
+        solve_time_ms: solve_time.as_millis() as u64,
+        suspiciously_fast,
+        origin: challenge.get_origin().map(str::to_string),
+    });
+
+    Ok(ResponseBody { valid, pass_token, details })
+}
+
+fn is_suspiciously_fast(site: &Site, solve_time: Duration) -> bool {
+    site.get_fast_solve_threshold()
+        .is_some_and(|threshold| solve_time < *threshold)
+}
+
+pub(crate) async fn check_solutions(
+    state: &crate::State,
+    site: &Site,
+    challenge: &Challenge,
+    solutions: Vec<Option<Solution>>,
+    exhaustive: bool,
+) -> Result<SolutionCheck, ErrorResponse> {
 
     let expected_prefix_count = site.get_prefix_count();
-    let prefix_len = body.solutions.len();
+    let prefix_len = solutions.len();
 
     if prefix_len != expected_prefix_count {
-        return Err(ErrorResponse::new(ErrorId::WrongNumberOfSolutions, format!("Expected {expected_prefix_count} solutions, got {prefix_len}")));
+        return Err(ErrorResponse::new(ErrorId::WrongNumberOfSolutions, format!("Expected {expected_prefix_count} solutions, got {prefix_len}"))
+            .with_site(site.get_id())
+            .with_challenge(challenge.get_id()));
     }
 
-    let mut valid_challenges: usize = 0;
-    
-    let difficulty = site.get_difficulty();
+    let mut candidates = Vec::with_capacity(prefix_len);
 
-    let mut index = 0;
-    for solution in body.solutions {
+    for (index, solution) in solutions.into_iter().enumerate() {
         if let Some(solution) = solution {
             let prefix = match challenge.get_prefix(index) {
-                Some(v) => v,
-                None => return Err(ErrorResponse::new(ErrorId::InternalServerError, "Internal challange was missing a prefix")),
+                Some(v) => v.clone(),
+                None => return Err(ErrorResponse::new(ErrorId::InternalServerError, "Internal challange was missing a prefix")
+                    .with_site(site.get_id())
+                    .with_challenge(challenge.get_id())),
             };
 
-            let r = solution._validate(prefix, difficulty).await;
-
-            if r {
-                valid_challenges += 1;
-            }
+            candidates.push((index, prefix, solution));
         }
-
-        index += 1;
     }
 
-    let valid = valid_challenges >= site.get_prefixes_to_solve();
+    let difficulty = site.get_difficulty();
+    let required = site.get_prefixes_to_solve();
+    let threads = state.get_config().get_verification().get_threads_per_set();
+
+    let permit = state.get_verification_permits().clone().acquire_owned().await
+        .map_err(|_| ErrorResponse::new(ErrorId::InternalServerError, "Verification pool closed")
+            .with_site(site.get_id())
+            .with_challenge(challenge.get_id()))?;
+
+    let span = info_span!("validate_solutions", prefixes = prefix_len, difficulty);
 
-    match state.get_storage().await.delete_challenge(&site, &challenge).await {
+    let verified_prefixes = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+
+        span.in_scope(|| verify_candidates(&candidates, required, exhaustive, threads, |prefix, solution| solution.validate(prefix, difficulty)))
+    })
+    .await
+    .map_err(|_| ErrorResponse::new(ErrorId::InternalServerError, "Verification failed")
+        .with_site(site.get_id())
+        .with_challenge(challenge.get_id()))?;
+
+    match state.get_storage().await.delete_challenge(site, challenge).await {
         Ok(_) => (),
         Err(_) => {
             info!("Challenge expired or got deleted while we were checking solution");
-            return Err(ErrorResponse::new(ErrorId::ChallangeNotFound, "Challange not found"))
+            return Err(ErrorResponse::new(ErrorId::ChallangeNotFound, "Challange not found")
+                .with_site(site.get_id())
+                .with_challenge(challenge.get_id()))
         },
     };
 
-    serde_json::to_string( &ResponseBody{
-        valid
-    }).map_err(|e| {
-        warn!("Unable to generate response {}", e);
-        ErrorResponse::new(ErrorId::InternalServerError, "Unable to generate response")
+    let valid = verified_prefixes.len() >= required;
+
+    let event = match valid {
+        true => WebhookEvent::ChallengeSolved,
+        false => WebhookEvent::ChallengeFailed,
+    };
+    state.get_webhooks().emit(site, event, Some(challenge.get_id()));
+
+    Ok(SolutionCheck {
+        valid,
+        verified_prefixes,
+    })
+}
+
+/// Returns the indices of the verified solutions in ascending order. Unless
+/// `exhaustive`, stops as soon as `required` solutions are valid, or when the
+/// remaining ones can no longer reach it. The candidates are split into
+/// chunks of at least `MIN_CHUNK` checked on up to `threads` threads.
+fn verify_candidates<F>(candidates: &[(usize, Prefix, Solution)], required: usize, exhaustive: bool, threads: usize, validate: F) -> Vec<usize>
+where
+    F: Fn(&Prefix, &Solution) -> bool + Sync,
+{
+    let found = AtomicUsize::new(0);
+    let unchecked = AtomicUsize::new(candidates.len());
+
+    let verify_chunk = |chunk: &[(usize, Prefix, Solution)]| {
+        let mut verified = Vec::new();
+
+        for (index, prefix, solution) in chunk {
+            if !exhaustive {
+                let found = found.load(Ordering::Relaxed);
+
+                if found >= required || found + unchecked.load(Ordering::Relaxed) < required {
+                    break;
+                }
+            }
+
+            let valid = validate(prefix, solution);
+
+            // Counted as found before it stops being unchecked, so other
+            // chunks never see too few candidates left
+            if valid {
+                found.fetch_add(1, Ordering::Relaxed);
+                verified.push(*index);
+            }
+
+            unchecked.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        verified
+    };
+
+    let chunk_size = candidates.len().div_ceil(threads.max(1)).max(MIN_CHUNK);
+    let mut chunks = candidates.chunks(chunk_size);
+
+    let Some(first) = chunks.next() else {
+        return Vec::new();
+    };
+
+    std::thread::scope(|scope| {
+        let others: Vec<_> = chunks
+            .map(|chunk| scope.spawn(|| verify_chunk(chunk)))
+            .collect();
+
+        let mut verified = verify_chunk(first);
+
+        for other in others {
+            verified.extend(other.join().expect("Verification thread panicked"));
+        }
+
+        verified
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Mutex, thread, time::Duration};
+
+    use axum::{body::Body, http::Request, Router};
+    use bytes::Bytes;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use crate::{
+        challenge::Prefix,
+        solution::Solution,
+        test_support::{self, body_json, site, solve, solve_json, SITE_ID},
+    };
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::verify_candidates;
+
+    fn candidates(count: usize) -> Vec<(usize, Prefix, Solution)> {
+        (0..count)
+            .map(|i| (i, Prefix::new(Bytes::from(vec![i as u8])), Solution::new(Bytes::new())))
+            .collect()
+    }
+
+    fn index(prefix: &Prefix) -> u8 {
+        prefix.get_bytes()[0]
+    }
+
+    #[test]
+    fn test_stops_after_enough_valid() {
+        let checked = Mutex::new(Vec::new());
+
+        let verified = verify_candidates(&candidates(6), 2, false, 1, |prefix, _| {
+            checked.lock().unwrap().push(index(prefix));
+            true
+        });
+
+        assert_eq!(verified, vec![0, 1]);
+        assert_eq!(checked.into_inner().unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_stops_when_impossible() {
+        let checked = Mutex::new(Vec::new());
+
+        let verified = verify_candidates(&candidates(4), 3, false, 1, |prefix, _| {
+            checked.lock().unwrap().push(index(prefix));
+            false
+        });
+
+        assert!(verified.is_empty());
+        assert_eq!(checked.into_inner().unwrap(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_exhaustive_checks_all() {
+        let verified = verify_candidates(&candidates(4), 1, true, 1, |prefix, _| index(prefix) % 2 == 1);
+
+        assert_eq!(verified, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_fans_out() {
+        let threads = Mutex::new(HashSet::new());
+
+        let verified = verify_candidates(&candidates(32), 1, true, 4, |prefix, _| {
+            threads.lock().unwrap().insert(thread::current().id());
+            index(prefix) % 2 == 1
+        });
+
+        assert_eq!(verified, (1..32).step_by(2).collect::<Vec<_>>());
+        assert_eq!(threads.into_inner().unwrap().len(), 4);
+
+        // Chunks stop once the others found enough
+        let checked = AtomicUsize::new(0);
+
+        let verified = verify_candidates(&candidates(32), 2, false, 4, |_, _| {
+            checked.fetch_add(1, Ordering::Relaxed);
+            true
+        });
+
+        assert!(verified.len() >= 2);
+        assert!(checked.into_inner() < 32);
+    }
+
+    fn router() -> Router {
+        test_support::router(site().with_fast_solve_threshold(Duration::from_secs(60)))
+    }
+
+    #[tokio::test]
+    async fn test_detailed_response() {
+        let router = router();
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge?action=login"))
+            .body(Body::empty())
+            .unwrap();
+        let challenge = body_json(router.clone().oneshot(request).await.unwrap()).await;
+
+        assert_eq!(challenge["action"], "login");
+
+        let prefixes = challenge["prefixes"].as_array().unwrap();
+        let solutions = vec![
+            serde_json::Value::Null,
+            solve_json(&prefixes[1], 4),
+            serde_json::Value::Null,
+            solve_json(&prefixes[3], 4),
+        ];
+
+        let request = Request::post(format!(
+            "/v1/site/{SITE_ID}/challenge/{}?details=true",
+            challenge["id"].as_str().unwrap()
+        ))
+        .header("api-key", "key")
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::json!({ "solutions": solutions }).to_string()))
+        .unwrap();
+
+        let response = body_json(router.oneshot(request).await.unwrap()).await;
+        let details = &response["details"];
+
+        assert_eq!(response["valid"], true);
+        assert_eq!(details["verifiedPrefixes"], serde_json::json!([1, 3]));
+        assert_eq!(details["prefixesToSolve"], 2);
+        assert_eq!(details["difficulty"], 4);
+        assert_eq!(details["action"], "login");
+        assert_eq!(details["issuedAt"], challenge["issuedAt"]);
+        assert_eq!(details["suspiciouslyFast"], true);
+        assert_eq!(details["origin"], serde_json::Value::Null);
+    }
+
+    fn cbor_field<'a>(value: &'a ciborium::Value, name: &str) -> &'a ciborium::Value {
+        value
+            .as_map()
+            .unwrap()
+            .iter()
+            .find(|(key, _)| key.as_text() == Some(name))
+            .map(|(_, value)| value)
+            .unwrap_or_else(|| panic!("{name} missing"))
+    }
+
+    #[tokio::test]
+    async fn test_cbor() {
+        let router = router();
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .header("accept", "application/cbor")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()["content-type"], "application/cbor");
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let challenge: ciborium::Value = ciborium::from_reader(&body[..]).unwrap();
+
+        // Ids are strings in binary formats too
+        let id = Uuid::parse_str(cbor_field(&challenge, "id").as_text().unwrap()).unwrap();
+        let solutions: Vec<ciborium::Value> = cbor_field(&challenge, "prefixes")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|prefix| {
+                let prefix = Prefix::new(Bytes::from(prefix.as_bytes().unwrap().clone()));
+
+                ciborium::Value::Bytes(solve(&prefix, 4).get_bytes().to_vec())
+            })
+            .collect();
+
+        let mut request_body = Vec::new();
+        ciborium::into_writer(
+            &ciborium::Value::Map(vec![("solutions".into(), ciborium::Value::Array(solutions))]),
+            &mut request_body,
+        )
+        .unwrap();
+
+        let request = Request::post(format!("/v1/site/{SITE_ID}/challenge/{id}"))
+            .header("api-key", "key")
+            .header("accept", "application/cbor")
+            .header("content-type", "application/cbor")
+            .body(Body::from(request_body))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let response: ciborium::Value = ciborium::from_reader(&body[..]).unwrap();
+
+        assert_eq!(cbor_field(&response, "valid").as_bool(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_error() {
+        let request = Request::post(format!("/v1/site/{SITE_ID}/challenge/{}", Uuid::new_v4()))
+            .header("accept", "application/x-msgpack")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.headers()["content-type"], "application/msgpack");
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let error: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+
+        assert_eq!(error["id"], "ChallengeNotFound");
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_media_type() {
+        let router = router();
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let challenge = body_json(router.clone().oneshot(request).await.unwrap()).await;
+
+        let request = Request::post(format!(
+            "/v1/site/{SITE_ID}/challenge/{}",
+            challenge["id"].as_str().unwrap()
+        ))
+        .header("api-key", "key")
+        .header("content-type", "text/plain")
+        .body(Body::from("solutions"))
+        .unwrap();
+
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 415);
+        assert_eq!(body_json(response).await["id"], "UnsupportedMediaType");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_action() {
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge?action=%3Cb%3E"))
+            .body(Body::empty())
+            .unwrap();
+
+        let response = router().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 400);
+        assert_eq!(body_json(response).await["id"], "InvalidAction");
+    }
+}
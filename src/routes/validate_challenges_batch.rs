@@ -0,0 +1,216 @@
+use axum::{
+    extract::{rejection::QueryRejection, Query, State},
+    response::Response,
+    Extension,
+};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    error_response::{ErrorId, ErrorResponse},
+    middleware::find_challenge,
+    site::Site,
+    solution::Solution,
+    wire::{Wire, WireFormat},
+};
+
+use super::validate_challenge::{validate, ResponseBody, ValidateQuery};
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchValidationItem {
+    #[serde(rename = "challengeId", deserialize_with = "crate::wire::deserialize_id")]
+    challenge_id: Uuid,
+    /// One entry per prefix, `null` for prefixes that were not solved
+    solutions: Vec<Option<Solution>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchValidationRequest {
+    /// At most the site's `maxBatchSize` entries
+    challenges: Vec<BatchValidationItem>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchValidationResult {
+    #[serde(rename = "challengeId", serialize_with = "crate::wire::serialize_id")]
+    challenge_id: Uuid,
+    #[serde(flatten)]
+    response: ResponseBody,
+    /// Why the solutions could not be checked, `valid` is `false` then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorId>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchValidationResponse {
+    /// In the order of the request
+    results: Vec<BatchValidationResult>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/site/{siteId}/solutions:batch",
+    tag = "challenge",
+    params(("siteId" = Uuid, Path, description = "Id of the site"), ValidateQuery),
+    request_body = BatchValidationRequest,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Solutions were checked, see the results for errors of single challenges", body = BatchValidationResponse),
+        (status = 400, description = "Too many or no challenges", body = ErrorResponse),
+        (status = 401, description = "Api-key missing, wrong or expired", body = ErrorResponse),
+        (status = 403, description = "Api-key lacks the required scope", body = ErrorResponse),
+        (status = 429, description = "Locked out after too many failed api-key checks", body = ErrorResponse),
+        (status = 404, description = "Site not found", body = ErrorResponse),
+        (status = 503, description = "Timeout", body = ErrorResponse),
+    )
+)]
+pub async fn validate_challenges_batch(
+    State(state): State<crate::State>,
+    Extension(site): Extension<Site>,
+    query: Result<Query<ValidateQuery>, QueryRejection>,
+    format: WireFormat,
+    Wire(body): Wire<BatchValidationRequest>,
+) -> Result<Response, ErrorResponse> {
+    let Query(query) = query?;
+
+    let max_batch_size = site.get_max_batch_size();
+    let count = body.challenges.len();
+
+    if count == 0 || count > max_batch_size {
+        return Err(ErrorResponse::new(
+            ErrorId::InvalidBatchSize,
+            format!("Expected 1 to {max_batch_size} challenges, got {count}"),
+        )
+        .with_site(site.get_id()));
+    }
+
+    let (state, site) = (&state, &site);
+
+    let results = join_all(body.challenges.into_iter().map(|item| async move {
+        let challenge_id = item.challenge_id;
+
+        let outcome = match find_challenge(state, site, &challenge_id).await {
+            Ok(challenge) => validate(state, site, &challenge, item.solutions, query.details).await,
+            Err(error) => Err(error),
+        };
+
+        match outcome {
+            Ok(response) => BatchValidationResult {
+                challenge_id,
+                response,
+                error: None,
+            },
+            Err(error) => BatchValidationResult {
+                challenge_id,
+                response: ResponseBody::invalid(),
+                error: Some(error.get_id()),
+            },
+        }
+    }))
+    .await;
+
+    Ok(format.render(&BatchValidationResponse { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, Router};
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use crate::test_support::{self, body_json, site, solve_json, DIFFICULTY, SITE_ID};
+
+    fn router() -> Router {
+        test_support::router(site().with_max_batch_size(3).expect("Valid batch size"))
+    }
+
+    fn post(path: &str, body: Value) -> Request<Body> {
+        Request::post(path)
+            .header("api-key", "key")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn solve(challenge: &Value) -> Vec<Value> {
+        challenge["prefixes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|prefix| solve_json(prefix, DIFFICULTY))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let router = router();
+
+        let request = post(
+            &format!("/v1/site/{SITE_ID}/challenges:batch"),
+            json!({ "count": 2, "action": "offline-form" }),
+        );
+        let response = body_json(router.clone().oneshot(request).await.unwrap()).await;
+        let challenges = response["challenges"].as_array().unwrap();
+
+        assert_eq!(challenges.len(), 2);
+        assert_eq!(challenges[0]["action"], "offline-form");
+
+        let body = json!({ "challenges": [
+            { "challengeId": challenges[0]["id"], "solutions": solve(&challenges[0]) },
+            { "challengeId": challenges[1]["id"], "solutions": [null, null, null, null] },
+            { "challengeId": challenges[0]["id"], "solutions": solve(&challenges[0]) },
+        ] });
+
+        let request = post(&format!("/v1/site/{SITE_ID}/solutions:batch"), body);
+        let response = body_json(router.oneshot(request).await.unwrap()).await;
+        let results = response["results"].as_array().unwrap();
+
+        assert_eq!(results[0]["challengeId"], challenges[0]["id"]);
+        assert_eq!(results[1]["valid"], false);
+        assert!(results[1].get("error").is_none());
+
+        // Single use: only one of the two submissions of the first challenge is checked
+        let valid: Vec<&Value> = [&results[0], &results[2]]
+            .into_iter()
+            .filter(|result| result["valid"] == true)
+            .collect();
+        assert_eq!(valid.len(), 1);
+        assert!([&results[0], &results[2]]
+            .iter()
+            .any(|result| result["error"] == "ChallengeNotFound"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_size() {
+        let router = router();
+
+        let request = post(&format!("/v1/site/{SITE_ID}/challenges:batch"), json!({ "count": 4 }));
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 400);
+        assert_eq!(body_json(response).await["id"], "InvalidBatchSize");
+
+        let request = post(&format!("/v1/site/{SITE_ID}/solutions:batch"), json!({ "challenges": [] }));
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 400);
+
+        let request = post(&format!("/v1/site/{SITE_ID}/challengesfoo"), json!({ "count": 1 }));
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+        assert_eq!(body_json(response).await["id"], "RouteNotFound");
+
+        // Checked before the api-key and the body
+        let request = Request::post(format!("/v1/site/{SITE_ID}/solutionsfoo"))
+            .header("content-type", "application/json")
+            .body(Body::from("not json"))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+        assert_eq!(body_json(response).await["id"], "RouteNotFound");
+    }
+}
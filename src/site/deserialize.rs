@@ -6,6 +6,12 @@ use serde::{
     Deserialize, Deserializer,
 };
 
+use crate::{
+    auth::{ApiKey, ApiKeyEntry, RequestSigningConfig, DEFAULT_KEY_ID},
+    pool::ChallengePoolConfig,
+    webhook::WebhookConfig,
+};
+
 use super::Site;
 
 impl<'de> Deserialize<'de> for Site {
@@ -16,12 +22,21 @@ impl<'de> Deserialize<'de> for Site {
         enum Field {
             Id,
             ApiKey,
+            ApiKeyHash,
+            ApiKeys,
             Prefixes,
             PrefixesToSolve,
             PrefixLength,
             Difficulty,
             SolutionLength,
             Lifetime,
+            PassToken,
+            FastSolveThreshold,
+            Webhooks,
+            RequestSigning,
+            AllowedOrigins,
+            ChallengePool,
+            MaxBatchSize,
         }
 
         impl<'de> Deserialize<'de> for Field {
@@ -35,7 +50,7 @@ impl<'de> Deserialize<'de> for Site {
                     type Value = Field;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("`id`, `apiKey`, `prefixLength`, `prefixes`, `prefixesToSolve`, `difficulty`, `solutionLength or `liftime`")
+                        formatter.write_str("`id`, `apiKey`, `apiKeyHash`, `apiKeys`, `prefixLength`, `prefixes`, `prefixesToSolve`, `difficulty`, `solutionLength`, `lifetime`, `passToken`, `fastSolveThreshold`, `webhooks`, `requestSigning`, `allowedOrigins`, `challengePool` or `maxBatchSize`")
                     }
 
                     fn visit_str<E>(self, value: &str) -> Result<Field, E>
@@ -45,12 +60,21 @@ impl<'de> Deserialize<'de> for Site {
                         match value {
                             "id" => Ok(Field::Id),
                             "apiKey" => Ok(Field::ApiKey),
+                            "apiKeyHash" => Ok(Field::ApiKeyHash),
+                            "apiKeys" => Ok(Field::ApiKeys),
                             "difficulty" => Ok(Field::Difficulty),
                             "prefixes" => Ok(Field::Prefixes),
                             "prefixesToSolve" => Ok(Field::PrefixesToSolve),
                             "prefixLength" => Ok(Field::PrefixLength),
                             "solutionLength" => Ok(Field::SolutionLength),
                             "lifetime" => Ok(Field::Lifetime),
+                            "passToken" => Ok(Field::PassToken),
+                            "fastSolveThreshold" => Ok(Field::FastSolveThreshold),
+                            "webhooks" => Ok(Field::Webhooks),
+                            "requestSigning" => Ok(Field::RequestSigning),
+                            "allowedOrigins" => Ok(Field::AllowedOrigins),
+                            "challengePool" => Ok(Field::ChallengePool),
+                            "maxBatchSize" => Ok(Field::MaxBatchSize),
                             _ => Err(de::Error::unknown_field(value, FIELDS)),
                         }
                     }
@@ -74,13 +98,22 @@ impl<'de> Deserialize<'de> for Site {
                 V: MapAccess<'de>,
             {
                 let mut id = None;
-                let mut api_key = None;
+                let mut api_key: Option<String> = None;
+                let mut api_key_hash: Option<ApiKey> = None;
+                let mut api_keys: Option<Vec<ApiKeyEntry>> = None;
                 let mut difficulty = None;
                 let mut prefixes = None;
                 let mut prefixes_to_solve = None;
                 let mut prefix_length = None;
                 let mut solution_length = None;
                 let mut lifetime = None;
+                let mut pass_token = None;
+                let mut fast_solve_threshold: Option<AbsoluteDuration> = None;
+                let mut webhooks: Option<Vec<WebhookConfig>> = None;
+                let mut request_signing: Option<RequestSigningConfig> = None;
+                let mut allowed_origins: Option<Vec<String>> = None;
+                let mut challenge_pool: Option<ChallengePoolConfig> = None;
+                let mut max_batch_size: Option<usize> = None;
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Id => {
@@ -95,6 +128,18 @@ impl<'de> Deserialize<'de> for Site {
                             }
                             api_key = Some(map.next_value()?);
                         }
+                        Field::ApiKeyHash => {
+                            if api_key_hash.is_some() {
+                                return Err(de::Error::duplicate_field("apiKeyHash"));
+                            }
+                            api_key_hash = Some(map.next_value()?);
+                        }
+                        Field::ApiKeys => {
+                            if api_keys.is_some() {
+                                return Err(de::Error::duplicate_field("apiKeys"));
+                            }
+                            api_keys = Some(map.next_value()?);
+                        }
                         Field::Difficulty => {
                             if difficulty.is_some() {
                                 return Err(de::Error::duplicate_field("difficulty"));
@@ -133,10 +178,77 @@ impl<'de> Deserialize<'de> for Site {
                             }
                             lifetime = Some(map.next_value()?);
                         }
+                        Field::PassToken => {
+                            if pass_token.is_some() {
+                                return Err(de::Error::duplicate_field("passToken"));
+                            }
+                            pass_token = Some(map.next_value()?);
+                        }
+                        Field::FastSolveThreshold => {
+                            if fast_solve_threshold.is_some() {
+                                return Err(de::Error::duplicate_field("fastSolveThreshold"));
+                            }
+                            fast_solve_threshold = Some(map.next_value()?);
+                        }
+                        Field::Webhooks => {
+                            if webhooks.is_some() {
+                                return Err(de::Error::duplicate_field("webhooks"));
+                            }
+                            webhooks = Some(map.next_value()?);
+                        }
+                        Field::RequestSigning => {
+                            if request_signing.is_some() {
+                                return Err(de::Error::duplicate_field("requestSigning"));
+                            }
+                            request_signing = Some(map.next_value()?);
+                        }
+                        Field::AllowedOrigins => {
+                            if allowed_origins.is_some() {
+                                return Err(de::Error::duplicate_field("allowedOrigins"));
+                            }
+                            allowed_origins = Some(map.next_value()?);
+                        }
+                        Field::ChallengePool => {
+                            if challenge_pool.is_some() {
+                                return Err(de::Error::duplicate_field("challengePool"));
+                            }
+                            challenge_pool = Some(map.next_value()?);
+                        }
+                        Field::MaxBatchSize => {
+                            if max_batch_size.is_some() {
+                                return Err(de::Error::duplicate_field("maxBatchSize"));
+                            }
+                            max_batch_size = Some(map.next_value()?);
+                        }
                     }
                 }
                 let id = id.ok_or_else(|| de::Error::missing_field("id"))?;
-                let api_key = api_key.ok_or_else(|| de::Error::missing_field("apiKey"))?;
+                let default_key = match (api_key, api_key_hash) {
+                    (Some(api_key), None) => Some(ApiKey::from_plaintext(&api_key)),
+                    (None, Some(api_key_hash)) => Some(api_key_hash),
+                    (Some(_), Some(_)) => {
+                        return Err(de::Error::custom(
+                            "only one of `apiKey` and `apiKeyHash` may be set",
+                        ))
+                    }
+                    (None, None) => None,
+                };
+                let api_keys: Vec<ApiKeyEntry> = default_key
+                    .map(|key| ApiKeyEntry::new(DEFAULT_KEY_ID, key))
+                    .into_iter()
+                    .chain(api_keys.into_iter().flatten())
+                    .collect();
+                if api_keys.is_empty() {
+                    return Err(de::Error::missing_field("apiKeys"));
+                }
+                for (i, key) in api_keys.iter().enumerate() {
+                    if api_keys[..i].iter().any(|other| other.get_id() == key.get_id()) {
+                        return Err(de::Error::custom(format!(
+                            "duplicate api-key id `{}`",
+                            key.get_id()
+                        )));
+                    }
+                }
                 let difficulty =
                     difficulty.ok_or_else(|| de::Error::missing_field("difficulty"))?;
                 let prefixes = prefixes.ok_or_else(|| de::Error::missing_field("prefixes"))?;
@@ -149,27 +261,79 @@ impl<'de> Deserialize<'de> for Site {
                 let lifetime: AbsoluteDuration =
                     lifetime.ok_or_else(|| de::Error::missing_field("lifetime"))?;
 
-                Ok(Site::new(
+                let site = Site::new(
                     id,
-                    api_key,
+                    String::new(),
                     prefixes,
                     prefix_length,
                     prefixes_to_solve,
                     difficulty,
                     solution_length,
                     lifetime.into(),
-                ))
+                );
+
+                let site = site.with_api_keys(api_keys);
+
+                let site = match pass_token {
+                    Some(pass_token) => site.with_pass_token(pass_token),
+                    None => site,
+                };
+
+                let site = match fast_solve_threshold {
+                    Some(threshold) => site.with_fast_solve_threshold(threshold.into()),
+                    None => site,
+                };
+
+                let site = webhooks
+                    .into_iter()
+                    .flatten()
+                    .fold(site, |site, webhook| site.with_webhook(webhook));
+
+                let site = match request_signing {
+                    Some(request_signing) => site.with_request_signing(request_signing),
+                    None => site,
+                };
+
+                let site = match allowed_origins {
+                    Some(allowed_origins) => site
+                        .with_allowed_origins(allowed_origins)
+                        .map_err(de::Error::custom)?,
+                    None => site,
+                };
+
+                let site = match challenge_pool {
+                    Some(challenge_pool) => site.with_challenge_pool(challenge_pool),
+                    None => site,
+                };
+
+                let site = match max_batch_size {
+                    Some(max_batch_size) => site
+                        .with_max_batch_size(max_batch_size)
+                        .map_err(de::Error::custom)?,
+                    None => site,
+                };
+
+                Ok(site)
             }
         }
 
         const FIELDS: &[&str] = &[
             "`id`",
             "`apiKey`",
+            "`apiKeyHash`",
+            "`apiKeys`",
             "`prefixes`",
             "`prefixes_to_solve`",
             "`difficulty`",
             "`solutionLength`",
             "`lifetime`",
+            "`passToken`",
+            "`fastSolveThreshold`",
+            "`webhooks`",
+            "`requestSigning`",
+            "`allowedOrigins`",
+            "`challengePool`",
+            "`maxBatchSize`",
         ];
         deserializer.deserialize_struct("Duration", FIELDS, SiteVisitor)
     }
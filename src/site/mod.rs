@@ -1,26 +1,45 @@
-use std::{io::Write, time::Duration};
+use std::time::Duration;
 
-use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::challenge::Challenge;
+use crate::{
+    auth::{ApiKey, ApiKeyEntry, RequestSigningConfig, DEFAULT_KEY_ID},
+    challenge::Challenge, pass_token::PassTokenConfig, pool::ChallengePoolConfig,
+    webhook::WebhookConfig,
+};
+
+/// Challenges issued or validated per batch request unless configured
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 10;
+
+/// Upper bound for `maxBatchSize`, a batch is generated and validated in one request
+pub const MAX_BATCH_SIZE: usize = 1000;
 
 mod deserialize;
+mod origin;
+
+pub(crate) use origin::{normalize_origin, origin_of_referer};
 
 #[derive(Debug, Clone)]
 pub struct Site {
     id: Uuid,
-    api_key: String,
-    api_key_hash: Vec<u8>,
+    api_keys: Vec<ApiKeyEntry>,
     prefixes: usize,
     prefix_length: usize,
     prefixes_to_solve: usize,
     difficulty: u8,
     solution_length: usize,
     lifetime: Duration,
+    pass_token: Option<PassTokenConfig>,
+    fast_solve_threshold: Option<Duration>,
+    webhooks: Vec<WebhookConfig>,
+    request_signing: Option<RequestSigningConfig>,
+    allowed_origins: Option<Vec<String>>,
+    challenge_pool: Option<ChallengePoolConfig>,
+    max_batch_size: usize,
 }
 
 impl Site {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: Uuid,
         api_key: String,
@@ -31,24 +50,99 @@ impl Site {
         solution_length: usize,
         lifetime: Duration,
     ) -> Self {
-        let mut hasher = Sha256::new();
-        hasher
-            .write_all(api_key.as_bytes())
-            .expect("Unable to hash api_key");
-        let api_key_hash = hasher.finalize();
-        let api_key_hash = api_key_hash.to_vec();
-
         Self {
             id,
-            api_key,
-            api_key_hash,
+            api_keys: vec![ApiKeyEntry::new(
+                DEFAULT_KEY_ID,
+                ApiKey::from_plaintext(&api_key),
+            )],
             prefixes,
             prefix_length,
             prefixes_to_solve,
             difficulty,
             solution_length,
             lifetime,
+            pass_token: None,
+            fast_solve_threshold: None,
+            webhooks: Vec::new(),
+            request_signing: None,
+            allowed_origins: None,
+            challenge_pool: None,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+        }
+    }
+
+    /// Replaces the api-key given to `new`, e.g. with an argon2 hash
+    pub fn with_api_key(mut self, api_key: ApiKey) -> Self {
+        self.api_keys = vec![ApiKeyEntry::new(DEFAULT_KEY_ID, api_key)];
+        self
+    }
+
+    /// Replaces all api-keys of the site
+    pub fn with_api_keys(mut self, api_keys: Vec<ApiKeyEntry>) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    pub fn with_pass_token(mut self, pass_token: PassTokenConfig) -> Self {
+        self.pass_token = Some(pass_token);
+        self
+    }
+
+    /// Valid solutions submitted faster than `threshold` after the challenge
+    /// was issued get flagged
+    pub fn with_fast_solve_threshold(mut self, threshold: Duration) -> Self {
+        self.fast_solve_threshold = Some(threshold);
+        self
+    }
+
+    pub fn with_webhook(mut self, webhook: WebhookConfig) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    /// Backend requests have to be signed in addition to carrying an api-key
+    pub fn with_request_signing(mut self, request_signing: RequestSigningConfig) -> Self {
+        self.request_signing = Some(request_signing);
+        self
+    }
+
+    /// Restricts browsers requesting and solving challenges to these
+    /// origins, `*` allows any. Fails on entries that are not an origin.
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> anyhow::Result<Self> {
+        let origins = origins
+            .iter()
+            .map(|origin| match origin.as_str() {
+                "*" => Ok("*".to_string()),
+                origin => normalize_origin(origin).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "`{origin}` in allowedOrigins is not an origin like `https://example.com`"
+                    )
+                }),
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        self.allowed_origins = Some(origins);
+        Ok(self)
+    }
+
+    /// Keeps challenges pre-generated for hand-out under burst traffic
+    pub fn with_challenge_pool(mut self, challenge_pool: ChallengePoolConfig) -> Self {
+        self.challenge_pool = Some(challenge_pool);
+        self
+    }
+
+    /// Caps the challenges issued or validated by one batch request, between 1
+    /// and [`MAX_BATCH_SIZE`]
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> anyhow::Result<Self> {
+        if !(1..=MAX_BATCH_SIZE).contains(&max_batch_size) {
+            anyhow::bail!(
+                "maxBatchSize must be between 1 and {MAX_BATCH_SIZE}, got {max_batch_size}"
+            );
         }
+
+        self.max_batch_size = max_batch_size;
+        Ok(self)
     }
 
     pub fn get_id(&self) -> &Uuid {
@@ -79,12 +173,48 @@ impl Site {
         &self.lifetime
     }
 
-    pub fn get_api_key(&self) -> &str {
-        &self.api_key
+    pub fn get_api_keys(&self) -> &[ApiKeyEntry] {
+        &self.api_keys
     }
 
-    pub fn get_api_key_hash(&self) -> &Vec<u8> {
-        &self.api_key_hash
+    pub fn get_pass_token(&self) -> Option<&PassTokenConfig> {
+        self.pass_token.as_ref()
+    }
+
+    pub fn get_fast_solve_threshold(&self) -> Option<&Duration> {
+        self.fast_solve_threshold.as_ref()
+    }
+
+    pub fn get_webhooks(&self) -> &[WebhookConfig] {
+        &self.webhooks
+    }
+
+    pub fn get_request_signing(&self) -> Option<&RequestSigningConfig> {
+        self.request_signing.as_ref()
+    }
+
+    /// `None` if the site does not restrict origins
+    pub fn get_allowed_origins(&self) -> Option<&[String]> {
+        self.allowed_origins.as_deref()
+    }
+
+    pub fn get_challenge_pool(&self) -> Option<&ChallengePoolConfig> {
+        self.challenge_pool.as_ref()
+    }
+
+    pub fn get_max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    /// True if the site does not restrict origins or lists `origin`
+    pub fn is_origin_allowed(&self, origin: &str) -> bool {
+        let Some(allowed) = &self.allowed_origins else {
+            return true;
+        };
+
+        let origin = origin.to_ascii_lowercase();
+
+        allowed.iter().any(|allowed| allowed == "*" || *allowed == origin)
     }
 }
 
@@ -101,6 +231,8 @@ mod tests {
     use hex_literal::hex;
     use uuid::uuid;
 
+    use crate::auth::ApiKey;
+
     use super::Site;
 
     #[test]
@@ -123,15 +255,282 @@ mod tests {
         let test = serde_json::from_str::<Site>(test_string).expect("Failed parsing json");
 
         assert_eq!(test.id, uuid!("60601796-7dc2-4d4f-afae-5728592bba6f"));
-        assert_eq!(test.api_key, "cool");
-        assert_eq!(
-            test.api_key_hash,
-            hex!("c34045c1a1db8d1b3fca8a692198466952daae07eaf6104b4c87ed3b55b6af1b")
-        );
+        assert!(matches!(
+            test.api_keys[0].get_key(),
+            ApiKey::Sha256(digest)
+                if *digest == hex!("c34045c1a1db8d1b3fca8a692198466952daae07eaf6104b4c87ed3b55b6af1b")
+        ));
+        assert!(test.api_keys[0].get_key().verify(b"cool"));
         assert_eq!(test.prefixes, 12);
         assert_eq!(test.prefix_length, 33);
         assert_eq!(test.prefixes_to_solve, 8);
         assert_eq!(test.solution_length, 21);
         assert_eq!(test.lifetime, Duration::from_secs(120));
+        assert!(test.pass_token.is_none());
+    }
+
+    #[test]
+    fn test_deserialize_pass_token() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKey": "cool",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                },
+                "passToken": {
+                    "secret": "0123456789abcdef0123456789abcdef",
+                    "lifetime": {
+                        "minutes": 10
+                    }
+                }
+            }
+        "#;
+
+        let test = serde_json::from_str::<Site>(test_string).expect("Failed parsing json");
+
+        let pass_token = test.pass_token.expect("Pass token config missing");
+        assert_eq!(pass_token.get_lifetime(), &Duration::from_secs(600));
+        assert!(test.request_signing.is_none());
+    }
+
+    #[test]
+    fn test_deserialize_api_key_hash() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKeyHash": "$argon2id$v=19$m=8,t=1,p=1$c2FsdHNhbHRzYWx0$qOAVMUBAC45Z7ZapMJ0aNZPCsCi/JIzIN6NxaJUgKHk",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                }
+            }
+        "#;
+
+        let test = serde_json::from_str::<Site>(test_string).expect("Failed parsing json");
+
+        assert!(matches!(test.api_keys[0].get_key(), ApiKey::Argon2(_)));
+        assert!(test.api_keys[0].get_key().verify(b"cool"));
+        assert!(!test.api_keys[0].get_key().verify(b"wrong"));
+    }
+
+    #[test]
+    fn test_deserialize_api_key_exclusive() {
+        let both = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKey": "cool",
+                "apiKeyHash": "$argon2id$v=19$m=8,t=1,p=1$c2FsdHNhbHRzYWx0$qOAVMUBAC45Z7ZapMJ0aNZPCsCi/JIzIN6NxaJUgKHk",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                }
+            }
+        "#;
+        let neither = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                }
+            }
+        "#;
+
+        assert!(serde_json::from_str::<Site>(both).is_err());
+        assert!(serde_json::from_str::<Site>(neither).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_api_keys() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKey": "cool",
+                "apiKeys": [
+                    { "id": "next", "key": "cooler", "notBefore": 1700000000, "scopes": ["validate"] }
+                ],
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                }
+            }
+        "#;
+
+        let test = serde_json::from_str::<Site>(test_string).expect("Failed parsing json");
+
+        let ids: Vec<_> = test.api_keys.iter().map(|key| key.get_id()).collect();
+        assert_eq!(ids, vec!["default", "next"]);
+        assert_eq!(test.api_keys[1].get_not_before(), Some(1700000000));
+    }
+
+    #[test]
+    fn test_deserialize_duplicate_key_id() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKeys": [
+                    { "id": "ci", "key": "cool" },
+                    { "id": "ci", "key": "cooler" }
+                ],
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                }
+            }
+        "#;
+
+        assert!(serde_json::from_str::<Site>(test_string).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_request_signing() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKey": "cool",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                },
+                "requestSigning": {
+                    "secret": "0123456789abcdef0123456789abcdef",
+                    "maxSkew": {
+                        "seconds": 30
+                    }
+                }
+            }
+        "#;
+
+        let test = serde_json::from_str::<Site>(test_string).expect("Failed parsing json");
+
+        let signing = test.request_signing.expect("Request signing config missing");
+        assert_eq!(signing.get_max_skew(), &Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_deserialize_allowed_origins() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKey": "cool",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                },
+                "allowedOrigins": ["https://Example.com/", "http://localhost:8080"]
+            }
+        "#;
+
+        let test = serde_json::from_str::<Site>(test_string).expect("Failed parsing json");
+
+        assert_eq!(
+            test.get_allowed_origins(),
+            Some(&["https://example.com".to_string(), "http://localhost:8080".to_string()][..])
+        );
+        assert!(test.is_origin_allowed("https://EXAMPLE.com"));
+        assert!(!test.is_origin_allowed("https://evil.com"));
+    }
+
+    #[test]
+    fn test_with_invalid_origin() {
+        let site = Site::new(
+            uuid!("60601796-7dc2-4d4f-afae-5728592bba6f"),
+            "cool".to_string(),
+            12,
+            33,
+            8,
+            17,
+            21,
+            Duration::from_secs(120),
+        );
+
+        assert!(site
+            .clone()
+            .with_allowed_origins(vec!["*".to_string(), "https://example.com".to_string()])
+            .is_ok());
+        assert!(site
+            .with_allowed_origins(vec!["https://example.com/login".to_string()])
+            .is_err());
+    }
+
+    #[test]
+    fn test_max_batch_size_bounds() {
+        for max_batch_size in [0, super::MAX_BATCH_SIZE + 1] {
+            let test_string = format!(
+                r#"
+                {{
+                    "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                    "apiKey": "cool",
+                    "difficulty": 17,
+                    "prefixes": 12,
+                    "prefixLength": 33,
+                    "prefixesToSolve": 8,
+                    "solutionLength": 21,
+                    "lifetime": {{
+                        "minutes": 2
+                    }},
+                    "maxBatchSize": {max_batch_size}
+                }}
+            "#
+            );
+
+            let error = serde_json::from_str::<Site>(&test_string).unwrap_err();
+            assert!(error.to_string().contains("maxBatchSize"), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_deserialize_invalid_origin() {
+        let test_string = r#"
+            {
+                "id": "60601796-7dc2-4d4f-afae-5728592bba6f",
+                "apiKey": "cool",
+                "difficulty": 17,
+                "prefixes": 12,
+                "prefixLength": 33,
+                "prefixesToSolve": 8,
+                "solutionLength": 21,
+                "lifetime": {
+                    "minutes": 2
+                },
+                "allowedOrigins": ["https://example.com/login"]
+            }
+        "#;
+
+        assert!(serde_json::from_str::<Site>(test_string).is_err());
     }
 }
@@ -0,0 +1,55 @@
+use axum::http::Uri;
+
+/// `scheme://host[:port]` in lowercase, or `None` if `value` is not an
+/// origin. A trailing `/` is accepted.
+pub(crate) fn normalize_origin(value: &str) -> Option<String> {
+    let uri: Uri = value.trim_end_matches('/').parse().ok()?;
+
+    if uri.path_and_query().is_some_and(|path| path.as_str() != "/") {
+        return None;
+    }
+
+    origin_of(&uri)
+}
+
+/// Origin of a `Referer`, dropping its path and query
+pub(crate) fn origin_of_referer(referer: &str) -> Option<String> {
+    origin_of(&referer.parse().ok()?)
+}
+
+fn origin_of(uri: &Uri) -> Option<String> {
+    let scheme = uri.scheme_str()?;
+    let authority = uri.authority()?;
+
+    if authority.as_str().contains('@') {
+        return None;
+    }
+
+    Some(format!("{scheme}://{authority}").to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_origin, origin_of_referer};
+
+    #[test]
+    fn test_normalize_origin() {
+        assert_eq!(normalize_origin("https://Example.com").as_deref(), Some("https://example.com"));
+        assert_eq!(
+            normalize_origin("http://localhost:8080/").as_deref(),
+            Some("http://localhost:8080")
+        );
+        assert_eq!(normalize_origin("https://example.com/login"), None);
+        assert_eq!(normalize_origin("example.com"), None);
+        assert_eq!(normalize_origin("null"), None);
+    }
+
+    #[test]
+    fn test_origin_of_referer() {
+        assert_eq!(
+            origin_of_referer("https://example.com/login?next=/").as_deref(),
+            Some("https://example.com")
+        );
+        assert_eq!(origin_of_referer("/relative"), None);
+    }
+}
@@ -1,7 +1,10 @@
 use base64::{prelude::BASE64_STANDARD, Engine};
 use bytes::Bytes;
 use serde::{de, Deserialize, Serialize};
-use sha2::{Digest, Sha256};
+use utoipa::{
+    openapi::{schema::SchemaType, KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type},
+    PartialSchema, ToSchema,
+};
 
 use crate::challenge::Prefix;
 
@@ -9,27 +12,17 @@ use crate::challenge::Prefix;
 pub struct Solution (Bytes);
 
 impl Solution {
-    pub async fn _validate(&self, prefix: &Prefix, difficulty: u8) -> bool{
-        let mut hasher = Sha256::new();
-        hasher.update(prefix._get_bytes());
-        hasher.update(&self.0);
-        let bytes = hasher.finalize();
-
-        let bytes_to_scan = (difficulty / 8) as usize;
-        let bits_to_scan =( difficulty % 8) as usize;
-
-        for i in 0..bytes_to_scan {
-            if bytes[i] != 0 {
-                return false;
-            }
-        };
+    pub fn new(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
 
-        let last_byte = bytes[bytes_to_scan];
-        
-        let mask = !(0xFF >> bits_to_scan);
-        let last_byte_masked = last_byte & mask;
+    pub fn get_bytes(&self) -> &Bytes {
+        &self.0
+    }
 
-        last_byte_masked == 0
+    /// Cpu bound, run it outside of the async executor
+    pub fn validate(&self, prefix: &Prefix, difficulty: u8) -> bool{
+        oxidecaptcha_core::is_valid(prefix.get_bytes(), &self.0, difficulty)
     }
 }
 
@@ -37,6 +30,10 @@ impl Serialize for Solution {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(&self.0);
+        }
+
         let bytes_b64 = BASE64_STANDARD.encode(&self.0);
 
         serializer.serialize_str(&bytes_b64)
@@ -47,6 +44,10 @@ impl<'de> Deserialize<'de> for Solution {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de> {
+            if !deserializer.is_human_readable() {
+                return deserializer.deserialize_bytes(SolutionVisitor);
+            }
+
             let value = String::deserialize(deserializer)?;
 
             let base_data = BASE64_STANDARD.decode(value)
@@ -58,10 +59,56 @@ impl<'de> Deserialize<'de> for Solution {
     }
 }
 
+/// Raw byte strings from binary formats, base64 strings are accepted too
+struct SolutionVisitor;
+
+impl de::Visitor<'_> for SolutionVisitor {
+    type Value = Solution;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a base64 encoded string or a byte string")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Solution, E>
+    where
+        E: de::Error,
+    {
+        let base_data = BASE64_STANDARD.decode(value)
+            .map_err(|_| de::Error::custom("could not base64 decode solution"))?;
+
+        Ok(Solution(Bytes::from(base_data)))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Solution, E>
+    where
+        E: de::Error,
+    {
+        Ok(Solution(Bytes::copy_from_slice(value)))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Solution, E>
+    where
+        E: de::Error,
+    {
+        Ok(Solution(Bytes::from(value)))
+    }
+}
+
+impl PartialSchema for Solution {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::new(Type::String))
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Byte)))
+            .description(Some("Base64 encoded solution, a byte string in CBOR and MessagePack"))
+            .into()
+    }
+}
+
+impl ToSchema for Solution {}
+
 #[cfg(test)]
 mod tests {
     use bytes::{BufMut, Bytes, BytesMut};
-    use futures::executor::block_on;
     use hex_literal::hex;
     use rand::{rngs::OsRng, Rng};
     use serde::Deserialize;
@@ -74,7 +121,7 @@ mod tests {
     #[ignore]
     fn find_fitting_test() {
         let prefix = bytes::Bytes::from_static(&hex!("12bedfcafb0491a1998f94f4648c494fc384ceec"));
-        let prefix = Prefix::_new(prefix);
+        let prefix = Prefix::new(prefix);
 
         let mut rng = OsRng;
 
@@ -92,7 +139,7 @@ mod tests {
 
             let solution = Solution(solution.into());
             
-            if block_on(solution._validate(&prefix, difficulty)) {
+            if solution.validate(&prefix, difficulty) {
                 _found_solution = Some(solution);
                 break;
             }
@@ -106,27 +153,27 @@ mod tests {
     #[test]
     fn test_valid() {
         let prefix =  bytes::Bytes::from_static(&hex!("12bedfcafb0491a1998f94f4648c494fc384ceec"));
-        let prefix = Prefix::_new(prefix);
+        let prefix = Prefix::new(prefix);
 
         let difficulty = 13;
 
         let solution = bytes::Bytes::from_static(&hex!("d85ae00d155c6ca8edb4838a"));
         let solution = Solution ( solution );
 
-        assert!(block_on(solution._validate(&prefix, difficulty)));
+        assert!(solution.validate(&prefix, difficulty));
     }
 
     #[test]
     fn test_invalid() {
         let prefix =  bytes::Bytes::from_static(&hex!("12bedfcafb0491a1998f94f4648c494fc384ceec"));
-        let prefix = Prefix::_new(prefix);
+        let prefix = Prefix::new(prefix);
 
         let difficulty = 13;
 
         let solution = bytes::Bytes::from_static(&hex!("d85ae00e155c6ca8edb4838a"));
         let solution = Solution ( solution );
 
-        assert!(!block_on(solution._validate(&prefix, difficulty)));
+        assert!(!solution.validate(&prefix, difficulty));
     }
 
     #[test]
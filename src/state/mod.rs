@@ -1,30 +1,156 @@
 use std::sync::Arc;
 
-use crate::{config::Config, storage::StorageProvider};
+use tokio::sync::Semaphore;
+
+use crate::{
+    audit::{AuditAction, AuditEntry, AuditLog, AuditOutcome},
+    auth::{BruteForceGuard, NonceCache, VerifiedKeyCache},
+    config::{Config, StorageTypeConfig},
+    metrics::Metrics,
+    pool::ChallengePools,
+    storage::StorageProvider,
+    webhook::Webhooks,
+};
 
 #[derive(Debug, Clone)]
 pub struct State(Arc<InnerState>);
 
 #[derive(Debug)]
 struct InnerState {
-    _config: Config,
+    config: Config,
     storage: StorageProvider,
+    verification_permits: Arc<Semaphore>,
+    webhooks: Webhooks,
+    verified_keys: VerifiedKeyCache,
+    rejected_keys: VerifiedKeyCache,
+    hash_permits: Arc<Semaphore>,
+    brute_force: BruteForceGuard,
+    nonces: NonceCache,
+    metrics: Metrics,
+    pools: ChallengePools,
+    audit: AuditLog,
 }
 
 impl State {
-    pub fn new(config: Config, storage: StorageProvider) -> State {
-        let inner = InnerState { _config: config, storage };
+    pub fn new(config: Config, storage: StorageProvider, audit: AuditLog) -> State {
+        Self::audit_config(&config, &storage, &audit);
+
+        let verification_permits =
+            Arc::new(Semaphore::new(config.get_verification().concurrency.get()));
+
+        let webhooks = Webhooks::new(config.get_webhooks());
+
+        let verified_keys = VerifiedKeyCache::new(
+            config.get_auth().get_cache_ttl(),
+            config.get_auth().cache_size,
+        );
+
+        let rejected_keys = VerifiedKeyCache::new(
+            config.get_auth().get_rejected_cache_ttl(),
+            config.get_auth().cache_size,
+        );
+
+        let hash_permits = Arc::new(Semaphore::new(config.get_auth().hash_concurrency.get()));
+
+        let brute_force = BruteForceGuard::new(config.get_auth().brute_force.clone());
+
+        let inner = InnerState {
+            config,
+            storage,
+            verification_permits,
+            webhooks,
+            verified_keys,
+            rejected_keys,
+            hash_permits,
+            brute_force,
+            nonces: NonceCache::default(),
+            metrics: Metrics::default(),
+            pools: ChallengePools::default(),
+            audit,
+        };
 
         let inner = Arc::new(inner);
 
         Self(inner)
     }
 
-    pub fn _get_config(&self) -> &Config {
-        &self.0._config
+    fn audit_config(config: &Config, storage: &StorageProvider, audit: &AuditLog) {
+        audit.record(AuditEntry::new(
+            "config",
+            AuditAction::ConfigLoaded,
+            AuditOutcome::Success,
+        ));
+
+        // Sites of a custom storage are not known up front, the config's are
+        // not served then
+        let sites = match (storage, config.get_storage()) {
+            (StorageProvider::Memory(_), Some(StorageTypeConfig::Memory(memory))) => {
+                memory.get_sites().as_slice()
+            }
+            _ => &[],
+        };
+
+        for site in sites {
+            let keys: Vec<&str> = site.get_api_keys().iter().map(|key| key.get_id()).collect();
+
+            audit.record(
+                AuditEntry::new("config", AuditAction::SiteLoaded, AuditOutcome::Success)
+                    .with_site(site.get_id())
+                    .with_detail(format!("api-keys: {}", keys.join(", "))),
+            );
+        }
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.0.config
     }
 
     pub async fn get_storage(&self) -> &StorageProvider {
         &self.0.storage
     }
+
+    /// Limits the solution sets verified at the same time
+    pub fn get_verification_permits(&self) -> &Arc<Semaphore> {
+        &self.0.verification_permits
+    }
+
+    pub fn get_webhooks(&self) -> &Webhooks {
+        &self.0.webhooks
+    }
+
+    pub fn get_verified_keys(&self) -> &VerifiedKeyCache {
+        &self.0.verified_keys
+    }
+
+    /// Keys recently found to match none of a site's `apiKeyHash`es
+    pub fn get_rejected_keys(&self) -> &VerifiedKeyCache {
+        &self.0.rejected_keys
+    }
+
+    /// Limits the argon2 verifications running at the same time
+    pub fn get_hash_permits(&self) -> &Arc<Semaphore> {
+        &self.0.hash_permits
+    }
+
+    pub fn get_brute_force(&self) -> &BruteForceGuard {
+        &self.0.brute_force
+    }
+
+    /// Nonces of signed requests
+    pub fn get_nonces(&self) -> &NonceCache {
+        &self.0.nonces
+    }
+
+    pub fn get_metrics(&self) -> &Metrics {
+        &self.0.metrics
+    }
+
+    /// Pre-generated challenges of sites with a `challengePool`
+    pub fn get_pools(&self) -> &ChallengePools {
+        &self.0.pools
+    }
+
+    pub fn get_audit(&self) -> &AuditLog {
+        &self.0.audit
+    }
 }
@@ -1,11 +1,11 @@
-use std::{error::Error, fmt::Display};
+use std::{error::Error, fmt::Display, future::Future};
 
 use uuid::Uuid;
 
 use crate::{challenge::Challenge, site::Site};
 
 mod storageprovider;
-pub use storageprovider::StorageProvider;
+pub use storageprovider::{MemoryStorage, StorageProvider};
 
 #[derive(Debug)]
 pub enum StorageError {
@@ -27,21 +27,25 @@ impl Display for StorageError {
 impl Error for StorageError {}
 
 pub trait Storage: Send + Sync {
-    async fn get_site(&self, id: &Uuid) -> Option<Site>;
+    fn get_site(&self, id: &Uuid) -> impl Future<Output = Option<Site>> + Send;
 
-    async fn get_challange(&self, id: &Uuid, site: &Site) -> Option<Challenge>;
+    fn get_challange(
+        &self,
+        id: &Uuid,
+        site: &Site,
+    ) -> impl Future<Output = Option<Challenge>> + Send;
 
-    async fn store_challenge(
+    fn store_challenge(
         &self,
         site: &Site,
         challenge: &Challenge,
-    ) -> Result<(), StorageError>;
+    ) -> impl Future<Output = Result<(), StorageError>> + Send;
 
-    async fn delete_challenge(
+    fn delete_challenge(
         &self,
         site: &Site,
         challenge: &Challenge,
-    ) -> Result<(), StorageError>;
+    ) -> impl Future<Output = Result<(), StorageError>> + Send;
 
-    async fn healthy(&self) -> bool;
+    fn healthy(&self) -> impl Future<Output = bool> + Send;
 }
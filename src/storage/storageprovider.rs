@@ -1,25 +1,32 @@
+mod custom;
 mod memory;
 
 use std::time::Duration;
 
+pub use custom::CustomStorage;
 pub use memory::MemoryStorage;
 
 use crate::{challenge::Challenge, config::Config, site::Site};
 
 use super::Storage;
+use tracing::instrument;
 
 #[derive(Debug, Clone)]
 pub enum StorageProvider {
     Memory(MemoryStorage),
+    Custom(CustomStorage),
 }
 
 impl Storage for StorageProvider {
+    #[instrument(name = "storage.get_site", skip_all)]
     async fn get_site(&self, id: &uuid::Uuid) -> Option<crate::site::Site> {
         match self {
             StorageProvider::Memory(memory_storage) => memory_storage.get_site(id).await,
+            StorageProvider::Custom(custom_storage) => custom_storage.get_site(id).await,
         }
     }
 
+    #[instrument(name = "storage.get_challange", skip_all)]
     async fn get_challange(
         &self,
         id: &uuid::Uuid,
@@ -27,9 +34,11 @@ impl Storage for StorageProvider {
     ) -> Option<Challenge> {
         match self {
             StorageProvider::Memory(memory_storage) => memory_storage.get_challange(id, site).await,
+            StorageProvider::Custom(custom_storage) => custom_storage.get_challange(id, site).await,
         }
     }
 
+    #[instrument(name = "storage.store_challenge", skip_all)]
     async fn store_challenge(
         &self,
         site: &Site,
@@ -42,9 +51,13 @@ impl Storage for StorageProvider {
                     challenge
                 ).await
             }
+            StorageProvider::Custom(custom_storage) => {
+                custom_storage.store_challenge(site, challenge).await
+            }
         }
     }
 
+    #[instrument(name = "storage.delete_challenge", skip_all)]
     async fn delete_challenge(
         &self,
         site: &Site,
@@ -57,31 +70,43 @@ impl Storage for StorageProvider {
                     challenge
                 ).await
             }
+            StorageProvider::Custom(custom_storage) => {
+                custom_storage.delete_challenge(site, challenge).await
+            }
         }
     }
 
+    #[instrument(name = "storage.healthy", skip_all)]
     async fn healthy(&self) -> bool {
         match self {
             StorageProvider::Memory(memory_storage) => {
                 memory_storage.healthy().await
             },
+            StorageProvider::Custom(custom_storage) => custom_storage.healthy().await,
         }
     }
 }
 
 impl StorageProvider {
-    pub fn new(config: &Config) -> Self {
-        let config = config.get_storage();
+    pub fn custom(storage: impl Storage + 'static) -> Self {
+        StorageProvider::Custom(CustomStorage::new(storage))
+    }
+
+    /// The storage described by the config, which needs a `storage` section
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let config = config
+            .get_storage()
+            .ok_or_else(|| anyhow::anyhow!("No storage configured"))?;
 
         match config {
             crate::config::StorageTypeConfig::Memory(in_memory_config) => {
                 let house_config = in_memory_config.get_house_keeping();
                 let duration = Duration::from(house_config.interval);
-                StorageProvider::Memory(MemoryStorage::new(
+                Ok(StorageProvider::Memory(MemoryStorage::new(
                     in_memory_config.get_sites(),
                     duration,
                     house_config.batch_size,
-                ))
+                )))
             }
         }
     }
@@ -0,0 +1,111 @@
+use std::{fmt, sync::Arc};
+
+use futures::future::BoxFuture;
+use uuid::Uuid;
+
+use crate::{
+    challenge::Challenge,
+    site::Site,
+    storage::{Storage, StorageError},
+};
+
+trait DynStorage: Send + Sync {
+    fn get_site<'a>(&'a self, id: &'a Uuid) -> BoxFuture<'a, Option<Site>>;
+
+    fn get_challange<'a>(&'a self, id: &'a Uuid, site: &'a Site)
+        -> BoxFuture<'a, Option<Challenge>>;
+
+    fn store_challenge<'a>(
+        &'a self,
+        site: &'a Site,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<(), StorageError>>;
+
+    fn delete_challenge<'a>(
+        &'a self,
+        site: &'a Site,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<(), StorageError>>;
+
+    fn healthy(&self) -> BoxFuture<'_, bool>;
+}
+
+impl<T: Storage> DynStorage for T {
+    fn get_site<'a>(&'a self, id: &'a Uuid) -> BoxFuture<'a, Option<Site>> {
+        Box::pin(Storage::get_site(self, id))
+    }
+
+    fn get_challange<'a>(
+        &'a self,
+        id: &'a Uuid,
+        site: &'a Site,
+    ) -> BoxFuture<'a, Option<Challenge>> {
+        Box::pin(Storage::get_challange(self, id, site))
+    }
+
+    fn store_challenge<'a>(
+        &'a self,
+        site: &'a Site,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        Box::pin(Storage::store_challenge(self, site, challenge))
+    }
+
+    fn delete_challenge<'a>(
+        &'a self,
+        site: &'a Site,
+        challenge: &'a Challenge,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        Box::pin(Storage::delete_challenge(self, site, challenge))
+    }
+
+    fn healthy(&self) -> BoxFuture<'_, bool> {
+        Box::pin(Storage::healthy(self))
+    }
+}
+
+/// Type erased, user supplied [`Storage`] implementation
+#[derive(Clone)]
+pub struct CustomStorage(Arc<dyn DynStorage>);
+
+impl CustomStorage {
+    pub fn new(storage: impl Storage + 'static) -> Self {
+        Self(Arc::new(storage))
+    }
+}
+
+impl fmt::Debug for CustomStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomStorage")
+    }
+}
+
+impl Storage for CustomStorage {
+    async fn get_site(&self, id: &Uuid) -> Option<Site> {
+        self.0.get_site(id).await
+    }
+
+    async fn get_challange(&self, id: &Uuid, site: &Site) -> Option<Challenge> {
+        self.0.get_challange(id, site).await
+    }
+
+    async fn store_challenge(
+        &self,
+        site: &Site,
+        challenge: &Challenge,
+    ) -> Result<(), StorageError> {
+        self.0.store_challenge(site, challenge).await
+    }
+
+    async fn delete_challenge(
+        &self,
+        site: &Site,
+        challenge: &Challenge,
+    ) -> Result<(), StorageError> {
+        self.0.delete_challenge(site, challenge).await
+    }
+
+    async fn healthy(&self) -> bool {
+        self.0.healthy().await
+    }
+}
@@ -151,6 +151,6 @@ impl Storage for MemoryStorage {
     async fn healthy(&self) -> bool {
         let _lock = self.inner.lock().await;
 
-        return true;
+        true
     }
 }
@@ -0,0 +1,69 @@
+//! Fixtures shared by the test modules
+
+use std::time::Duration;
+
+use axum::{response::Response, Router};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use uuid::{uuid, Uuid};
+
+use crate::{
+    challenge::Prefix,
+    config::{Config, HousekeepingConfig, InMemoryConfig, StorageTypeConfig},
+    site::Site,
+    solution::Solution,
+    RouterBuilder,
+};
+
+pub(crate) const SITE_ID: Uuid = uuid!("60601796-7dc2-4d4f-afae-5728592bba6f");
+
+/// Difficulty of the sites returned by [`site_with_id`]
+pub(crate) const DIFFICULTY: u8 = 4;
+
+/// Site with api-key `key`, 2 of 4 prefixes to solve
+pub(crate) fn site() -> Site {
+    site_with_id(SITE_ID)
+}
+
+pub(crate) fn site_with_id(id: Uuid) -> Site {
+    Site::new(id, "key".to_string(), 4, 8, 2, DIFFICULTY, 8, Duration::from_secs(60))
+}
+
+/// Config storing `sites` in memory
+pub(crate) fn config(sites: Vec<Site>) -> Config {
+    let housekeeping = HousekeepingConfig::new(Duration::from_secs(10), 10);
+
+    Config::new(StorageTypeConfig::Memory(InMemoryConfig::new(housekeeping, sites)))
+}
+
+/// Router with the default route groups serving `site`
+pub(crate) fn router(site: Site) -> Router {
+    RouterBuilder::new(config(vec![site]))
+        .build()
+        .expect("Unable to build router")
+}
+
+pub(crate) async fn body_json(response: Response) -> serde_json::Value {
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    serde_json::from_slice(&body).expect("Body is not json")
+}
+
+/// Brute forces a solution of `difficulty` for `prefix`
+pub(crate) fn solve(prefix: &Prefix, difficulty: u8) -> Solution {
+    (0u64..)
+        .map(|counter| Solution::new(Bytes::copy_from_slice(&counter.to_le_bytes())))
+        .find(|solution| solution.validate(prefix, difficulty))
+        .unwrap()
+}
+
+/// [`solve`] for a base64 prefix of a json challenge, the solution is base64
+/// as well
+pub(crate) fn solve_json(prefix: &serde_json::Value, difficulty: u8) -> serde_json::Value {
+    let prefix = Prefix::new(Bytes::from(
+        BASE64_STANDARD.decode(prefix.as_str().unwrap()).unwrap(),
+    ));
+
+    serde_json::to_value(solve(&prefix, difficulty)).unwrap()
+}
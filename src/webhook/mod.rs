@@ -0,0 +1,594 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use sha2::Sha256;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::{config::WebhookDeliveryConfig, site::Site};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const EVENT_HEADER: &str = "x-oxidecaptcha-event";
+pub const DELIVERY_HEADER: &str = "x-oxidecaptcha-delivery";
+/// `t=<unix seconds>,v1=<hex hmac-sha256 of "<t>.<body>">`
+pub const SIGNATURE_HEADER: &str = "x-oxidecaptcha-signature";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "challenge.issued")]
+    ChallengeIssued,
+    #[serde(rename = "challenge.solved")]
+    ChallengeSolved,
+    #[serde(rename = "challenge.failed")]
+    ChallengeFailed,
+    /// An expired challenge was presented for validation. Challenges removed
+    /// by the storage's housekeeping without being presented emit nothing.
+    #[serde(rename = "challenge.expired")]
+    ChallengeExpired,
+    #[serde(rename = "auth.failed")]
+    AuthFailed,
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let event = match self {
+            WebhookEvent::ChallengeIssued => "challenge.issued",
+            WebhookEvent::ChallengeSolved => "challenge.solved",
+            WebhookEvent::ChallengeFailed => "challenge.failed",
+            WebhookEvent::ChallengeExpired => "challenge.expired",
+            WebhookEvent::AuthFailed => "auth.failed",
+        };
+
+        f.write_str(event)
+    }
+}
+
+/// Webhook of a site, receiving the subscribed events or all of them if
+/// `events` is empty
+#[derive(Clone, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(deserialize_with = "deserialize_url")]
+    url: String,
+    secret: String,
+    #[serde(default)]
+    events: Vec<WebhookEvent>,
+}
+
+impl WebhookConfig {
+    pub fn new(url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: secret.into(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_events(mut self, events: impl IntoIterator<Item = WebhookEvent>) -> Self {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    pub fn get_url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn subscribes(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+impl fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("url", &self.url)
+            .field("secret", &"..")
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+fn deserialize_url<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let url = String::deserialize(deserializer)?;
+
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(de::Error::custom("webhook url must be http or https"));
+    }
+
+    Ok(url)
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    id: Uuid,
+    event: WebhookEvent,
+    #[serde(rename = "siteId")]
+    site_id: &'a Uuid,
+    #[serde(rename = "challengeId", skip_serializing_if = "Option::is_none")]
+    challenge_id: Option<&'a Uuid>,
+    #[serde(rename = "occurredAt")]
+    occurred_at: u64,
+}
+
+struct Delivery {
+    id: Uuid,
+    event: WebhookEvent,
+    url: String,
+    secret: String,
+    body: Bytes,
+}
+
+impl fmt::Debug for Delivery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Delivery")
+            .field("id", &self.id)
+            .field("event", &self.event)
+            .field("url", &self.url)
+            .field("secret", &"..")
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// Queues webhook deliveries for a background worker, emitting never blocks
+#[derive(Debug, Clone)]
+pub(crate) struct Webhooks {
+    sender: mpsc::Sender<Delivery>,
+}
+
+impl Webhooks {
+    /// Must be called from within a tokio runtime
+    pub(crate) fn new(config: &WebhookDeliveryConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+
+        tokio::spawn(worker(receiver, config.clone()));
+
+        Self { sender }
+    }
+
+    pub(crate) fn emit(&self, site: &Site, event: WebhookEvent, challenge_id: Option<&Uuid>) {
+        let webhooks = site.get_webhooks();
+
+        if !webhooks.iter().any(|webhook| webhook.subscribes(event)) {
+            return;
+        }
+
+        let id = Uuid::new_v4();
+
+        let payload = Payload {
+            id,
+            event,
+            site_id: site.get_id(),
+            challenge_id,
+            occurred_at: unix_time(),
+        };
+
+        let body: Bytes = match serde_json::to_vec(&payload) {
+            Ok(v) => v.into(),
+            Err(e) => {
+                warn!("Unable to serialize webhook payload: {}", e);
+                return;
+            }
+        };
+
+        for webhook in webhooks.iter().filter(|webhook| webhook.subscribes(event)) {
+            let delivery = Delivery {
+                id,
+                event,
+                url: webhook.url.clone(),
+                secret: webhook.secret.clone(),
+                body: body.clone(),
+            };
+
+            if self.sender.try_send(delivery).is_err() {
+                warn!(site_id = %site.get_id(), %event, "Webhook queue full, dropping event");
+            }
+        }
+    }
+}
+
+async fn worker(mut receiver: mpsc::Receiver<Delivery>, config: WebhookDeliveryConfig) {
+    let client = match reqwest::Client::builder()
+        .timeout(config.get_timeout())
+        .build()
+    {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Unable to create webhook client, webhooks are disabled: {}", e);
+            return;
+        }
+    };
+
+    let permits = Arc::new(Semaphore::new(config.concurrency.max(1)));
+    // Deliveries waiting for a retry, on top of the queue
+    let pending = Arc::new(Semaphore::new(config.queue_size.max(1)));
+
+    while let Some(delivery) = receiver.recv().await {
+        let Ok(pending) = pending.clone().acquire_owned().await else {
+            return;
+        };
+
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            return;
+        };
+
+        let client = client.clone();
+        let config = config.clone();
+        let permits = permits.clone();
+
+        tokio::spawn(async move {
+            deliver(&client, &config, &permits, permit, delivery).await;
+            drop(pending);
+        });
+    }
+}
+
+/// Attempts the delivery while holding a permit of `permits`, which is
+/// released during the backoff
+async fn deliver(
+    client: &reqwest::Client,
+    config: &WebhookDeliveryConfig,
+    permits: &Arc<Semaphore>,
+    permit: OwnedSemaphorePermit,
+    delivery: Delivery,
+) {
+    let max_attempts = config.max_attempts.get();
+    let mut backoff = config.get_initial_backoff();
+    let mut first = Some(permit);
+
+    for attempt in 1..=max_attempts {
+        let permit = match first.take() {
+            Some(permit) => permit,
+            None => match permits.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+        };
+
+        let timestamp = unix_time();
+
+        let response = client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, delivery.event.to_string())
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &delivery.body))
+            .body(delivery.body.clone())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                debug!(delivery = %delivery.id, attempt, "Webhook delivered");
+                return;
+            }
+            Ok(response) if !is_retryable(response.status()) => {
+                warn!(delivery = %delivery.id, status = %response.status(), "Webhook rejected");
+                return;
+            }
+            Ok(response) => {
+                debug!(delivery = %delivery.id, attempt, status = %response.status(), "Webhook failed")
+            }
+            Err(e) => debug!(delivery = %delivery.id, attempt, "Webhook failed: {}", e),
+        }
+
+        if attempt < max_attempts {
+            drop(permit);
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2).min(Duration::from_secs(300));
+        }
+    }
+
+    warn!(
+        delivery = %delivery.id,
+        url = %delivery.url,
+        "Giving up on webhook after {} attempts",
+        max_attempts
+    );
+}
+
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Signature of a delivery, receivers recompute it over the raw body
+pub fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("Hmac accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+
+    format!("t={timestamp},v1={signature}")
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("Duration since failed")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use axum::{
+        body::{Body, Bytes},
+        extract::State,
+        http::{HeaderMap, Request, StatusCode},
+        routing::post,
+        Router,
+    };
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+
+    use crate::{
+        config::WebhookDeliveryConfig,
+        site::Site,
+        test_support::{config, SITE_ID},
+        RouterBuilder,
+    };
+
+    use super::{
+        sign, Delivery, WebhookConfig, WebhookEvent, DELIVERY_HEADER, EVENT_HEADER,
+        SIGNATURE_HEADER,
+    };
+
+    const SECRET: &str = "webhook-secret";
+
+    type Received = mpsc::UnboundedSender<(HeaderMap, Bytes)>;
+
+    /// Answers with 500 for the first `failures` requests
+    async fn spawn_receiver(failures: usize) -> (String, mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let failures = Arc::new(AtomicUsize::new(failures));
+
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State((sender, failures)): State<(Received, Arc<AtomicUsize>)>,
+                     headers: HeaderMap,
+                     body: Bytes| async move {
+                        sender.send((headers, body)).unwrap();
+
+                        match failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |f| f.checked_sub(1)) {
+                            Ok(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                            Err(_) => StatusCode::NO_CONTENT,
+                        }
+                    },
+                ),
+            )
+            .with_state((sender, failures));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Unable to bind receiver");
+        let address = listener.local_addr().expect("No local address");
+
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (format!("http://{address}/hook"), receiver)
+    }
+
+    fn router(webhook: WebhookConfig) -> Router {
+        router_with_lifetime(webhook, Duration::from_secs(60))
+    }
+
+    fn router_with_lifetime(webhook: WebhookConfig, lifetime: Duration) -> Router {
+        let site = Site::new(SITE_ID, "key".to_string(), 4, 8, 2, 4, 8, lifetime)
+            .with_webhook(webhook);
+
+        let delivery = WebhookDeliveryConfig {
+            initial_backoff: Duration::from_millis(10).into(),
+            ..WebhookDeliveryConfig::default()
+        };
+
+        RouterBuilder::new(config(vec![site]).with_webhooks(delivery))
+            .build()
+            .expect("Unable to build router")
+    }
+
+    async fn next(receiver: &mut mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) -> (HeaderMap, serde_json::Value, Bytes) {
+        let (headers, body) = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("No webhook received")
+            .expect("Receiver closed");
+
+        let json = serde_json::from_slice(&body).expect("Payload is not json");
+
+        (headers, json, body)
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", 1700000000, br#"{"a":1}"#),
+            "t=1700000000,v1=49f24e537407743fa4a0242bb63b94b9a47ee99cbbe071ccd8a22550ae411686"
+        );
+    }
+
+    #[test]
+    fn test_subscribes() {
+        let all = WebhookConfig::new("http://localhost/hook", SECRET);
+        let auth_only = all.clone().with_events([WebhookEvent::AuthFailed]);
+
+        assert!(all.subscribes(WebhookEvent::ChallengeIssued));
+        assert!(auth_only.subscribes(WebhookEvent::AuthFailed));
+        assert!(!auth_only.subscribes(WebhookEvent::ChallengeIssued));
+    }
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let webhook = WebhookConfig::new("http://localhost/hook", SECRET);
+        let delivery = Delivery {
+            id: uuid::Uuid::new_v4(),
+            event: WebhookEvent::AuthFailed,
+            url: webhook.get_url().to_string(),
+            secret: SECRET.to_string(),
+            body: Bytes::new(),
+        };
+
+        for debug in [format!("{webhook:?}"), format!("{delivery:?}")] {
+            assert!(debug.contains("http://localhost/hook"), "{debug}");
+            assert!(!debug.contains(SECRET), "{debug}");
+        }
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let testee = r#"{ "url": "https://example.com/hook", "secret": "s", "events": ["challenge.solved", "auth.failed"] }"#;
+
+        let webhook: WebhookConfig = serde_json::from_str(testee).expect("Failed parsing json");
+
+        assert!(webhook.subscribes(WebhookEvent::ChallengeSolved));
+        assert!(!webhook.subscribes(WebhookEvent::ChallengeIssued));
+
+        serde_json::from_str::<WebhookConfig>(r#"{ "url": "ftp://example.com", "secret": "s" }"#)
+            .expect_err("Accepted ftp url");
+    }
+
+    #[tokio::test]
+    async fn test_signed_events() {
+        let (url, mut receiver) = spawn_receiver(0).await;
+        let router = router(WebhookConfig::new(url, SECRET));
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        router.clone().oneshot(request).await.unwrap();
+
+        let (headers, json, body) = next(&mut receiver).await;
+
+        assert_eq!(headers[EVENT_HEADER], "challenge.issued");
+        assert_eq!(json["event"], "challenge.issued");
+        assert_eq!(json["siteId"], SITE_ID.to_string());
+        assert_eq!(json["id"].as_str().unwrap(), headers[DELIVERY_HEADER]);
+
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        let timestamp: u64 = signature[2..signature.find(',').unwrap()].parse().unwrap();
+        assert_eq!(signature, sign(SECRET, timestamp, &body));
+
+        let request = Request::post(format!("/v1/site/{SITE_ID}/challenge/{}", json["challengeId"].as_str().unwrap()))
+            .header("api-key", "wrong")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"solutions": [null, null, null, null]}"#))
+            .unwrap();
+        router.oneshot(request).await.unwrap();
+
+        let (_, json, _) = next(&mut receiver).await;
+
+        assert_eq!(json["event"], "auth.failed");
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let (url, mut receiver) = spawn_receiver(2).await;
+        let router = router(WebhookConfig::new(url, SECRET).with_events([WebhookEvent::ChallengeIssued]));
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        router.oneshot(request).await.unwrap();
+
+        let mut deliveries = Vec::new();
+        for _ in 0..3 {
+            let (headers, _, _) = next(&mut receiver).await;
+            deliveries.push(headers[DELIVERY_HEADER].clone());
+        }
+
+        assert!(deliveries.iter().all(|id| *id == deliveries[0]));
+    }
+
+    #[tokio::test]
+    async fn test_backoff_frees_slot() {
+        let (failing, mut failing_receiver) = spawn_receiver(usize::MAX).await;
+        let (healthy, mut healthy_receiver) = spawn_receiver(0).await;
+
+        let site = Site::new(SITE_ID, "key".to_string(), 4, 8, 2, 4, 8, Duration::from_secs(60))
+            .with_webhook(WebhookConfig::new(failing, SECRET))
+            .with_webhook(WebhookConfig::new(healthy, SECRET));
+
+        let delivery = WebhookDeliveryConfig {
+            concurrency: 1,
+            initial_backoff: Duration::from_secs(60).into(),
+            ..WebhookDeliveryConfig::default()
+        };
+
+        let router = RouterBuilder::new(config(vec![site]).with_webhooks(delivery))
+            .build()
+            .expect("Unable to build router");
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        router.oneshot(request).await.unwrap();
+
+        // The failing delivery waits for its retry without holding the only slot
+        next(&mut failing_receiver).await;
+        next(&mut healthy_receiver).await;
+    }
+
+    #[test]
+    fn test_max_attempts_required() {
+        let config = serde_json::from_str::<WebhookDeliveryConfig>(r#"{ "maxAttempts": 0 }"#);
+
+        assert!(config.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expired_event() {
+        let (url, mut receiver) = spawn_receiver(0).await;
+        let router = router_with_lifetime(
+            WebhookConfig::new(url, SECRET).with_events([WebhookEvent::ChallengeExpired]),
+            Duration::ZERO,
+        );
+
+        let request = Request::get(format!("/v1/site/{SITE_ID}/challenge"))
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let body = http_body_util::BodyExt::collect(response.into_body()).await.unwrap().to_bytes();
+        let challenge: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let request = Request::post(format!("/v1/site/{SITE_ID}/challenge/{}", challenge["id"].as_str().unwrap()))
+            .header("api-key", "key")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"solutions": [null, null, null, null]}"#))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+
+        let (_, json, _) = next(&mut receiver).await;
+
+        assert_eq!(json["event"], "challenge.expired");
+        assert_eq!(json["challengeId"], challenge["id"]);
+    }
+}
@@ -0,0 +1,58 @@
+use axum::Router;
+use tower_http::services::ServeDir;
+
+use crate::config::WidgetConfig;
+
+pub fn router(config: &WidgetConfig) -> Router {
+    Router::new().nest_service("/widget", ServeDir::new(&config.directory))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use crate::config::WidgetConfig;
+
+    #[tokio::test]
+    async fn test_serves_assets() {
+        let directory =
+            std::env::temp_dir().join(format!("oxidecaptcha-widget-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(directory.join("pkg")).unwrap();
+        std::fs::write(directory.join("oxidecaptcha.js"), "export {};").unwrap();
+        std::fs::write(directory.join("pkg/oxidecaptcha_wasm_bg.wasm"), b"\0asm").unwrap();
+
+        let router = super::router(&WidgetConfig {
+            directory: directory.clone(),
+        });
+
+        let request = Request::get("/widget/oxidecaptcha.js")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/javascript");
+        assert_eq!(
+            &response.into_body().collect().await.unwrap().to_bytes()[..],
+            b"export {};"
+        );
+
+        let request = Request::get("/widget/pkg/oxidecaptcha_wasm_bg.wasm")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()["content-type"], "application/wasm");
+
+        let request = Request::get("/widget/missing.js")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), 404);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,294 @@
+use std::convert::Infallible;
+
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        request::Parts,
+        HeaderMap, HeaderValue,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+use crate::error_response::{ErrorId, ErrorResponse};
+
+pub const CBOR: &str = "application/cbor";
+pub const MSGPACK: &str = "application/msgpack";
+
+/// Media types accepted for MessagePack, there is no registered one
+const MSGPACK_ALIASES: [&str; 3] = [MSGPACK, "application/x-msgpack", "application/vnd.msgpack"];
+
+/// Encoding of request and response bodies. The binary formats carry
+/// prefixes and solutions as raw byte strings instead of base64, ids are
+/// strings in every format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl WireFormat {
+    /// Parses a media type, ignoring parameters like `charset`
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let essence = media_type.split(';').next()?.trim().to_ascii_lowercase();
+
+        match essence.as_str() {
+            CBOR => Some(Self::Cbor),
+            essence if MSGPACK_ALIASES.contains(&essence) => Some(Self::MessagePack),
+            "application/json" => Some(Self::Json),
+            essence if essence.starts_with("application/") && essence.ends_with("+json") => {
+                Some(Self::Json)
+            }
+            _ => None,
+        }
+    }
+
+    /// The supported format with the highest `q` in `Accept`, binary formats
+    /// win ties with json. Json if nothing supported is acceptable.
+    pub fn from_accept(headers: &HeaderMap) -> Self {
+        let mut best: Option<(Self, f32)> = None;
+
+        for (media_type, quality) in accepted_media_types(headers) {
+            let Some(format) = Self::from_media_type(&media_type) else {
+                continue;
+            };
+
+            let better = match best {
+                Some((best, best_quality)) => {
+                    quality > best_quality
+                        || (quality == best_quality && best == Self::Json && format != Self::Json)
+                }
+                None => true,
+            };
+
+            if quality > 0.0 && better {
+                best = Some((format, quality));
+            }
+        }
+
+        best.map(|(format, _)| format).unwrap_or(Self::Json)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Cbor => CBOR,
+            Self::MessagePack => MSGPACK,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> anyhow::Result<Vec<u8>> {
+        let encoded = match self {
+            Self::Json => serde_json::to_vec(value)?,
+            Self::Cbor => {
+                let mut encoded = Vec::new();
+                ciborium::into_writer(value, &mut encoded)?;
+                encoded
+            }
+            Self::MessagePack => rmp_serde::to_vec_named(value)?,
+        };
+
+        Ok(encoded)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> anyhow::Result<T> {
+        let decoded = match self {
+            Self::Json => serde_json::from_slice(bytes)?,
+            Self::Cbor => ciborium::from_reader(bytes)?,
+            Self::MessagePack => rmp_serde::from_slice(bytes)?,
+        };
+
+        Ok(decoded)
+    }
+
+    /// Encodes `value` as the body of a `200` response
+    pub fn render<T: Serialize>(&self, value: &T) -> Response {
+        match self.encode(value) {
+            Ok(body) => {
+                let mut response = Response::new(Body::from(body));
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static(self.content_type()));
+                response
+            }
+            Err(_) => ErrorResponse::new(ErrorId::InternalServerError, "Unable to encode response")
+                .into_response(),
+        }
+    }
+}
+
+/// Media types listed in `Accept`, lowercased and without parameters, along
+/// with their `q`. Entries with an invalid `q` are left out.
+pub(crate) fn accepted_media_types(headers: &HeaderMap) -> impl Iterator<Item = (String, f32)> + '_ {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let media_type = parts.next()?.trim().to_ascii_lowercase();
+
+            if media_type.is_empty() {
+                return None;
+            }
+
+            let mut quality = 1.0;
+
+            for parameter in parts {
+                let Some((name, value)) = parameter.split_once('=') else {
+                    continue;
+                };
+
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+                }
+            }
+
+            Some((media_type, quality))
+        })
+}
+
+/// Serializes an id as a string. Uuid writes 16 raw bytes in binary formats,
+/// which would not match the documented schema.
+pub(crate) fn serialize_id<S: Serializer>(id: &Uuid, serializer: S) -> Result<S::Ok, S::Error> {
+    id.hyphenated().serialize(serializer)
+}
+
+pub(crate) fn serialize_optional_id<S: Serializer>(
+    id: &Option<Uuid>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    id.map(|id| id.hyphenated()).serialize(serializer)
+}
+
+/// Counterpart of [`serialize_id`]
+pub(crate) fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+    let id = String::deserialize(deserializer)?;
+
+    Uuid::parse_str(&id).map_err(serde::de::Error::custom)
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for WireFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_accept(&parts.headers))
+    }
+}
+
+/// Like [`axum::Json`], but decodes the body according to `Content-Type`
+#[derive(Debug)]
+pub struct Wire<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Wire<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ErrorResponse;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let format = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(WireFormat::from_media_type)
+            .ok_or_else(|| {
+                ErrorResponse::new(
+                    ErrorId::UnsupportedMediaType,
+                    format!("Expected a body of type application/json, {CBOR} or {MSGPACK}"),
+                )
+            })?;
+
+        let bytes = Bytes::from_request(request, state).await.map_err(|error| {
+            ErrorResponse::new(ErrorId::InvalidBody, format!("Unable to read body: {error}"))
+        })?;
+
+        let value = format.decode(&bytes).map_err(|error| {
+            ErrorResponse::new(ErrorId::InvalidBody, format!("Unable to decode body: {error}"))
+        })?;
+
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::ACCEPT, HeaderMap, HeaderValue};
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    use super::WireFormat;
+
+    #[test]
+    fn test_from_media_type() {
+        assert_eq!(WireFormat::from_media_type("application/cbor"), Some(WireFormat::Cbor));
+        assert_eq!(
+            WireFormat::from_media_type("application/x-msgpack"),
+            Some(WireFormat::MessagePack)
+        );
+        assert_eq!(
+            WireFormat::from_media_type("application/json; charset=utf-8"),
+            Some(WireFormat::Json)
+        );
+        assert_eq!(WireFormat::from_media_type("text/plain"), None);
+    }
+
+    #[test]
+    fn test_from_accept() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::Json);
+
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json, application/msgpack"));
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::MessagePack);
+
+        headers.insert(ACCEPT, HeaderValue::from_static("*/*"));
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::Json);
+
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/json, application/msgpack;q=0.1"),
+        );
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::Json);
+
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/cbor;q=0.5, application/msgpack;q=0.8"),
+        );
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::MessagePack);
+
+        headers.insert(ACCEPT, HeaderValue::from_static("application/cbor;q=0, text/html"));
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::Json);
+
+        headers.insert(ACCEPT, HeaderValue::from_static("application/cbor; Q=2, application/msgpack"));
+        assert_eq!(WireFormat::from_accept(&headers), WireFormat::MessagePack);
+    }
+
+    #[test]
+    fn test_ids_are_strings() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Item {
+            #[serde(serialize_with = "super::serialize_id", deserialize_with = "super::deserialize_id")]
+            id: Uuid,
+        }
+
+        let item = Item { id: Uuid::new_v4() };
+
+        for format in [WireFormat::Json, WireFormat::Cbor, WireFormat::MessagePack] {
+            let encoded = format.encode(&item).unwrap();
+
+            let generic: serde_json::Value = format.decode(&encoded).unwrap();
+            assert_eq!(generic["id"], item.id.to_string(), "{format:?}");
+
+            assert_eq!(format.decode::<Item>(&encoded).unwrap(), item);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// Embeddable oxidecaptcha widget.
+//
+//   <form>
+//     <div class="oxidecaptcha" data-site-id="..." data-api="https://captcha.example.com"></div>
+//   </form>
+//   <script type="module" src="https://captcha.example.com/widget/oxidecaptcha.js"></script>
+//
+// Once solved, the hidden field (`data-field`, default "oxidecaptcha") holds
+// `{"challengeId": ..., "solutions": [...]}`, to be forwarded by the backend
+// to `POST /v1/site/{siteId}/challenge/{challengeId}`.
+
+const WORKER_URL = new URL("./worker.js", import.meta.url);
+const STYLE = `
+.oxidecaptcha-box { display: inline-flex; flex-direction: column; gap: .5rem; padding: .75rem 1rem; border: 1px solid #ccc; border-radius: 4px; background: #fafafa; font: 14px system-ui, sans-serif; min-width: 16rem; }
+.oxidecaptcha-box label { display: flex; align-items: center; gap: .5rem; cursor: pointer; }
+.oxidecaptcha-box progress { width: 100%; }
+.oxidecaptcha-box[data-state="error"] .oxidecaptcha-status { color: #b00020; }
+`;
+
+const decode = (value) => Uint8Array.from(atob(value), (c) => c.charCodeAt(0));
+const encode = (bytes) => btoa(String.fromCharCode(...bytes));
+
+function injectStyle() {
+  if (document.getElementById("oxidecaptcha-style")) return;
+
+  const style = document.createElement("style");
+  style.id = "oxidecaptcha-style";
+  style.textContent = STYLE;
+  document.head.append(style);
+}
+
+// Expected number of hashes until a prefix is solved
+const expectedAttempts = (difficulty) => 2 ** difficulty;
+
+function solve(challenge, onProgress) {
+  const required = challenge.prefixesToSolve;
+  const workerCount = Math.max(1, Math.min(navigator.hardwareConcurrency || 2, challenge.prefixes.length));
+  const solutions = challenge.prefixes.map(() => null);
+  const attempts = new Map();
+  const queue = challenge.prefixes.map((prefix, index) => ({ index, prefix: decode(prefix) }));
+  const workers = [];
+  let solved = 0;
+  let running = 0;
+
+  const report = () => {
+    // Unsolved prefixes contribute their share of the expected work
+    const expected = expectedAttempts(challenge.difficulty);
+    const partial = [...attempts.values()].reduce((sum, a) => sum + Math.min(a / expected, 0.95), 0);
+    onProgress(Math.min((solved + partial) / required, 1));
+  };
+
+  return new Promise((resolve, reject) => {
+    const stop = () => workers.forEach((worker) => worker.terminate());
+
+    const next = (worker) => {
+      const job = queue.shift();
+
+      if (!job) {
+        // Nothing left to hand out and nothing running that could still succeed
+        if (running === 0 && solved < required) {
+          stop();
+          reject(new Error("unable to solve enough prefixes"));
+        }
+        return;
+      }
+
+      running++;
+      worker.postMessage({
+        index: job.index,
+        prefix: job.prefix,
+        difficulty: challenge.difficulty,
+        solutionLength: challenge.solutionLength,
+      });
+    };
+
+    for (let i = 0; i < workerCount; i++) {
+      const worker = new Worker(WORKER_URL, { type: "module" });
+
+      worker.onmessage = ({ data }) => {
+        if (data.type === "progress") {
+          attempts.set(data.index, data.attempts);
+          report();
+          return;
+        }
+
+        attempts.delete(data.index);
+        running--;
+
+        if (data.type === "exhausted") {
+          next(worker);
+          return;
+        }
+
+        solutions[data.index] = encode(data.solution);
+        solved++;
+        report();
+
+        if (solved >= required) {
+          stop();
+          resolve(solutions);
+        } else {
+          next(worker);
+        }
+      };
+
+      worker.onerror = (e) => {
+        stop();
+        reject(new Error(e.message || "worker failed"));
+      };
+
+      workers.push(worker);
+      next(worker);
+    }
+  });
+}
+
+function render(element) {
+  const siteId = element.dataset.siteId;
+  const api = (element.dataset.api || "").replace(/\/$/, "");
+  const fieldName = element.dataset.field || "oxidecaptcha";
+
+  injectStyle();
+
+  const box = document.createElement("div");
+  box.className = "oxidecaptcha-box";
+  box.dataset.state = "idle";
+
+  const label = document.createElement("label");
+  const checkbox = document.createElement("input");
+  checkbox.type = "checkbox";
+  label.append(checkbox, document.createTextNode("I am not a robot"));
+
+  const progress = document.createElement("progress");
+  progress.max = 1;
+  progress.value = 0;
+  progress.hidden = true;
+
+  const status = document.createElement("span");
+  status.className = "oxidecaptcha-status";
+
+  const field = document.createElement("input");
+  field.type = "hidden";
+  field.name = fieldName;
+
+  box.append(label, progress, status, field);
+  element.replaceChildren(box);
+
+  const fail = (message) => {
+    box.dataset.state = "error";
+    status.textContent = message;
+    checkbox.checked = false;
+    checkbox.disabled = false;
+    progress.hidden = true;
+  };
+
+  checkbox.addEventListener("change", async () => {
+    if (!checkbox.checked) return;
+
+    checkbox.disabled = true;
+    box.dataset.state = "solving";
+    status.textContent = "Verifying...";
+    progress.hidden = false;
+    progress.value = 0;
+
+    try {
+      const response = await fetch(`${api}/v1/site/${siteId}/challenge`, { cache: "no-store" });
+      if (!response.ok) throw new Error(`challenge request failed with ${response.status}`);
+
+      const challenge = await response.json();
+      const solutions = await solve(challenge, (value) => (progress.value = value));
+
+      field.value = JSON.stringify({ challengeId: challenge.id, solutions });
+      box.dataset.state = "solved";
+      status.textContent = "Verified";
+      progress.value = 1;
+
+      element.dispatchEvent(new CustomEvent("oxidecaptcha:solved", {
+        bubbles: true,
+        detail: { siteId, challengeId: challenge.id, solutions },
+      }));
+    } catch (e) {
+      fail(`Verification failed: ${e.message}`);
+    }
+  });
+}
+
+export function mount(root = document) {
+  root.querySelectorAll(".oxidecaptcha:not([data-mounted])").forEach((element) => {
+    element.dataset.mounted = "";
+    render(element);
+  });
+}
+
+if (document.readyState === "loading") {
+  document.addEventListener("DOMContentLoaded", () => mount());
+} else {
+  mount();
+}
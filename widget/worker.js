@@ -0,0 +1,37 @@
+// Solves prefixes handed out by the widget using the WebAssembly solver.
+// Messages in:  { index, prefix: Uint8Array, difficulty, solutionLength }
+// Messages out: { type: "progress", index, attempts }
+//               { type: "solved", index, solution: Uint8Array, attempts }
+//               { type: "exhausted", index, attempts }
+
+import init, { PrefixSolver } from "./pkg/oxidecaptcha_wasm.js";
+
+const BATCH = 4096;
+const ready = init();
+
+self.onmessage = async ({ data }) => {
+  await ready;
+
+  const { index, prefix, difficulty, solutionLength } = data;
+  const solver = new PrefixSolver(prefix, difficulty, solutionLength);
+
+  try {
+    for (;;) {
+      const solution = solver.step(BATCH);
+
+      if (solution !== undefined) {
+        self.postMessage({ type: "solved", index, solution, attempts: solver.attempts });
+        return;
+      }
+
+      if (solver.exhausted) {
+        self.postMessage({ type: "exhausted", index, attempts: solver.attempts });
+        return;
+      }
+
+      self.postMessage({ type: "progress", index, attempts: solver.attempts });
+    }
+  } finally {
+    solver.free();
+  }
+};